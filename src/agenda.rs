@@ -1,18 +1,204 @@
-use chrono::{Date, DateTime, Datelike, Duration, Month, NaiveDate, TimeZone, Utc};
+use chrono::{
+    DateTime, Datelike, Duration, Local, Month, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc,
+};
+use chrono_tz::Tz;
 use log;
 use num_traits::FromPrimitive;
-use std::ops::Bound::Included;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::ops::Bound::{Excluded, Included};
+use std::ops::{Range, RangeInclusive};
+use std::path::PathBuf;
 
 use crate::config::Config;
+use crate::history;
+use crate::provider::merged::MergedCalendar;
 use crate::provider::*;
+use crate::timing;
+
+/// Compact overview of a single day, e.g. for the month pane
+#[derive(Clone, Debug, Default)]
+pub struct DaySummary {
+    pub events: usize,
+    /// Time covered by timed events, overlapping events are counted once
+    pub busy: Duration,
+    pub has_allday: bool,
+    /// An event of an earlier day lasts into this one
+    pub continued: bool,
+    /// Categories of the day's events without duplicates
+    pub categories: Vec<String>,
+}
+
+/// Time until the nearest event boundary, see `Agenda::countdown`
+#[derive(Clone, Copy)]
+pub enum Countdown<'a> {
+    /// The event is ongoing and ends after the duration
+    Ends(CalendarEvent<'a>, Duration),
+    /// The event starts after the duration
+    Starts(CalendarEvent<'a>, Duration),
+}
+
+impl std::fmt::Display for Countdown<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (event, verb, remaining) = match self {
+            Countdown::Ends(event, remaining) => (event, "ends", remaining),
+            Countdown::Starts(event, remaining) => (event, "starts", remaining),
+        };
+
+        let seconds = remaining.num_seconds().max(0);
+        write!(f, "{} {} in ", event.summary(), verb)?;
+        if seconds >= 24 * 60 * 60 {
+            write!(f, "{}d ", seconds / (24 * 60 * 60))?;
+        }
+        write!(
+            f,
+            "{}:{:02}:{:02}",
+            seconds / (60 * 60) % 24,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
+
+/// Reasons to double-check an event before creating it, see
+/// `Agenda::check_new_event`
+pub struct NewEventWarnings<'a> {
+    pub in_past: bool,
+    /// Opaque timed events sharing some of the new event's time
+    pub overlapping: Vec<CalendarEvent<'a>>,
+}
+
+impl NewEventWarnings<'_> {
+    pub fn is_empty(&self) -> bool {
+        !self.in_past && self.overlapping.is_empty()
+    }
+}
+
+impl std::fmt::Display for NewEventWarnings<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut reasons = Vec::new();
+        if self.in_past {
+            reasons.push("begins in the past".to_owned());
+        }
+        match self.overlapping.as_slice() {
+            [] => {}
+            [event] => reasons.push(format!("overlaps '{}'", event.summary())),
+            events => reasons.push(format!("overlaps {} events", events.len())),
+        }
+        write!(f, "{}", reasons.join(" and "))
+    }
+}
+
+/// What an entry of the upcoming feed is about
+#[derive(Clone)]
+pub enum FeedItemKind<'a> {
+    /// The event begins
+    Event(CalendarEvent<'a>),
+    /// An alarm of the event fires
+    Alarm(CalendarEvent<'a>, Alarm),
+    /// An open task is due
+    Due(CalendarTodo<'a>),
+}
+
+/// An entry of the upcoming feed, see `Agenda::upcoming`
+#[derive(Clone)]
+pub struct FeedItem<'a> {
+    pub time: DateTime<Utc>,
+    pub kind: FeedItemKind<'a>,
+}
+
+/// Watching of the calendars by other instances is suspended while this
+/// lives, see `Agenda::suspend_watching`
+pub struct WatchSuspension {
+    markers: Vec<PathBuf>,
+}
+
+impl Drop for WatchSuspension {
+    fn drop(&mut self) {
+        for marker in &self.markers {
+            if let Err(e) = fs::remove_file(marker) {
+                log::warn!("Could not remove {}: {}", marker.display(), e);
+            }
+        }
+    }
+}
+
+pub type SubscriptionId = usize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// A change of an event within a subscribed range
+#[derive(Clone, Debug)]
+pub struct RangeChange {
+    pub kind: ChangeKind,
+    pub calendar: String,
+    pub uid: String,
+}
+
+/// Fingerprints of the events in a range, keyed by calendar id and UID
+type Snapshot = BTreeMap<(String, String), u64>;
+
+struct Subscription {
+    id: SubscriptionId,
+    range: Range<NaiveDateTime>,
+    snapshot: Snapshot,
+}
+
+/// An operation on a single event as part of a batch, see `Agenda::apply`
+#[derive(Clone, Debug)]
+pub enum EventOperation {
+    Remove,
+    /// Moves the event into the calendar with the given id
+    MoveTo(String),
+    Modify(EventChange),
+}
+
+/// The state of an event before a batch, `None` if it did not exist
+struct EventBackup {
+    calendar: String,
+    uid: String,
+    raw_ical: Option<String>,
+}
+
+struct UndoEntry {
+    /// Id of the batch in the history
+    batch: u64,
+    description: String,
+    backups: Vec<EventBackup>,
+}
+
+fn unknown_calendar(id: &str) -> Error {
+    Error::new(
+        ErrorKind::NotFound,
+        &format!("No calendar with id '{}'", id),
+    )
+}
 
 pub struct Agenda {
     collections: Vec<Box<dyn Collectionlike>>,
+    /// Merged calendars own their members, which are taken out of their
+    /// collections
+    merged: Vec<MergedCalendar>,
+    undo_stack: Vec<UndoEntry>,
+    subscriptions: Vec<Subscription>,
+    next_subscription_id: SubscriptionId,
+    /// Ids of calendars whose events are left out of views and searches
+    hidden: BTreeSet<String>,
 }
 
 impl Agenda {
     pub fn from_config(config: &Config) -> Result<Self> {
-        let collections: Vec<Box<dyn Collectionlike>> = config
+        let _span = timing::span("agenda_load", config.collections.len());
+
+        let mut collections: Vec<Box<dyn Collectionlike>> = config
             .collections
             .iter()
             .map(|collection_spec| {
@@ -31,26 +217,694 @@ impl Agenda {
             .map(|calendar| -> Box<dyn Collectionlike> { Box::new(calendar) })
             .collect();
 
-        Ok(Agenda { collections })
+        let merged = config
+            .merged
+            .iter()
+            .filter_map(|spec| {
+                let members = spec
+                    .calendars
+                    .iter()
+                    .filter_map(|id| {
+                        let member = collections
+                            .iter_mut()
+                            .find_map(|collection| collection.take_calendar(id));
+                        if member.is_none() {
+                            log::warn!(
+                                "Calendar '{}' of merged calendar '{}' not found",
+                                id,
+                                spec.id
+                            );
+                        }
+                        member
+                    })
+                    .collect();
+
+                MergedCalendar::new(spec, members)
+                    .map_err(|e| log::warn!("{}", e))
+                    .ok()
+            })
+            .collect();
+
+        Ok(Agenda {
+            collections,
+            merged,
+            undo_stack: Vec::new(),
+            subscriptions: Vec::new(),
+            next_subscription_id: 0,
+            hidden: BTreeSet::new(),
+        })
+    }
+
+    fn snapshot(&self, range: &Range<NaiveDateTime>) -> Snapshot {
+        self.filter_events(EventFilter::default().datetime_range(range.clone()))
+            .map(|event| {
+                let mut hasher = DefaultHasher::new();
+                event.raw_ical().hash(&mut hasher);
+                (
+                    (event.calendar.id().to_owned(), event.uid().to_owned()),
+                    hasher.finish(),
+                )
+            })
+            .collect()
+    }
+
+    /// Registers interest in the events beginning within `range`. Changes in
+    /// that range are reported by `refresh`.
+    pub fn subscribe(&mut self, range: Range<NaiveDateTime>) -> SubscriptionId {
+        let id = self.next_subscription_id;
+        self.next_subscription_id += 1;
+
+        let snapshot = self.snapshot(&range);
+        self.subscriptions.push(Subscription {
+            id,
+            range,
+            snapshot,
+        });
+        id
+    }
+
+    /// Moves a subscription to another range without reporting the events
+    /// of the new range as changes
+    pub fn set_subscription_range(&mut self, id: SubscriptionId, range: Range<NaiveDateTime>) {
+        let snapshot = self.snapshot(&range);
+        if let Some(subscription) = self
+            .subscriptions
+            .iter_mut()
+            .find(|s| s.id == id && s.range != range)
+        {
+            subscription.range = range;
+            subscription.snapshot = snapshot;
+        }
+    }
+
+    /// Moves events that ended before `before` into the archives of their
+    /// calendars. Returns the number of archived events by calendar id.
+    pub fn archive(&mut self, before: DateTime<Utc>) -> Result<Vec<(String, usize)>> {
+        let mut archived = Vec::new();
+        for calendar in self.calendar_iter_mut() {
+            archived.push((calendar.id().to_owned(), calendar.archive(before)?));
+        }
+        Ok(archived)
+    }
+
+    /// Loads the archived events of all calendars, e.g. to search them
+    pub fn load_archive(&mut self) {
+        for calendar in self.calendar_iter_mut() {
+            if let Err(e) = calendar.load_archive() {
+                log::warn!("Could not load the archive of '{}': {}", calendar.id(), e);
+            }
+        }
+    }
+
+    /// Keeps other instances (the TUI, `jk agenda --watch`) from reloading
+    /// the calendars for every single file written by a bulk operation. They
+    /// rescan once the returned guard is dropped.
+    pub fn suspend_watching(&self) -> Result<WatchSuspension> {
+        let mut suspension = WatchSuspension {
+            markers: Vec::new(),
+        };
+        for calendar in self.calendar_iter() {
+            suspension.markers.extend(calendar.suspend_watching()?);
+        }
+        Ok(suspension)
+    }
+
+    /// Reloads calendars which changed on disk and returns the changes of
+    /// all subscribed ranges
+    pub fn refresh(&mut self) -> Vec<(SubscriptionId, RangeChange)> {
+        let mut any_reloaded = false;
+        for collection in &mut self.collections {
+            any_reloaded |= collection.load_missing();
+        }
+        for calendar in self.calendar_iter_mut() {
+            match calendar.reload() {
+                Ok(reloaded) => any_reloaded |= reloaded,
+                Err(e) => log::warn!("Could not reload '{}': {}", calendar.id(), e),
+            }
+        }
+
+        if !any_reloaded {
+            return Vec::new();
+        }
+
+        let mut changes = Vec::new();
+        let snapshots: Vec<Snapshot> = self
+            .subscriptions
+            .iter()
+            .map(|subscription| self.snapshot(&subscription.range))
+            .collect();
+
+        for (subscription, current) in self.subscriptions.iter_mut().zip(snapshots) {
+            let previous = std::mem::replace(&mut subscription.snapshot, current);
+            let current = &subscription.snapshot;
+
+            for key in previous
+                .keys()
+                .chain(current.keys().filter(|k| !previous.contains_key(*k)))
+            {
+                let kind = match (previous.get(key), current.get(key)) {
+                    (None, Some(_)) => ChangeKind::Added,
+                    (Some(_), None) => ChangeKind::Removed,
+                    (Some(before), Some(after)) if before != after => ChangeKind::Modified,
+                    _ => continue,
+                };
+                changes.push((
+                    subscription.id,
+                    RangeChange {
+                        kind,
+                        calendar: key.0.clone(),
+                        uid: key.1.clone(),
+                    },
+                ));
+            }
+        }
+
+        changes
+    }
+
+    pub fn calendar_iter(&self) -> impl Iterator<Item = &dyn Calendarlike> {
+        self.collections
+            .iter()
+            .flat_map(|collection| collection.calendar_iter())
+            .chain(self.merged.iter().map(|c| c as &dyn Calendarlike))
+    }
+
+    /// Calendars that are not hidden
+    fn shown_calendars(&self) -> impl Iterator<Item = &dyn Calendarlike> {
+        self.calendar_iter()
+            .filter(move |calendar| !self.hidden.contains(calendar.id()))
+    }
+
+    pub fn is_hidden(&self, calendar_id: &str) -> bool {
+        self.hidden.contains(calendar_id)
+    }
+
+    /// Hides or shows the events of `calendar_id` in views and searches.
+    /// Free time and conflicts are still computed from all calendars.
+    pub fn set_hidden(&mut self, calendar_id: &str, hidden: bool) -> Result<()> {
+        if self.calendar_by_id(calendar_id).is_none() {
+            return Err(unknown_calendar(calendar_id));
+        }
+        if hidden {
+            self.hidden.insert(calendar_id.to_owned());
+        } else {
+            self.hidden.remove(calendar_id);
+        }
+        Ok(())
+    }
+
+    pub fn calendar_iter_mut(&mut self) -> impl Iterator<Item = &mut (dyn Calendarlike + '_)> {
+        self.collections
+            .iter_mut()
+            .flat_map(|collection| collection.calendar_iter_mut())
+            .chain(self.merged.iter_mut().map(|c| c as &mut dyn Calendarlike))
+    }
+
+    fn backup(&self, calendar: &str, uid: &str) -> Result<EventBackup> {
+        let calendar = self
+            .calendar_by_id(calendar)
+            .ok_or_else(|| unknown_calendar(calendar))?;
+        // Tasks are restored like events
+        let raw_ical = calendar
+            .event_iter()
+            .find(|event| event.uid() == uid)
+            .map(|event| event.raw_ical())
+            .or_else(|| {
+                calendar
+                    .todo_iter()
+                    .find(|todo| todo.uid() == uid)
+                    .map(|todo| todo.raw_ical())
+            });
+
+        Ok(EventBackup {
+            calendar: calendar.id().to_owned(),
+            uid: uid.to_owned(),
+            raw_ical,
+        })
+    }
+
+    fn apply_operation(
+        &mut self,
+        calendar: &str,
+        event: &EventId,
+        operation: &EventOperation,
+        backups: &mut Vec<EventBackup>,
+    ) -> Result<()> {
+        let uid = event.uid.as_str();
+        let backup = self.backup(calendar, uid)?;
+        let raw_ical = backup.raw_ical.clone().ok_or_else(|| {
+            Error::new(ErrorKind::NotFound, &format!("No event with UID '{}'", uid))
+        })?;
+        backups.push(backup);
+
+        if let EventOperation::MoveTo(target) = operation {
+            backups.push(self.backup(target, uid)?);
+            self.calendar_by_id_mut(target)
+                .ok_or_else(|| unknown_calendar(target))?
+                .import_ics(&raw_ical)?;
+        }
+
+        let calendar = self
+            .calendar_by_id_mut(calendar)
+            .ok_or_else(|| unknown_calendar(calendar))?;
+        match operation {
+            EventOperation::Remove => calendar.remove_event(event),
+            // The whole file was copied, overrides do not move on their own
+            EventOperation::MoveTo(_) => calendar.remove_event(&EventId::series(uid)),
+            EventOperation::Modify(change) => calendar.modify_event(event, change),
+        }
+    }
+
+    /// Appends the changes of the events in `backups` since the backups
+    /// were taken to the history. Failing to do so does not undo them.
+    fn record_history(
+        &self,
+        batch: u64,
+        description: &str,
+        backups: &[EventBackup],
+        undoes: Option<u64>,
+    ) {
+        let entries: Vec<history::Entry> = backups
+            .iter()
+            .filter_map(|backup| {
+                let after = self.backup(&backup.calendar, &backup.uid).ok()?.raw_ical;
+                let entry = history::Entry::new(
+                    batch,
+                    description,
+                    &backup.calendar,
+                    &backup.uid,
+                    backup.raw_ical.clone(),
+                    after,
+                )?;
+                Some(match undoes {
+                    Some(undone) => entry.undoing(undone),
+                    None => entry,
+                })
+            })
+            .collect();
+
+        if let Err(err) = history::record(&entries) {
+            log::warn!("Could not record the change in the history: {}", err);
+        }
+    }
+
+    /// Records `backups` taken before a batch of changes as a single undo
+    /// entry and in the history
+    fn finish_batch(&mut self, description: &str, backups: Vec<EventBackup>) {
+        if backups.is_empty() {
+            return;
+        }
+
+        let batch = history::new_batch();
+        self.record_history(batch, description, &backups, None);
+        self.undo_stack.push(UndoEntry {
+            batch,
+            description: description.to_owned(),
+            backups,
+        });
+    }
+
+    /// Applies `operations` given as (calendar id, event, operation) and
+    /// records them as a single undo entry. Operations applied before a
+    /// failing one are kept and can be undone.
+    pub fn apply(
+        &mut self,
+        description: &str,
+        operations: &[(String, EventId, EventOperation)],
+    ) -> Result<()> {
+        let mut backups = Vec::new();
+        let mut result = Ok(());
+
+        for (calendar, event, operation) in operations {
+            result = self.apply_operation(calendar, event, operation, &mut backups);
+            if result.is_err() {
+                break;
+            }
+        }
+
+        self.finish_batch(description, backups);
+        result
+    }
+
+    /// Adds `event` to the first calendar and returns its UID
+    pub fn new_event(&mut self, event: NewEvent<Tz>) -> Result<String> {
+        let calendar_id = self
+            .calendar_iter()
+            .next()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "No calendar available"))?
+            .id()
+            .to_owned();
+        self.new_event_in(&calendar_id, event)
+    }
+
+    /// Adds `event` to the calendar `calendar_id` and returns its UID
+    pub fn new_event_in(&mut self, calendar_id: &str, event: NewEvent<Tz>) -> Result<String> {
+        let calendar = self
+            .calendar_by_id_mut(calendar_id)
+            .ok_or_else(|| unknown_calendar(calendar_id))?;
+        let uid = calendar.new_event(event)?;
+
+        let backup = EventBackup {
+            calendar: calendar_id.to_owned(),
+            uid: uid.clone(),
+            raw_ical: None,
+        };
+        self.finish_batch("added", vec![backup]);
+        Ok(uid)
+    }
+
+    /// Adds an all-day event lasting `days` to the first calendar
+    pub fn new_allday_event(
+        &mut self,
+        summary: &str,
+        days: &RangeInclusive<NaiveDate>,
+    ) -> Result<()> {
+        let calendar_id = self
+            .calendar_iter()
+            .next()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "No calendar available"))?
+            .id()
+            .to_owned();
+        self.new_allday_event_in(&calendar_id, summary, days)
+    }
+
+    /// Adds an all-day event lasting `days` to the calendar `calendar_id`
+    pub fn new_allday_event_in(
+        &mut self,
+        calendar_id: &str,
+        summary: &str,
+        days: &RangeInclusive<NaiveDate>,
+    ) -> Result<()> {
+        let ics = ical::allday_event_ics(summary, *days.start(), *days.end());
+        self.import_batch(calendar_id, &ics, "added").map(|_| ())
+    }
+
+    /// Imports the events of `ics` into the calendar `calendar_id` so that
+    /// the import can be undone
+    pub fn import_ics(&mut self, calendar_id: &str, ics: &str) -> Result<ImportSummary> {
+        self.import_batch(calendar_id, ics, "imported")
+    }
+
+    fn import_batch(
+        &mut self,
+        calendar_id: &str,
+        ics: &str,
+        description: &str,
+    ) -> Result<ImportSummary> {
+        let backups = ical::import::uids(ics)
+            .iter()
+            .map(|uid| self.backup(calendar_id, uid))
+            .collect::<Result<Vec<_>>>()?;
+
+        let summary = self
+            .calendar_by_id_mut(calendar_id)
+            .ok_or_else(|| unknown_calendar(calendar_id))?
+            .import_ics(ics);
+        self.finish_batch(description, backups);
+        summary
+    }
+
+    /// Reverts the last batch of changes and returns its description. Once
+    /// everything of this session is undone, batches of earlier sessions are
+    /// taken from the history.
+    pub fn undo(&mut self) -> Result<Option<String>> {
+        let entry = match self.undo_stack.pop() {
+            Some(entry) => entry,
+            None => {
+                let entries = history::last_undoable()
+                    .map_err(|err| Error::new(ErrorKind::Invalid, &err.to_string()))?;
+                // Other programs, e.g. a sync, may have changed the events
+                // since, which would be lost
+                for entry in &entries {
+                    let current = self.backup(&entry.calendar, &entry.uid)?.raw_ical;
+                    if entry.is_outdated(current.as_deref()) {
+                        return Err(Error::new(
+                            ErrorKind::Conflict,
+                            &format!(
+                                "Not undoing '{}', event '{}' changed since",
+                                entry.description, entry.uid
+                            ),
+                        ));
+                    }
+                }
+                match entries.first() {
+                    Some(first) => UndoEntry {
+                        batch: first.batch,
+                        description: first.description.clone(),
+                        backups: entries
+                            .iter()
+                            .map(|entry| EventBackup {
+                                calendar: entry.calendar.clone(),
+                                uid: entry.uid.clone(),
+                                raw_ical: entry.before.clone(),
+                            })
+                            .collect(),
+                    },
+                    None => return Ok(None),
+                }
+            }
+        };
+
+        let mut undone = Vec::new();
+        for backup in entry.backups.iter().rev() {
+            undone.push(self.backup(&backup.calendar, &backup.uid)?);
+            let calendar = self
+                .calendar_by_id_mut(&backup.calendar)
+                .ok_or_else(|| unknown_calendar(&backup.calendar))?;
+
+            match &backup.raw_ical {
+                Some(raw_ical) => {
+                    calendar.import_ics(raw_ical)?;
+                }
+                None => calendar.remove_event(&EventId::series(&backup.uid))?,
+            }
+        }
+
+        let description = format!("undo {}", entry.description);
+        self.record_history(
+            history::new_batch(),
+            &description,
+            &undone,
+            Some(entry.batch),
+        );
+        Ok(Some(entry.description))
+    }
+
+    pub fn event_by_uid(&self, uid: &str) -> Option<CalendarEvent<'_>> {
+        self.calendar_iter()
+            .flat_map(|calendar| {
+                calendar
+                    .event_iter()
+                    .map(move |event| CalendarEvent { calendar, event })
+            })
+            .find(|event| event.uid() == uid)
+    }
+
+    /// The first instance of the event with `uid` that begins at or after
+    /// `now`, the last one if all are over. Only instances known without
+    /// expanding the recurrence rule are considered.
+    pub fn next_occurrence<Tz: TimeZone>(
+        &self,
+        uid: &str,
+        now: &DateTime<Tz>,
+    ) -> Option<CalendarEvent<'_>> {
+        let now = now.with_timezone(&Utc);
+        let instances: Vec<_> = self
+            .calendar_iter()
+            .flat_map(|calendar| {
+                calendar
+                    .event_iter()
+                    .map(move |event| CalendarEvent { calendar, event })
+            })
+            .filter(|event| event.uid() == uid)
+            .collect();
+
+        let begin = |event: &CalendarEvent| event.begin().with_timezone(&Utc);
+        instances
+            .iter()
+            .filter(|event| begin(event) >= now)
+            .min_by_key(|event| begin(event))
+            .or_else(|| instances.iter().max_by_key(|event| begin(event)))
+            .copied()
+    }
+
+    /// Events of all calendars containing every word of `query` in their
+    /// summary, description or location, ignoring case, ordered by begin
+    pub fn search(&self, query: &str) -> Vec<CalendarEvent<'_>> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        if words.is_empty() {
+            return Vec::new();
+        }
+
+        let mut matches: Vec<_> = self
+            .shown_calendars()
+            .flat_map(|calendar| {
+                calendar
+                    .event_iter()
+                    .map(move |event| CalendarEvent { calendar, event })
+            })
+            .filter(|event| {
+                let text = [
+                    Some(event.summary().to_owned()),
+                    event.description(),
+                    event.location(),
+                ]
+                .iter()
+                .flatten()
+                .map(|text| text.to_lowercase())
+                .collect::<Vec<_>>()
+                .join("\n");
+                words.iter().all(|word| text.contains(word.as_str()))
+            })
+            .collect();
+        matches.sort_by_key(|event| event.begin());
+        matches
+    }
+
+    /// Duplicate UIDs of all calendars, keyed by calendar id
+    pub fn uid_conflicts(&self) -> Vec<(String, UidConflict)> {
+        self.calendar_iter()
+            .flat_map(|calendar| {
+                calendar
+                    .uid_conflicts()
+                    .into_iter()
+                    .map(move |conflict| (calendar.id().to_owned(), conflict))
+            })
+            .collect()
+    }
+
+    pub fn calendar_by_id<'a>(&'a self, id: &str) -> Option<&'a (dyn Calendarlike + 'a)> {
+        self.calendar_iter().find(|calendar| calendar.id() == id)
+    }
+
+    pub fn calendar_by_id_mut<'a>(
+        &'a mut self,
+        id: &str,
+    ) -> Option<&'a mut (dyn Calendarlike + 'a)> {
+        self.calendar_iter_mut()
+            .find(|calendar| calendar.id() == id)
     }
 
     pub fn events_of_month<'a>(
         &'a self,
         month: Month,
         year: i32,
-    ) -> impl Iterator<Item = &'a dyn Eventlike> + 'a {
+    ) -> impl Iterator<Item = CalendarEvent<'a>> + 'a {
         let begin = NaiveDate::from_ymd(year, month.number_from_month() as u32, 1).and_hms(0, 0, 0);
         let end = begin + Duration::days(days_of_month(&month, year) as i64);
 
-        self.collections
-            .iter()
-            .flat_map(|collection| collection.calendar_iter())
-            .flat_map(move |calendar| {
-                calendar.filter_events(EventFilter::default().datetime_range(begin..=end))
+        self.filter_events(EventFilter::default().datetime_range(begin..end))
+    }
+
+    /// Events overlapping any of `days`
+    pub fn events_of_days(
+        &self,
+        days: &RangeInclusive<NaiveDate>,
+    ) -> impl Iterator<Item = CalendarEvent<'_>> {
+        let begin = days.start().and_time(NaiveTime::MIN);
+        let end = days.end().and_time(NaiveTime::MIN) + Duration::days(1);
+
+        self.filter_events(EventFilter::default().datetime_range(begin..end))
+    }
+
+    /// Events covering any of `days`, unlike `events_of_days` including
+    /// those that begin earlier, see `Occurrence::days`
+    pub fn events_covering(
+        &self,
+        days: &RangeInclusive<NaiveDate>,
+    ) -> impl Iterator<Item = CalendarEvent<'_>> {
+        let days = days.clone();
+        let end = days.end().and_time(NaiveTime::MIN) + Duration::days(1);
+
+        self.filter_events(EventFilter::default().to_datetime(Excluded(end)))
+            .filter(move |event| {
+                // Skip the days of long past events quickly
+                event.end().date_naive() >= *days.start()
+                    && event.occurrence().days().any(|day| days.contains(&day))
             })
     }
 
-    pub fn events_of_current_month(&self) -> impl Iterator<Item = &dyn Eventlike> {
+    /// The events of `days` as a single iCalendar document and their number
+    pub fn export_days(&self, days: &RangeInclusive<NaiveDate>) -> Result<(String, usize)> {
+        // Instances of a recurring event share their file
+        let mut exported = BTreeSet::new();
+        let documents: Vec<String> = self
+            .events_of_days(days)
+            .filter(|event| exported.insert(event.path().to_owned()))
+            .map(|event| event.raw_ical())
+            .collect();
+        Ok((ical::calendar::combine_ics(&documents)?, documents.len()))
+    }
+
+    /// Summaries of all days of a month, indexed by day - 1, computed in a
+    /// single pass over the events of the month
+    pub fn month_summary(&self, month: Month, year: i32) -> Vec<DaySummary> {
+        match NaiveDate::from_ymd_opt(year, month.number_from_month(), 1) {
+            Some(first) => {
+                let last = first + Duration::days(days_of_month(&month, year) as i64 - 1);
+                self.day_summaries(&(first..=last))
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Summaries of `days`, indexed by the day's offset from the first one
+    pub fn day_summaries(&self, days: &RangeInclusive<NaiveDate>) -> Vec<DaySummary> {
+        let first = *days.start();
+        let num_days = usize::try_from((*days.end() - first).num_days() + 1).unwrap_or(0);
+
+        let mut summaries = vec![DaySummary::default(); num_days];
+        let mut busy_spans = vec![Vec::new(); num_days];
+
+        for event in self.events_covering(days) {
+            let categories = event.categories();
+            let occurrence = event.occurrence();
+            let first_day = occurrence.as_date();
+            let begin = occurrence.begin().naive_local();
+            let end = occurrence.end().naive_local();
+
+            for date in occurrence.days() {
+                let idx = match usize::try_from((date - first).num_days()) {
+                    Ok(idx) if idx < num_days => idx,
+                    _ => continue,
+                };
+
+                let summary = &mut summaries[idx];
+                summary.events += 1;
+                summary.continued |= date > first_day;
+                for category in &categories {
+                    if !summary.categories.contains(category) {
+                        summary.categories.push(category.clone());
+                    }
+                }
+
+                if occurrence.is_allday() {
+                    summary.has_allday = true;
+                } else {
+                    let day_begin = date.and_time(NaiveTime::MIN);
+                    let day_end = day_begin + Duration::days(1);
+                    busy_spans[idx].push((begin.max(day_begin), end.min(day_end)));
+                }
+            }
+        }
+
+        for (summary, mut spans) in summaries.iter_mut().zip(busy_spans) {
+            spans.sort_unstable();
+
+            let mut covered_until = None;
+            for (begin, end) in spans {
+                let begin = covered_until.map_or(begin, |until| begin.max(until));
+                if end > begin {
+                    summary.busy += end - begin;
+                    covered_until = Some(end);
+                }
+            }
+        }
+
+        summaries
+    }
+
+    pub fn events_of_current_month(&self) -> impl Iterator<Item = CalendarEvent<'_>> {
         let today = Utc::today();
         let curr_month = Month::from_u32(today.month()).unwrap();
         let curr_year = today.year();
@@ -58,19 +912,290 @@ impl Agenda {
         self.events_of_month(curr_month, curr_year)
     }
 
-    pub fn events_of_day(&self, date: &NaiveDate) -> impl Iterator<Item = &dyn Eventlike> {
+    pub fn events_of_day(&self, date: &NaiveDate) -> impl Iterator<Item = CalendarEvent<'_>> {
         let begin = date.and_hms(0, 0, 0);
         let end = begin + Duration::days(1);
 
-        self.collections
-            .iter()
-            .flat_map(|collection| collection.calendar_iter())
-            .flat_map(move |calendar| {
-                calendar.filter_events(EventFilter::default().datetime_range(begin..=end))
+        self.filter_events(EventFilter::default().datetime_range(begin..end))
+    }
+
+    /// Timed events whose span contains `now`, ordered by their end
+    pub fn ongoing_at<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Vec<CalendarEvent<'_>> {
+        let mut ongoing: Vec<CalendarEvent> = self
+            .shown_calendars()
+            .flat_map(|calendar| {
+                let local_now = now.with_timezone(calendar.tz());
+                calendar
+                    .filter_events(
+                        EventFilter::default().to_datetime(Included(local_now.naive_local())),
+                    )
+                    .filter(move |event| {
+                        !event.occurrence().is_allday()
+                            && event.begin() <= local_now
+                            && event.end() > local_now
+                    })
+                    .map(move |event| CalendarEvent { calendar, event })
+            })
+            .collect();
+
+        ongoing.sort_by_key(|event| event.end());
+        ongoing
+    }
+
+    /// Opaque timed events sharing time with `begin..end`. An empty span
+    /// overlaps the events that are ongoing at `begin`. Events of hidden
+    /// calendars count as well, hiding them does not free the time.
+    pub fn overlapping<Tz: TimeZone>(
+        &self,
+        begin: &DateTime<Tz>,
+        end: &DateTime<Tz>,
+    ) -> Vec<CalendarEvent<'_>> {
+        self.calendar_iter()
+            .flat_map(|calendar| {
+                let local_begin = begin.with_timezone(calendar.tz());
+                let local_end = end.with_timezone(calendar.tz());
+                calendar
+                    .filter_events(
+                        EventFilter::default().to_datetime(Included(local_end.naive_local())),
+                    )
+                    .filter(move |event| {
+                        !event.occurrence().is_allday()
+                            && event.is_opaque()
+                            && (event.begin() < local_end || event.begin() <= local_begin)
+                            && event.end() > local_begin
+                    })
+                    .map(move |event| CalendarEvent { calendar, event })
             })
+            .collect()
+    }
+
+    /// Gaps of at least `min_duration` in `range` between the events returned
+    /// by `overlapping`, i.e. the time nothing is scheduled for
+    pub fn free_slots<Tz: TimeZone>(
+        &self,
+        range: Range<DateTime<Tz>>,
+        min_duration: Duration,
+    ) -> Vec<Range<DateTime<Tz>>> {
+        let tz = range.start.timezone();
+        let mut busy: Vec<(DateTime<Tz>, DateTime<Tz>)> = self
+            .overlapping(&range.start, &range.end)
+            .into_iter()
+            .map(|event| {
+                (
+                    event.begin().with_timezone(&tz),
+                    event.end().with_timezone(&tz),
+                )
+            })
+            .collect();
+        busy.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let is_slot = |from: &DateTime<Tz>, to: &DateTime<Tz>| {
+            to > from && to.clone() - from.clone() >= min_duration
+        };
+        let mut slots = Vec::new();
+        let mut free_from = range.start.clone();
+        for (begin, end) in busy {
+            if is_slot(&free_from, &begin) {
+                slots.push(free_from.clone()..begin);
+            }
+            free_from = free_from.max(end);
+        }
+        if is_slot(&free_from, &range.end) {
+            slots.push(free_from..range.end);
+        }
+        slots
+    }
+
+    /// Checks whether `event` begins before `now` or overlaps existing events
+    pub fn check_new_event<Tz: TimeZone>(
+        &self,
+        event: &NewEvent<Tz>,
+        now: &DateTime<Tz>,
+    ) -> NewEventWarnings<'_> {
+        let end = event.end.as_ref().unwrap_or(&event.begin);
+        NewEventWarnings {
+            in_past: event.begin < *now,
+            overlapping: self.overlapping(&event.begin, end),
+        }
+    }
+
+    /// The first timed event of any calendar beginning after `now`
+    pub fn next_event_after<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Option<CalendarEvent<'_>> {
+        self.shown_calendars()
+            .filter_map(|calendar| {
+                let local_now = now.with_timezone(calendar.tz());
+                calendar
+                    .filter_events(
+                        EventFilter::default().from_datetime(Excluded(local_now.naive_local())),
+                    )
+                    .find(|event| !event.occurrence().is_allday() && event.begin() > local_now)
+                    .map(|event| CalendarEvent { calendar, event })
+            })
+            .min_by_key(|event| event.begin().with_timezone(&Utc))
+    }
+
+    /// Time until the earliest ongoing event ends or, if there is none, until
+    /// the next one starts
+    pub fn countdown<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Option<Countdown<'_>> {
+        let now = now.with_timezone(&Utc);
+        if let Some(event) = self.ongoing_at(&now).into_iter().next() {
+            let remaining = event.end().with_timezone(&Utc) - now;
+            return Some(Countdown::Ends(event, remaining));
+        }
+
+        self.next_event_after(&now).map(|event| {
+            let remaining = event.begin().with_timezone(&Utc) - now;
+            Countdown::Starts(event, remaining)
+        })
+    }
+
+    /// All pinned events, whatever their date, in chronological order
+    pub fn pinned_events(&self) -> Vec<CalendarEvent<'_>> {
+        let mut pinned: Vec<_> = self
+            .filter_events(EventFilter::default())
+            .filter(|event| event.is_pinned())
+            .collect();
+        pinned.sort_by_key(|event| event.begin().with_timezone(&Utc));
+        pinned
+    }
+
+    /// All alarms of any event that fire in `range`, in the order they fire
+    pub fn alarms_between<Tz: TimeZone>(
+        &self,
+        range: Range<DateTime<Tz>>,
+    ) -> Vec<(DateTime<Utc>, CalendarEvent<'_>, Alarm)> {
+        let range = range.start.with_timezone(&Utc)..range.end.with_timezone(&Utc);
+
+        // Triggers can be arbitrarily far away from their event, so all
+        // events have to be considered
+        let mut alarms = Vec::new();
+        // Absolute triggers of a series fire once, not for every instance
+        let mut absolute_fired = BTreeSet::new();
+        for event in self.filter_events(EventFilter::default()) {
+            for alarm in event.alarms() {
+                for time in alarm.trigger_times(event.occurrence()) {
+                    let time = time.with_timezone(&Utc);
+                    let first_firing = match alarm.related {
+                        AlarmRelation::At(_) => {
+                            absolute_fired.insert((event.uid().to_owned(), time))
+                        }
+                        _ => true,
+                    };
+                    if first_firing && range.contains(&time) {
+                        alarms.push((time, event, alarm.clone()));
+                    }
+                }
+            }
+        }
+
+        alarms.sort_by_key(|(time, _, _)| *time);
+        alarms
+    }
+
+    /// Beginnings of events, firing alarms and deadlines of open tasks in
+    /// `range` as one chronological feed. At the same time alarms come first,
+    /// then tasks and then events.
+    pub fn upcoming<Tz: TimeZone>(&self, range: Range<DateTime<Tz>>) -> Vec<FeedItem<'_>> {
+        let range = range.start.with_timezone(&Utc)..range.end.with_timezone(&Utc);
+
+        let mut feed: Vec<FeedItem> = self
+            .alarms_between(range.clone())
+            .into_iter()
+            .map(|(time, event, alarm)| FeedItem {
+                time,
+                kind: FeedItemKind::Alarm(event, alarm),
+            })
+            .collect();
+        feed.extend(self.todos().into_iter().filter_map(|todo| {
+            let time = todo.due()?.with_timezone(&Utc);
+            (!todo.is_done() && range.contains(&time)).then_some(FeedItem {
+                time,
+                kind: FeedItemKind::Due(todo),
+            })
+        }));
+        feed.extend(
+            self.filter_events(EventFilter::default())
+                .map(|event| FeedItem {
+                    time: event.begin().with_timezone(&Utc),
+                    kind: FeedItemKind::Event(event),
+                })
+                .filter(|item| range.contains(&item.time)),
+        );
+
+        feed.sort_by_key(|item| {
+            let order = match item.kind {
+                FeedItemKind::Alarm(..) => 0,
+                FeedItemKind::Due(_) => 1,
+                FeedItemKind::Event(_) => 2,
+            };
+            (item.time, order)
+        });
+        feed
+    }
+
+    /// Tasks of all calendars, those due first come first and those without
+    /// deadline last
+    pub fn todos(&self) -> Vec<CalendarTodo<'_>> {
+        let mut todos: Vec<CalendarTodo> = self
+            .shown_calendars()
+            .flat_map(|calendar| {
+                calendar
+                    .todo_iter()
+                    .map(move |todo| CalendarTodo { calendar, todo })
+            })
+            .collect();
+        todos.sort_by_key(|todo| {
+            (
+                todo.due().map(|due| due.with_timezone(&Utc)).is_none(),
+                todo.due().map(|due| due.with_timezone(&Utc)),
+                todo.priority().unwrap_or(u8::MAX),
+            )
+        });
+        todos
+    }
+
+    /// Tasks due on `date` in local time. On the day of `now` open tasks that
+    /// are overdue are included as well.
+    pub fn todos_of_day(&self, date: NaiveDate, now: &DateTime<Local>) -> Vec<CalendarTodo<'_>> {
+        let today = now.date_naive();
+        self.todos()
+            .into_iter()
+            .filter(|todo| match todo.due() {
+                Some(due) => {
+                    let due = due.with_timezone(&Local).date_naive();
+                    due == date || (date == today && due < today && !todo.is_done())
+                }
+                None => false,
+            })
+            .collect()
+    }
+
+    /// Marks the task `uid` as done or not done so that it can be undone
+    pub fn set_todo_done(&mut self, calendar: &str, uid: &str, done: bool) -> Result<()> {
+        let backup = self.backup(calendar, uid)?;
+        self.calendar_by_id_mut(calendar)
+            .ok_or_else(|| unknown_calendar(calendar))?
+            .set_todo_done(uid, done)?;
+
+        let description = if done {
+            "marked done"
+        } else {
+            "marked not done"
+        };
+        self.finish_batch(description, vec![backup]);
+        Ok(())
+    }
+
+    /// Events of all calendars matching `filter`
+    pub fn filter_events(&self, filter: EventFilter) -> impl Iterator<Item = CalendarEvent<'_>> {
+        self.shown_calendars().flat_map(move |calendar| {
+            calendar
+                .filter_events(filter.clone())
+                .map(move |event| CalendarEvent { calendar, event })
+        })
     }
 
-    pub fn events_of_current_day(&self) -> impl Iterator<Item = &dyn Eventlike> {
+    pub fn events_of_current_day(&self) -> impl Iterator<Item = CalendarEvent<'_>> {
         let today = Utc::today();
 
         self.events_of_day(&today.naive_utc())
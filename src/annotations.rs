@@ -0,0 +1,52 @@
+use std::collections::BTreeMap;
+use std::io;
+
+use crate::state::{Migration, StateFile};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Version 1 only added the version header
+const MIGRATIONS: &[Migration] = &[Ok];
+
+/// Private notes on events by UID. They are kept next to, not in, the
+/// calendars so that events of read-only or synced calendars can be
+/// annotated as well.
+#[derive(Default)]
+pub struct Annotations {
+    file: Option<StateFile>,
+    notes: BTreeMap<String, String>,
+}
+
+impl Annotations {
+    pub fn load() -> Result<Self> {
+        let file = StateFile::new("annotations.toml", MIGRATIONS)?;
+        let notes = file.load()?;
+
+        Ok(Annotations {
+            file: Some(file),
+            notes,
+        })
+    }
+
+    fn save(&self) -> Result<()> {
+        self.file
+            .as_ref()
+            .ok_or_else(|| io::Error::other("annotations could not be loaded"))?
+            .save(&self.notes)
+    }
+
+    pub fn get(&self, uid: &str) -> Option<&str> {
+        self.notes.get(uid).map(String::as_str)
+    }
+
+    /// Sets the note of `uid`, removing it if `note` is empty
+    pub fn set(&mut self, uid: &str, note: &str) -> Result<()> {
+        let note = note.trim();
+        if note.is_empty() {
+            self.notes.remove(uid);
+        } else {
+            self.notes.insert(uid.to_owned(), note.to_owned());
+        }
+        self.save()
+    }
+}
@@ -0,0 +1,242 @@
+//! Stable interface for programs using jackal's calendars, e.g. status bars,
+//! bots or exporters.
+//!
+//! Everything in this module follows semantic versioning. The rest of the
+//! crate is shared with the `jk` binary and changes without notice, so it is
+//! hidden from the documentation and should not be used directly.
+//!
+//! ```no_run
+//! use chrono::{Duration, Utc};
+//! use jackal::api::Calendars;
+//!
+//! let calendars = Calendars::load()?;
+//! let now = Utc::now();
+//! for event in calendars.events_between(now, now + Duration::days(7)) {
+//!     println!("{} {}", event.begin, event.summary);
+//! }
+//! # Ok::<(), jackal::api::Error>(())
+//! ```
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use chrono_tz::Tz;
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+
+use crate::agenda::Agenda;
+use crate::config::{self, Config};
+use crate::provider::{self, datetime, CalendarEvent, NewEvent};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Why loading, querying or changing the calendars failed. Only the message
+/// is part of the interface, the underlying error types are not.
+#[derive(Debug)]
+pub struct Error {
+    source: Box<dyn std::error::Error>,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.source.fmt(f)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<provider::Error> for Error {
+    fn from(error: provider::Error) -> Self {
+        Error {
+            source: Box::new(error),
+        }
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for Error {
+    fn from(source: Box<dyn std::error::Error>) -> Self {
+        Error { source }
+    }
+}
+
+/// A calendar as configured in jackal's config file
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CalendarInfo {
+    /// Identifies the calendar in `Calendars::add_event`
+    pub id: String,
+    pub name: String,
+    /// As configured, e.g. `#ff0000` or `blue`
+    pub color: Option<String>,
+}
+
+/// A single occurrence of an event, instances of recurring events are
+/// returned separately with the same `uid`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Event {
+    pub uid: String,
+    /// Id of the calendar the event belongs to, see `CalendarInfo::id`
+    pub calendar: String,
+    pub summary: String,
+    pub begin: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// All-day events begin and end at midnight of the calendar's time zone
+    pub all_day: bool,
+    pub location: Option<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub categories: Vec<String>,
+}
+
+impl From<CalendarEvent<'_>> for Event {
+    fn from(event: CalendarEvent<'_>) -> Self {
+        Event {
+            uid: event.uid().to_owned(),
+            calendar: event.calendar.id().to_owned(),
+            summary: event.summary().to_owned(),
+            begin: event.begin().with_timezone(&Utc),
+            end: event.end().with_timezone(&Utc),
+            all_day: event.occurrence().is_allday(),
+            location: event.location(),
+            description: event.description(),
+            url: event.url(),
+            categories: event.categories(),
+        }
+    }
+}
+
+/// The calendars of a jackal configuration
+pub struct Calendars {
+    agenda: Agenda,
+}
+
+impl Calendars {
+    /// Loads the calendars of the config file `jk` uses, i.e. the one in
+    /// `$JACKAL_CONFIG` or the user's config directory
+    pub fn load() -> Result<Self> {
+        let config = match config::find_configfile() {
+            Ok(path) => Config::load(&path)?,
+            Err(_) => Config::default(),
+        };
+        Self::from_config(&config)
+    }
+
+    /// Loads the calendars of the config file at `path`
+    pub fn load_from(path: &Path) -> Result<Self> {
+        Self::from_config(&Config::load(path)?)
+    }
+
+    fn from_config(config: &Config) -> Result<Self> {
+        Ok(Calendars {
+            agenda: Agenda::from_config(config)?,
+        })
+    }
+
+    /// Re-reads the events that changed on disk since they were loaded, e.g.
+    /// by `jk` or a sync. Returns whether anything changed.
+    pub fn refresh(&mut self) -> bool {
+        !self.agenda.refresh().is_empty()
+    }
+
+    /// All calendars that are not hidden
+    pub fn calendars(&self) -> Vec<CalendarInfo> {
+        self.agenda
+            .calendar_iter()
+            .filter(|calendar| !self.agenda.is_hidden(calendar.id()))
+            .map(|calendar| CalendarInfo {
+                id: calendar.id().to_owned(),
+                name: calendar.name().to_owned(),
+                color: calendar.color().map(str::to_owned),
+            })
+            .collect()
+    }
+
+    /// Events overlapping any of the days from `first` to `last`, ordered by
+    /// begin
+    pub fn events_of_days(&self, first: NaiveDate, last: NaiveDate) -> Vec<Event> {
+        sorted(
+            self.agenda
+                .events_covering(&(first..=last))
+                .map(Event::from),
+        )
+    }
+
+    /// Events overlapping the time from `begin` to `end`, ordered by begin
+    pub fn events_between(&self, begin: DateTime<Utc>, end: DateTime<Utc>) -> Vec<Event> {
+        // Calendars use their local dates, a day on either side covers all
+        // time zones
+        let days =
+            (begin - Duration::days(1)).date_naive()..=(end + Duration::days(1)).date_naive();
+        sorted(
+            self.agenda
+                .events_covering(&days)
+                .map(Event::from)
+                .filter(|event| event.begin < end && event.end > begin),
+        )
+    }
+
+    /// The first event beginning after `time`
+    pub fn next_event_after(&self, time: DateTime<Utc>) -> Option<Event> {
+        self.agenda.next_event_after(&time).map(Event::from)
+    }
+
+    /// Gaps of at least `min_duration` between `begin` and `end` in which no
+    /// event blocks the time, ignoring all-day and transparent events
+    pub fn free_slots(
+        &self,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+        min_duration: Duration,
+    ) -> Vec<Range<DateTime<Utc>>> {
+        self.agenda.free_slots(begin..end, min_duration)
+    }
+
+    /// Events containing every word of `query` in their summary, description
+    /// or location, ignoring case, ordered by begin
+    pub fn search(&self, query: &str) -> Vec<Event> {
+        self.agenda
+            .search(query)
+            .into_iter()
+            .map(Event::from)
+            .collect()
+    }
+
+    fn calendar_tz(&self, calendar: &str) -> Result<Tz> {
+        let calendar = self.agenda.calendar_by_id(calendar).ok_or_else(|| {
+            provider::Error::new(
+                provider::ErrorKind::NotFound,
+                &format!("No calendar with id '{}'", calendar),
+            )
+        })?;
+        Ok(*calendar.tz())
+    }
+
+    /// Adds an event to the calendar `calendar` and returns its UID
+    pub fn add_event(
+        &mut self,
+        calendar: &str,
+        summary: &str,
+        begin: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<String> {
+        let tz = self.calendar_tz(calendar)?;
+        let mut event = NewEvent::new(begin.with_timezone(&tz));
+        event.end = Some(end.with_timezone(&tz));
+        event.summary = Some(summary.to_owned());
+        Ok(self.agenda.new_event_in(calendar, event)?)
+    }
+
+    /// Adds an event described like in `jk new`, e.g. "lunch with Anna
+    /// tomorrow 12:30", to the calendar `calendar` and returns its UID
+    pub fn add_event_from_text(&mut self, calendar: &str, text: &str) -> Result<String> {
+        let tz = self.calendar_tz(calendar)?;
+        let now = Utc::now().with_timezone(&tz);
+        let event = datetime::parse_event(text, Local::now().date_naive(), &now)?;
+        Ok(self.agenda.new_event_in(calendar, event)?)
+    }
+}
+
+fn sorted(events: impl Iterator<Item = Event>) -> Vec<Event> {
+    let mut events: Vec<Event> = events.collect();
+    events.sort_by(|a, b| (a.begin, &a.summary).cmp(&(b.begin, &b.summary)));
+    events
+}
@@ -0,0 +1,189 @@
+// Backups of the calendar collections and jackal's state as tar archives.
+// Archives contain `collections/<name>/<calendar>/...` for every configured
+// collection and `state/...` with the notes and other state files.
+
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::os::unix::fs::{symlink, DirBuilderExt};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use crate::config::Config;
+use crate::state;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const COLLECTIONS_DIR: &str = "collections";
+const STATE_DIR: &str = "state";
+
+fn backup_error(msg: String) -> Box<dyn std::error::Error> {
+    Box::new(io::Error::other(msg))
+}
+
+/// A new directory in the temporary directory that only we can access and
+/// that is removed when dropped
+struct Staging(PathBuf);
+
+impl Staging {
+    fn new(purpose: &str) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "jackal-{}-{}-{}",
+            purpose,
+            process::id(),
+            uuid::Uuid::new_v4().simple()
+        ));
+        // Fails if it exists, e.g. planted by someone else
+        fs::DirBuilder::new().mode(0o700).create(&path)?;
+        Ok(Staging(path))
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_dir_all(&self.0) {
+            log::warn!("Could not remove {}: {}", self.0.display(), err);
+        }
+    }
+}
+
+fn tar(args: &[&OsStr]) -> Result<()> {
+    let output = Command::new("tar")
+        .args(args)
+        .output()
+        .map_err(|err| backup_error(format!("could not run tar: {}", err)))?;
+    if !output.status.success() {
+        return Err(backup_error(format!(
+            "tar failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the collections of `config` and the state directory to the
+/// gzip-compressed tar archive `file`
+pub fn create(config: &Config, file: &Path) -> Result<()> {
+    let staging = Staging::new("backup")?;
+    let collections = staging.0.join(COLLECTIONS_DIR);
+    fs::create_dir(&collections)?;
+    for spec in &config.collections {
+        if spec.path.is_dir() {
+            symlink(fs::canonicalize(&spec.path)?, collections.join(&spec.name))?;
+        } else {
+            log::warn!(
+                "Skipping collection {}, {} is missing",
+                spec.name,
+                spec.path.display()
+            );
+        }
+    }
+
+    let mut entries = vec![COLLECTIONS_DIR];
+    let state_dir = state::state_dir()?;
+    if state_dir.is_dir() {
+        symlink(&state_dir, staging.0.join(STATE_DIR))?;
+        entries.push(STATE_DIR);
+    }
+
+    // Links are followed (-h) so that the archive holds the files themselves
+    let mut args = vec![
+        OsStr::new("-czhf"),
+        file.as_os_str(),
+        OsStr::new("-C"),
+        staging.0.as_os_str(),
+    ];
+    args.extend(entries.iter().map(OsStr::new));
+    tar(&args)
+}
+
+/// What `restore` brought back
+#[derive(Default)]
+pub struct Restored {
+    /// `<collection>/<calendar>` of the restored calendars
+    pub calendars: Vec<String>,
+    pub state: bool,
+}
+
+/// Replaces the calendars in the archive `file` by their backed up
+/// versions. Only calendars whose collection or calendar name is in `only`
+/// are restored unless it is empty. Event files that were added since the
+/// backup are removed. The state files are restored if `with_state` is set.
+pub fn restore(
+    config: &Config,
+    file: &Path,
+    only: &[String],
+    with_state: bool,
+) -> Result<Restored> {
+    let staging = Staging::new("restore")?;
+    tar(&[
+        OsStr::new("-xzf"),
+        file.as_os_str(),
+        OsStr::new("-C"),
+        staging.0.as_os_str(),
+    ])?;
+
+    let mut restored = Restored::default();
+    let collections = staging.0.join(COLLECTIONS_DIR);
+    for spec in &config.collections {
+        let backed_up = collections.join(&spec.name);
+        if !backed_up.is_dir() {
+            continue;
+        }
+
+        for entry in fs::read_dir(&backed_up)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let calendar = entry.file_name().to_string_lossy().into_owned();
+            let id = format!("{}/{}", spec.name, calendar);
+            if !only.is_empty()
+                && !only
+                    .iter()
+                    .any(|o| *o == spec.name || *o == calendar || *o == id)
+            {
+                continue;
+            }
+
+            restore_calendar(&entry.path(), &spec.path.join(&calendar))?;
+            restored.calendars.push(id);
+        }
+    }
+
+    let state = staging.0.join(STATE_DIR);
+    if with_state && state.is_dir() {
+        copy_dir(&state, &state::state_dir()?)?;
+        restored.state = true;
+    }
+
+    Ok(restored)
+}
+
+fn restore_calendar(backup: &Path, calendar: &Path) -> Result<()> {
+    if calendar.is_dir() {
+        for entry in fs::read_dir(calendar)? {
+            let path = entry?.path();
+            let is_event_file = path.extension().is_some_and(|ext| ext == "ics");
+            if is_event_file && !backup.join(path.file_name().unwrap_or_default()).exists() {
+                fs::remove_file(&path)?;
+            }
+        }
+    }
+    copy_dir(backup, calendar)?;
+    Ok(())
+}
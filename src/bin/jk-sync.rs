@@ -0,0 +1,51 @@
+//! Standalone `jk sync`, e.g. for service managers and cron jobs
+use flexi_logger::Logger;
+use jackal::agenda::Agenda;
+use jackal::config::{self, Config};
+use jackal::sync;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "jk-sync",
+    about = "Runs the [sync] command of jackal's config and tells running instances to reload."
+)]
+struct Args {
+    #[structopt(
+        name = "CONFIG",
+        short = "c",
+        long = "config",
+        help = "path to config file",
+        parse(from_os_str)
+    )]
+    configfile: Option<PathBuf>,
+
+    #[structopt(long, help = "keep syncing every interval_minutes")]
+    daemon: bool,
+}
+
+fn main() {
+    if let Err(err) = run() {
+        log::error!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+    Logger::try_with_env_or_str("info")?.start()?;
+
+    let args = Args::from_args();
+    let config = match args.configfile {
+        Some(path) => Config::load(&path)?,
+        None => Config::load(&config::find_configfile()?)?,
+    };
+    let spec = config.sync.as_ref().ok_or("No [sync] section in config")?;
+
+    let agenda = Agenda::from_config(&config)?;
+    if args.daemon {
+        sync::run_daemon(&agenda, spec)
+    } else {
+        sync::sync(&agenda, spec)
+    }
+}
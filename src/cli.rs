@@ -0,0 +1,1652 @@
+use structopt::clap::AppSettings;
+use structopt::StructOpt;
+
+use crate::agenda::{Agenda, EventOperation, FeedItemKind};
+use crate::annotations::Annotations;
+use crate::backup;
+use crate::config::Config;
+use crate::history;
+use crate::link::EventLink;
+use crate::locale::{FormatLocalized, Locale};
+use crate::mail;
+use crate::notify;
+use crate::print;
+use crate::provider::ical::{self, calendar::IcalDuration, itip, itip::PartStat, xcal};
+use crate::provider::{
+    datetime, tz, Alarm, AlarmRelation, Error, ErrorKind, EventChange, EventFilter, EventId,
+    Eventlike, Occurrence,
+};
+use crate::sync;
+use crate::ui::format_relative;
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::ops::RangeInclusive;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    #[structopt(about = "manage calendar metadata")]
+    Calendar(CalendarCommand),
+    #[structopt(about = "import events from an iCalendar file")]
+    Import {
+        #[structopt(
+            long = "split",
+            help = "allow files with several events, writing one file per UID"
+        )]
+        split: bool,
+        #[structopt(
+            short = "c",
+            long = "calendar",
+            help = "id of the target calendar, asked for on the terminal if omitted"
+        )]
+        calendar: Option<String>,
+        #[structopt(
+            parse(from_os_str),
+            help = "iCalendar or xCal (RFC 6321) file, - to read from stdin"
+        )]
+        file: PathBuf,
+    },
+    #[structopt(about = "import the calendar data of an email, e.g. an invitation")]
+    ImportMail {
+        #[structopt(
+            long = "split",
+            help = "allow files with several events, writing one file per UID"
+        )]
+        split: bool,
+        #[structopt(
+            short = "c",
+            long = "calendar",
+            help = "id of the target calendar, asked for on the terminal if omitted"
+        )]
+        calendar: Option<String>,
+        #[structopt(long = "preview", help = "only show the events, import nothing")]
+        preview: bool,
+        #[structopt(
+            parse(from_os_str),
+            help = "RFC 822 message, e.g. an .eml file, - to read from stdin"
+        )]
+        file: PathBuf,
+    },
+    #[structopt(
+        about = "answer an invitation from a mail or .ics file, storing the event and printing the reply for the organizer"
+    )]
+    Invite {
+        #[structopt(
+            short = "c",
+            long = "calendar",
+            help = "id of the calendar for new events, asked for on the terminal if omitted"
+        )]
+        calendar: Option<String>,
+        #[structopt(
+            short = "a",
+            long = "answer",
+            default_value = "accepted",
+            help = "accepted, declined or tentative"
+        )]
+        answer: PartStat,
+        #[structopt(
+            long = "as",
+            help = "your address among the attendees, the first one of 'addresses' in the config that is invited by default"
+        )]
+        address: Option<String>,
+        #[structopt(
+            short = "o",
+            long = "reply",
+            parse(from_os_str),
+            help = "file to write the reply to instead of stdout"
+        )]
+        reply: Option<PathBuf>,
+        #[structopt(
+            parse(from_os_str),
+            help = "RFC 822 message or iCalendar file with METHOD:REQUEST, - to read from stdin"
+        )]
+        file: PathBuf,
+    },
+    #[structopt(about = "print all events of a calendar as iCalendar")]
+    Export {
+        #[structopt(short = "c", long = "calendar", help = "id of the calendar")]
+        calendar: String,
+        #[structopt(long = "xcal", help = "print xCal (RFC 6321) instead")]
+        xcal: bool,
+    },
+    #[structopt(about = "list all calendars with their number of events and next event")]
+    ListCalendars,
+    #[structopt(about = "check calendars for inconsistencies")]
+    Doctor {
+        #[structopt(
+            long = "fix",
+            help = "resolve duplicate UIDs by keeping the event with the newest SEQUENCE"
+        )]
+        fix: bool,
+    },
+    #[structopt(about = "upload a single event to the configured WebDAV collection")]
+    Publish { uid: String },
+    #[structopt(about = "edit the iCalendar data of an event or task in $VISUAL or $EDITOR")]
+    Edit { uid: String },
+    #[structopt(about = "move past events into the archive of their calendar")]
+    Archive {
+        #[structopt(
+            long = "days",
+            help = "archive events that ended this many days ago [default: archive_after_days]"
+        )]
+        days: Option<u32>,
+    },
+    #[structopt(about = "print the time until the current event ends or the next one starts")]
+    Countdown,
+    #[structopt(about = "run the [sync] command and tell running instances to reload")]
+    Sync {
+        #[structopt(long, help = "keep syncing every interval_minutes")]
+        daemon: bool,
+    },
+    #[structopt(about = "print the events of the next days")]
+    Agenda {
+        #[structopt(
+            short = "d",
+            long = "days",
+            default_value = "7",
+            help = "number of days to show, starting today"
+        )]
+        days: u32,
+        #[structopt(
+            long = "watch",
+            help = "keep running, redrawing every minute and whenever calendars change"
+        )]
+        watch: bool,
+        #[structopt(
+            short = "a",
+            long = "all",
+            help = "also list alarms, everything in the order it happens"
+        )]
+        all: bool,
+    },
+    #[structopt(about = "print the free time of a day or week, e.g. to schedule a meeting")]
+    Free {
+        #[structopt(help = "YYYY-MM-DD, today if not given")]
+        date: Option<NaiveDate>,
+        #[structopt(long = "week", help = "the whole week containing the date")]
+        week: bool,
+        #[structopt(
+            short = "m",
+            long = "min",
+            default_value = "30m",
+            parse(try_from_str = parse_window),
+            help = "shortest time to list, e.g. 30m or 2h"
+        )]
+        min: Duration,
+        #[structopt(
+            long = "from",
+            help = "only look after this time of each day, HH:MM [default: start of the availability blocks]"
+        )]
+        from: Option<NaiveTime>,
+        #[structopt(
+            long = "to",
+            help = "only look before this time of each day, HH:MM [default: end of the availability blocks]"
+        )]
+        to: Option<NaiveTime>,
+    },
+    #[structopt(about = "show or set the private note of an event, an empty note removes it")]
+    Note { uid: String, note: Option<String> },
+    #[structopt(about = "inspect the alarms of events")]
+    Alarm(AlarmCommand),
+    #[structopt(about = "show the events of an iCalendar file without importing them")]
+    Preview {
+        #[structopt(
+            parse(from_os_str),
+            help = "iCalendar or xCal (RFC 6321) file, - to read from stdin"
+        )]
+        file: PathBuf,
+    },
+    #[structopt(about = "show the latest changes jackal made to events")]
+    History {
+        #[structopt(
+            short = "n",
+            long = "limit",
+            default_value = "20",
+            help = "number of changes to show"
+        )]
+        limit: usize,
+        #[structopt(
+            long = "undo",
+            help = "revert the latest change that was not undone yet"
+        )]
+        undo: bool,
+    },
+    #[structopt(about = "save all calendars and jackal's own state in a tar archive")]
+    Backup {
+        #[structopt(
+            parse(from_os_str),
+            help = "archive to write, jackal-<date>.tar.gz in the current directory by default"
+        )]
+        file: Option<PathBuf>,
+    },
+    #[structopt(about = "bring back calendars from a backup, events created since are removed")]
+    Restore {
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+        #[structopt(
+            long = "only",
+            help = "only restore this collection or calendar, may be repeated"
+        )]
+        only: Vec<String>,
+        #[structopt(long = "no-state", help = "keep the current notes and other state")]
+        no_state: bool,
+    },
+    #[structopt(about = "show the event a jackal:// link refers to")]
+    Show {
+        #[structopt(long = "path", help = "only print the file the event is stored in")]
+        path: bool,
+        link: EventLink,
+    },
+    #[structopt(
+        about = "show a time in the local and the secondary time zones, e.g. \"2024-07-03 09:00 America/New_York\""
+    )]
+    Convert {
+        #[structopt(
+            help = "[YYYY-MM-DD] [HH:MM[:SS]] [TZID], the local time zone if none is given"
+        )]
+        time: Vec<String>,
+    },
+    #[structopt(
+        about = "add an event described in natural language, e.g. \"lunch with Ana tomorrow 12:30-13:30\""
+    )]
+    New {
+        #[structopt(
+            long = "force",
+            help = "do not ask before adding events in the past or overlapping others"
+        )]
+        force: bool,
+        #[structopt(required = true)]
+        description: Vec<String>,
+    },
+    #[structopt(about = "print a week as HTML page, e.g. to print it from a browser")]
+    Print {
+        #[structopt(
+            long = "week",
+            help = "the week containing the given date, this week if no date is given"
+        )]
+        week: Option<Option<NaiveDate>>,
+    },
+    #[structopt(
+        about = "move events of a calendar to another time zone, keeping their local times"
+    )]
+    TzShift {
+        #[structopt(short = "c", long = "calendar", help = "id of the calendar")]
+        calendar: String,
+        #[structopt(
+            long = "from",
+            help = "time zone of the events to move, e.g. Europe/Berlin"
+        )]
+        from: Tz,
+        #[structopt(long = "to", help = "new time zone, e.g. Europe/Lisbon")]
+        to: Tz,
+        #[structopt(
+            long = "after",
+            help = "only move events beginning on or after this date"
+        )]
+        after: Option<NaiveDate>,
+        #[structopt(
+            long = "keep-instant",
+            help = "convert the times so that events stay at the same point in time"
+        )]
+        keep_instant: bool,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum CalendarCommand {
+    #[structopt(about = "change the display name of a calendar")]
+    Rename { id: String, name: String },
+    #[structopt(about = "change the color of a calendar (#RRGGBB)")]
+    SetColor { id: String, color: String },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum AlarmCommand {
+    #[structopt(about = "print all alarms that fire within the given time from now, e.g. 24h")]
+    Plan {
+        #[structopt(parse(try_from_str = parse_window))]
+        window: Duration,
+    },
+    #[structopt(
+        about = "add an alarm to an event, e.g. -P1D for a day before it starts",
+        setting = AppSettings::AllowLeadingHyphen
+    )]
+    Add {
+        uid: String,
+        #[structopt(
+            parse(try_from_str = parse_trigger),
+            help = "offset from the start of the event as iCalendar duration"
+        )]
+        trigger: Duration,
+        #[structopt(
+            short = "m",
+            long = "message",
+            help = "text to show, defaults to the summary of the event"
+        )]
+        message: Option<String>,
+    },
+    #[structopt(
+        about = "show alarms as desktop notifications with notify-send until killed, with actions to snooze them"
+    )]
+    Notify,
+    #[structopt(
+        about = "remove the alarms with the given trigger from an event",
+        setting = AppSettings::AllowLeadingHyphen
+    )]
+    Remove {
+        uid: String,
+        #[structopt(parse(try_from_str = parse_trigger))]
+        trigger: Duration,
+    },
+}
+
+/// An iCalendar duration like "-P1D" or "-PT15M"
+fn parse_trigger(input: &str) -> Result<Duration, String> {
+    input
+        .parse::<IcalDuration>()
+        .map(Duration::from)
+        .map_err(|_| format!("invalid duration '{}', expected e.g. -P1D or -PT15M", input))
+}
+
+/// A time span like "90m", "24h", "2d" or "1w"
+fn parse_window(input: &str) -> Result<Duration, String> {
+    let error = || {
+        format!(
+            "invalid time span '{}', expected e.g. 30m, 24h or 7d",
+            input
+        )
+    };
+
+    let (split, _) = input.char_indices().last().ok_or_else(error)?;
+    let (amount, unit) = input.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| error())?;
+
+    match unit {
+        "m" => Ok(Duration::minutes(amount)),
+        "h" => Ok(Duration::hours(amount)),
+        "d" => Ok(Duration::days(amount)),
+        "w" => Ok(Duration::weeks(amount)),
+        _ => Err(error()),
+    }
+}
+
+impl Command {
+    pub fn run(
+        self,
+        agenda: &mut Agenda,
+        config: &Config,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            Command::Calendar(cmd) => cmd.run(agenda),
+            Command::Import {
+                split,
+                calendar,
+                file,
+            } => import(agenda, calendar, &file, split),
+            Command::ImportMail {
+                split,
+                calendar,
+                preview,
+                file,
+            } => import_mail(agenda, calendar, &file, split, preview, &config.locale()),
+            Command::Invite {
+                calendar,
+                answer,
+                address,
+                reply,
+                file,
+            } => invite(agenda, config, calendar, answer, address, reply, &file),
+            Command::Export { calendar, xcal } => export(agenda, &calendar, xcal),
+            Command::ListCalendars => list_calendars(agenda),
+            Command::Doctor { fix } => doctor(agenda, fix),
+            Command::Publish { uid } => publish(agenda, config, &uid),
+            Command::Edit { uid } => edit(agenda, &uid),
+            Command::Archive { days } => {
+                let days = days.unwrap_or(config.archive_after_days);
+                let before = Utc::now() - Duration::days(days as i64);
+                let _suspension = agenda.suspend_watching()?;
+                for (calendar, num_events) in agenda.archive(before)? {
+                    println!("{}: archived {} event(s)", calendar, num_events);
+                }
+                Ok(())
+            }
+            Command::Agenda { days, watch, all } => {
+                let locale = config.locale();
+                if watch {
+                    watch_agenda(agenda, days, all, &locale)
+                } else {
+                    let mut out = io::stdout().lock();
+                    print_agenda(agenda, days, all, &locale, &Local::now(), &mut out)?;
+                    Ok(())
+                }
+            }
+            Command::Free {
+                date,
+                week,
+                min,
+                from,
+                to,
+            } => {
+                let date = date.unwrap_or_else(|| Local::now().date_naive());
+                let days = if week {
+                    let monday =
+                        date - Duration::days(date.weekday().num_days_from_monday() as i64);
+                    monday..=monday + Duration::days(6)
+                } else {
+                    date..=date
+                };
+                let mut out = io::stdout().lock();
+                print_free_slots(agenda, config, days, min, (from, to), &mut out)?;
+                Ok(())
+            }
+            Command::Note { uid, note } => {
+                let mut annotations = Annotations::load()?;
+                match note {
+                    Some(note) => annotations.set(&uid, &note)?,
+                    None => {
+                        if let Some(note) = annotations.get(&uid) {
+                            println!("{}", note);
+                        }
+                    }
+                }
+                Ok(())
+            }
+            Command::Alarm(cmd) => cmd.run(agenda, config),
+            Command::Preview { file } => preview(agenda, &file, &config.locale()),
+            Command::History { limit, undo } => {
+                if undo {
+                    let _suspension = agenda.suspend_watching()?;
+                    match agenda.undo()? {
+                        Some(description) => println!("Undid {}", description),
+                        None => println!("Nothing to undo"),
+                    }
+                    return Ok(());
+                }
+                let mut out = io::stdout().lock();
+                history::print(limit, &mut out)
+            }
+            Command::Backup { file } => {
+                let file = file.unwrap_or_else(|| {
+                    PathBuf::from(format!(
+                        "jackal-{}.tar.gz",
+                        Local::now().format("%Y-%m-%d-%H%M")
+                    ))
+                });
+                backup::create(config, &file)?;
+                println!("Saved backup to {}", file.display());
+                Ok(())
+            }
+            Command::Restore {
+                file,
+                only,
+                no_state,
+            } => {
+                let _suspension = agenda.suspend_watching()?;
+                let restored = backup::restore(config, &file, &only, !no_state)?;
+                for calendar in &restored.calendars {
+                    println!("Restored {}", calendar);
+                }
+                if restored.state {
+                    println!("Restored notes and other state");
+                }
+                if restored.calendars.is_empty() && !restored.state {
+                    println!("Nothing to restore");
+                }
+                Ok(())
+            }
+            Command::Show { path, link } => show(agenda, &link, path, &config.locale()),
+            Command::Convert { time } => convert(&time.join(" "), config),
+            Command::New { force, description } => new_event(agenda, &description.join(" "), force),
+            Command::Print { week } => {
+                let date = week.ok_or_else(|| {
+                    Error::new(ErrorKind::Invalid, "Nothing to print, use --week")
+                })?;
+                let date = date.unwrap_or_else(|| Local::now().date_naive());
+                let mut out = io::stdout().lock();
+                print::write_week_html(agenda, date, &config.locale(), &mut out)?;
+                Ok(())
+            }
+            Command::TzShift {
+                calendar,
+                from,
+                to,
+                after,
+                keep_instant,
+            } => tz_shift(agenda, &calendar, from, to, after, keep_instant),
+            Command::Countdown => {
+                // Print an empty line if there is nothing to count down to so
+                // that status bars clear their previous output
+                match agenda.countdown(&Local::now()) {
+                    Some(countdown) => println!("{}", countdown),
+                    None => println!(),
+                }
+                Ok(())
+            }
+            Command::Sync { daemon } => {
+                let spec = config.sync.as_ref().ok_or_else(|| {
+                    Error::new(ErrorKind::NotFound, "No [sync] section in config")
+                })?;
+                if daemon {
+                    sync::run_daemon(agenda, spec)
+                } else {
+                    sync::sync(agenda, spec)
+                }
+            }
+        }
+    }
+}
+
+const WATCH_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// E.g. "09:30 - 10:00", "09:30" for instants or "Allday"
+fn time_label(occurrence: &Occurrence<Local>) -> String {
+    if occurrence.is_allday() {
+        "Allday".to_owned()
+    } else if occurrence.is_instant() {
+        occurrence.begin().format("%H:%M").to_string()
+    } else {
+        format!(
+            "{} - {}",
+            occurrence.begin().format("%H:%M"),
+            occurrence.end().format("%H:%M")
+        )
+    }
+}
+
+/// Prints the events of `days` days starting at the day of `now`, with a
+/// marker at the current time
+fn print_agenda(
+    agenda: &Agenda,
+    days: u32,
+    all: bool,
+    locale: &Locale,
+    now: &DateTime<Local>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let today = now.date_naive();
+
+    let pinned = agenda.pinned_events();
+    if !pinned.is_empty() {
+        writeln!(out, "Pinned")?;
+        for event in pinned {
+            writeln!(
+                out,
+                "  {}  {} ({})",
+                event.begin().with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                event.summary(),
+                event.calendar.id()
+            )?;
+        }
+        writeln!(out)?;
+    }
+
+    for date in today.iter_days().take(days as usize) {
+        writeln!(
+            out,
+            "{} \u{00b7} W{:02}",
+            date.format_in(locale, "%A, %-d %B %Y"),
+            date.iso_week().week()
+        )?;
+
+        // Time of timed entries, label and text
+        let mut entries = Vec::new();
+        if all {
+            let begin = Local
+                .from_local_datetime(&date.and_time(NaiveTime::MIN))
+                .earliest()
+                .unwrap_or(*now);
+            for item in agenda.upcoming(begin..begin + Duration::days(1)) {
+                let time = item.time.with_timezone(&Local);
+                entries.push(match item.kind {
+                    FeedItemKind::Event(event) => {
+                        let occurrence = event.occurrence().clone().with_tz(&Local {});
+                        (
+                            Some(time).filter(|_| !occurrence.is_allday()),
+                            time_label(&occurrence),
+                            format!("{} ({})", event.summary(), event.calendar.id()),
+                        )
+                    }
+                    FeedItemKind::Alarm(event, _) => (
+                        Some(time),
+                        time.format("%H:%M").to_string(),
+                        format!("[alarm] {} ({})", event.summary(), event.calendar.id()),
+                    ),
+                    FeedItemKind::Due(todo) => (
+                        Some(time).filter(|_| todo.has_due_time()),
+                        if todo.has_due_time() {
+                            time.format("%H:%M").to_string()
+                        } else {
+                            "Allday".to_owned()
+                        },
+                        format!("[due] {} ({})", todo.summary(), todo.calendar.id()),
+                    ),
+                });
+            }
+        } else {
+            let mut events: Vec<_> = agenda.events_of_day(&date).collect();
+            events.sort_by_key(|event| event.begin().with_timezone(&Local));
+            for event in events {
+                let occurrence = event.occurrence().clone().with_tz(&Local {});
+                entries.push((
+                    Some(occurrence.begin()).filter(|_| !occurrence.is_allday()),
+                    time_label(&occurrence),
+                    format!("{} ({})", event.summary(), event.calendar.id()),
+                ));
+            }
+        }
+
+        let mut now_shown = date != today;
+        for (time, label, text) in entries {
+            if !now_shown && time.is_some_and(|time| time > *now) {
+                writeln!(out, "  -> {}", now.format("%H:%M"))?;
+                now_shown = true;
+            }
+
+            writeln!(out, "  {:<13}  {}", label, text)?;
+        }
+
+        if !now_shown {
+            writeln!(out, "  -> {}", now.format("%H:%M"))?;
+        }
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+/// Times of `date` to look for free slots in: between `from` and `to` if
+/// either is given, during the availability blocks of the day otherwise and
+/// the whole day if there are none
+fn free_slot_windows(
+    config: &Config,
+    date: NaiveDate,
+    (from, to): (Option<NaiveTime>, Option<NaiveTime>),
+) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+    let day_begin = date.and_time(NaiveTime::MIN);
+    let day_end = day_begin + Duration::days(1);
+
+    if from.is_some() || to.is_some() {
+        return vec![(
+            from.map_or(day_begin, |from| date.and_time(from)),
+            to.map_or(day_end, |to| date.and_time(to)),
+        )];
+    }
+
+    let mut windows: Vec<_> = config
+        .availability
+        .iter()
+        .filter(|spec| spec.applies_to(date))
+        .map(|spec| (date.and_time(spec.begin), date.and_time(spec.end)))
+        .collect();
+    if windows.is_empty() {
+        windows.push((day_begin, day_end));
+    }
+    windows.sort();
+    windows
+}
+
+fn print_free_slots(
+    agenda: &Agenda,
+    config: &Config,
+    days: RangeInclusive<NaiveDate>,
+    min_duration: Duration,
+    limits: (Option<NaiveTime>, Option<NaiveTime>),
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let locale = config.locale();
+    let local = |time: NaiveDateTime| Local.from_local_datetime(&time).earliest();
+
+    for date in days
+        .start()
+        .iter_days()
+        .take_while(|date| days.contains(date))
+    {
+        writeln!(
+            out,
+            "{} \u{00b7} W{:02}",
+            date.format_in(&locale, "%A, %-d %B %Y"),
+            date.iso_week().week()
+        )?;
+
+        let mut slots = Vec::new();
+        // Overlapping blocks would list the same time twice
+        let mut covered_until = None;
+        for (begin, end) in free_slot_windows(config, date, limits) {
+            let begin = covered_until.map_or(begin, |until: NaiveDateTime| begin.max(until));
+            if let (Some(begin), Some(end)) = (local(begin), local(end)) {
+                if begin < end {
+                    slots.extend(agenda.free_slots(begin..end, min_duration));
+                }
+            }
+            covered_until = Some(covered_until.map_or(end, |until| until.max(end)));
+        }
+
+        if slots.is_empty() {
+            writeln!(out, "  nothing free")?;
+        }
+        for slot in slots {
+            let length = slot.end - slot.start;
+            // Slots lasting until midnight end at 24:00 rather than 00:00
+            let end = if slot.end.time() == NaiveTime::MIN && slot.end.date_naive() > date {
+                "24:00".to_owned()
+            } else {
+                slot.end.format("%H:%M").to_string()
+            };
+            writeln!(
+                out,
+                "  {} - {}  {}h {:02}m",
+                slot.start.format("%H:%M"),
+                end,
+                length.num_hours(),
+                length.num_minutes() % 60
+            )?;
+        }
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+/// Redraws the agenda whenever the minute changes or calendars are modified
+/// on disk, until interrupted
+fn watch_agenda(
+    agenda: &mut Agenda,
+    days: u32,
+    all: bool,
+    locale: &Locale,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let range = |now: &DateTime<Local>| {
+        let begin = now.date_naive().and_time(NaiveTime::MIN);
+        begin..begin + Duration::days(days as i64)
+    };
+    let subscription = agenda.subscribe(range(&Local::now()));
+    let mut shown_minute = None;
+
+    loop {
+        let now = Local::now();
+        agenda.set_subscription_range(subscription, range(&now));
+        let changed = !agenda.refresh().is_empty();
+
+        let minute = now.format("%Y-%m-%d %H:%M").to_string();
+        if changed || shown_minute.as_ref() != Some(&minute) {
+            let mut out = io::stdout().lock();
+            write!(
+                out,
+                "{}{}",
+                termion::clear::All,
+                termion::cursor::Goto(1, 1)
+            )?;
+            print_agenda(agenda, days, all, locale, &now, &mut out)?;
+            out.flush()?;
+            shown_minute = Some(minute);
+        }
+
+        thread::sleep(WATCH_POLL_INTERVAL);
+    }
+}
+
+/// Asks for the target calendar on the controlling terminal, which also
+/// works if stdin is used for the data to import
+fn prompt_calendar(agenda: &Agenda) -> Result<String, Box<dyn std::error::Error>> {
+    let no_terminal = |_| {
+        Error::new(
+            ErrorKind::Unsupported,
+            "No terminal to ask for the calendar, use --calendar",
+        )
+    };
+    let mut tty = termion::get_tty().map_err(no_terminal)?;
+
+    let calendars: Vec<_> = agenda.calendar_iter().collect();
+    for (i, calendar) in calendars.iter().enumerate() {
+        writeln!(tty, "{}) {} ({})", i + 1, calendar.id(), calendar.name())?;
+    }
+    write!(tty, "Import into calendar: ")?;
+    tty.flush()?;
+
+    let mut answer = String::new();
+    io::BufReader::new(tty).read_line(&mut answer)?;
+    let answer = answer.trim();
+
+    // Accept both the number shown and the id
+    Ok(match answer.parse::<usize>() {
+        Ok(n) if (1..=calendars.len()).contains(&n) => calendars[n - 1].id().to_owned(),
+        _ => answer.to_owned(),
+    })
+}
+
+fn import(
+    agenda: &mut Agenda,
+    calendar_id: Option<String>,
+    file: &Path,
+    split: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut ics = read_input(file)?;
+    if xcal::is_xcal(&ics) {
+        ics = xcal::xcal_to_ics(&ics)?;
+    }
+    import_text(agenda, calendar_id, &ics, file, split)
+}
+
+fn read_input(file: &Path) -> io::Result<String> {
+    if file == Path::new("-") {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(file)
+    }
+}
+
+/// Imports the iCalendar data `ics` read from `file`
+fn import_text(
+    agenda: &mut Agenda,
+    calendar_id: Option<String>,
+    ics: &str,
+    file: &Path,
+    split: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !split && ics.matches("BEGIN:VEVENT").count() > 1 {
+        return Err(Box::new(Error::new(
+            ErrorKind::Invalid,
+            &format!(
+                "'{}' contains several events, use --split to import them",
+                if file == Path::new("-") {
+                    "stdin".into()
+                } else {
+                    file.display().to_string()
+                }
+            ),
+        )));
+    }
+
+    let calendar_id = match calendar_id {
+        Some(id) => id,
+        None => prompt_calendar(agenda)?,
+    };
+    let _suspension = agenda.suspend_watching()?;
+    let summary = agenda.import_ics(&calendar_id, ics)?;
+    println!(
+        "Imported {} event(s) and {} override(s) into {} file(s)",
+        summary.events, summary.overrides, summary.files
+    );
+
+    Ok(())
+}
+
+fn preview(
+    agenda: &Agenda,
+    file: &Path,
+    locale: &Locale,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut ics = read_input(file)?;
+    if xcal::is_xcal(&ics) {
+        ics = xcal::xcal_to_ics(&ics)?;
+    }
+    preview_text(agenda, &ics, file, locale)
+}
+
+/// Prints the events of the iCalendar data `ics` read from `file`
+fn preview_text(
+    agenda: &Agenda,
+    ics: &str,
+    file: &Path,
+    locale: &Locale,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut last_date = None;
+    for event in ical::Event::parse_all(file, ics)? {
+        let occurrence = event.occurrence().clone().with_tz(&Local {});
+        let date = occurrence.begin().date_naive();
+        if last_date != Some(date) {
+            if last_date.is_some() {
+                println!();
+            }
+            println!(
+                "{} \u{00b7} W{:02}",
+                date.format_in(locale, "%A, %-d %B %Y"),
+                date.iso_week().week()
+            );
+            last_date = Some(date);
+        }
+
+        print!("  {:<13}  {}", time_label(&occurrence), event.summary());
+        match agenda.event_by_uid(event.uid()) {
+            Some(existing) => println!(" (already in {})", existing.calendar.id()),
+            None => println!(),
+        }
+
+        print_details(&event);
+    }
+
+    Ok(())
+}
+
+/// Location, alarms and description of `event` below its summary
+fn print_details(event: &dyn Eventlike) {
+    let indent = " ".repeat(17);
+    if let Some(location) = event.location() {
+        println!("{}@ {}", indent, location);
+    }
+    for alarm in event.alarms() {
+        println!("{}alarm {}", indent, describe_trigger(&alarm));
+    }
+    if let Some(description) = event.description() {
+        for line in description.lines() {
+            println!("{}{}", indent, line);
+        }
+    }
+}
+
+fn show(
+    agenda: &Agenda,
+    link: &EventLink,
+    only_path: bool,
+    locale: &Locale,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let event = link.resolve(agenda).ok_or_else(|| {
+        Error::new(
+            ErrorKind::NotFound,
+            &format!("No event with UID '{}' in '{}'", link.uid, link.calendar),
+        )
+    })?;
+    if !link.matches_start(&event) {
+        log::warn!("The event has moved since the link was created");
+    }
+    if only_path {
+        println!("{}", event.path().display());
+        return Ok(());
+    }
+
+    let occurrence = event.occurrence().clone().with_tz(&Local {});
+    println!("{}", occurrence.begin().format_in(locale, "%A, %-d %B %Y"));
+    println!(
+        "  {:<13}  {} ({})",
+        time_label(&occurrence),
+        event.summary(),
+        event.calendar.id()
+    );
+    print_details(event.event);
+    println!("{}{}", " ".repeat(17), event.path().display());
+    Ok(())
+}
+
+fn import_mail(
+    agenda: &mut Agenda,
+    calendar_id: Option<String>,
+    file: &Path,
+    split: bool,
+    preview: bool,
+    locale: &Locale,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let message = if file == Path::new("-") {
+        let mut message = Vec::new();
+        io::stdin().read_to_end(&mut message)?;
+        message
+    } else {
+        fs::read(file)?
+    };
+
+    let parts = mail::calendar_parts(&message);
+    if parts.is_empty() {
+        return Err(Box::new(Error::new(
+            ErrorKind::NotFound,
+            "The message contains no calendar data",
+        )));
+    }
+
+    if preview {
+        for ics in &parts {
+            preview_text(agenda, ics, file, locale)?;
+        }
+        return Ok(());
+    }
+
+    let calendar_id = match calendar_id {
+        Some(id) => id,
+        None => prompt_calendar(agenda)?,
+    };
+    for ics in &parts {
+        import_text(agenda, Some(calendar_id.clone()), ics, file, split)?;
+    }
+    Ok(())
+}
+
+fn invite(
+    agenda: &mut Agenda,
+    config: &Config,
+    calendar_id: Option<String>,
+    answer: PartStat,
+    address: Option<String>,
+    reply_file: Option<PathBuf>,
+    file: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let input = if file == Path::new("-") {
+        let mut input = Vec::new();
+        io::stdin().read_to_end(&mut input)?;
+        input
+    } else {
+        fs::read(file)?
+    };
+
+    // Plain iCalendar files are taken as they are, everything else as mail
+    let text = String::from_utf8_lossy(&input);
+    let parts = if text.trim_start().starts_with("BEGIN:VCALENDAR") {
+        vec![text.into_owned()]
+    } else {
+        mail::calendar_parts(&input)
+    };
+    // Mails may carry other calendar parts that are broken, only the
+    // invitation matters
+    let mut parse_error = None;
+    let message = parts
+        .iter()
+        .filter_map(|ics| match itip::Message::parse(ics) {
+            Ok(message) => Some(message),
+            Err(err) => {
+                log::warn!("Skipping a calendar part that can not be read: {}", err);
+                parse_error.get_or_insert(err);
+                None
+            }
+        })
+        .find(|message| message.method() == Some("REQUEST"));
+    let message = match (message, parse_error) {
+        (Some(message), _) => message,
+        (None, Some(err)) => return Err(Box::new(err)),
+        (None, None) => {
+            return Err(Box::new(Error::new(
+                ErrorKind::NotFound,
+                "No invitation (METHOD:REQUEST) found",
+            )))
+        }
+    };
+
+    let addresses = match address {
+        Some(address) => vec![address],
+        None => config.addresses.clone(),
+    };
+    let address = message.find_attendee(&addresses).ok_or_else(|| {
+        Error::new(
+            ErrorKind::NotFound,
+            "You are not among the attendees, set 'addresses' in the config or use --as",
+        )
+    })?;
+
+    // Informational output goes to stderr, stdout is the reply
+    if let Some(organizer) = message.organizer() {
+        eprintln!(
+            "{} invites to '{}'",
+            organizer.name.unwrap_or(organizer.address),
+            message.summary()
+        );
+    }
+    for attendee in message.attendees() {
+        eprintln!(
+            "    {} ({})",
+            attendee.name.unwrap_or(attendee.address),
+            attendee
+                .partstat
+                .as_deref()
+                .unwrap_or("NEEDS-ACTION")
+                .to_ascii_lowercase()
+        );
+    }
+
+    let existing = agenda.event_by_uid(message.uid()).map(|event| {
+        let sequence = itip::Message::parse(&event.raw_ical()).map_or(0, |m| m.sequence());
+        (event.calendar.id().to_owned(), sequence)
+    });
+    if let Some((_, sequence)) = existing
+        .as_ref()
+        .filter(|(_, seq)| *seq > message.sequence())
+    {
+        return Err(Box::new(Error::new(
+            ErrorKind::Invalid,
+            &format!(
+                "The invitation is outdated, the event was updated since (sequence {} > {})",
+                sequence,
+                message.sequence()
+            ),
+        )));
+    }
+
+    let _suspension = agenda.suspend_watching()?;
+    match (answer, existing) {
+        // Declined events are not kept
+        (PartStat::Declined, Some((calendar, _))) => {
+            agenda.apply(
+                "declined",
+                &[(
+                    calendar,
+                    EventId::series(message.uid()),
+                    EventOperation::Remove,
+                )],
+            )?;
+            eprintln!("Removed the event");
+        }
+        (PartStat::Declined, None) => {}
+        (_, existing) => {
+            let calendar = match (calendar_id, existing) {
+                // Importing there would leave the old event in its calendar
+                (Some(id), Some((existing_id, _))) if id != existing_id => {
+                    return Err(Box::new(Error::new(
+                        ErrorKind::Conflict,
+                        &format!(
+                            "The event is in calendar '{}' already, leave out --calendar or move the event first",
+                            existing_id
+                        ),
+                    )))
+                }
+                (Some(id), _) => id,
+                (None, Some((id, _))) => id,
+                (None, None) => prompt_calendar(agenda)?,
+            };
+            agenda.import_ics(&calendar, &message.to_event(address, answer))?;
+            eprintln!("Saved the event in {}", calendar);
+        }
+    }
+
+    let reply = message.reply(address, answer);
+    match reply_file {
+        Some(path) => fs::write(path, reply)?,
+        None => print!("{}", reply),
+    }
+    Ok(())
+}
+
+/// Opens `path` in the user's editor, through the shell so that the editor
+/// command may contain arguments
+fn run_editor(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_owned());
+    let status = process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(path)
+        .status()?;
+    if !status.success() {
+        return Err(Box::new(Error::new(
+            ErrorKind::Invalid,
+            &format!("'{}' failed ({}), nothing was saved", editor, status),
+        )));
+    }
+    Ok(())
+}
+
+fn ask_edit_again() -> io::Result<bool> {
+    let mut tty = termion::get_tty()?;
+    write!(tty, "Edit again? [Y/n] ")?;
+    tty.flush()?;
+
+    let mut answer = String::new();
+    io::BufReader::new(tty).read_line(&mut answer)?;
+    Ok(!answer.trim().eq_ignore_ascii_case("n"))
+}
+
+/// Edits `path` until it holds a valid event or task with `uid`. Returns the
+/// content or `None` if the user gave up.
+fn edit_until_valid(path: &Path, uid: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    loop {
+        run_editor(path)?;
+        let problem = match ical::calendar::check_file(path) {
+            Ok(new_uid) if new_uid == uid => return Ok(Some(fs::read_to_string(path)?)),
+            Ok(new_uid) => format!("The UID must stay '{}', not '{}'", uid, new_uid),
+            Err(err) => err.to_string(),
+        };
+        eprintln!("{}", problem);
+        if !ask_edit_again()? {
+            return Ok(None);
+        }
+    }
+}
+
+/// Edits the event or task `uid` in the user's editor and records the change
+pub fn edit(agenda: &Agenda, uid: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let path = agenda
+        .event_by_uid(uid)
+        .map(|event| event.path().to_owned())
+        .or_else(|| {
+            agenda
+                .todos()
+                .into_iter()
+                .find(|todo| todo.uid() == uid)
+                .map(|todo| todo.path().to_owned())
+        })
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                &format!("No event or task with UID '{}'", uid),
+            )
+        })?;
+    let original = fs::read_to_string(&path)?;
+    // Only changes of events are recorded in the history, tasks have none
+    let calendar = agenda
+        .event_by_uid(uid)
+        .map(|event| event.calendar.id().to_owned());
+
+    // Edit a copy so that the calendar never sees a broken file. It is kept
+    // in a new directory only we can access, others could guess its name.
+    let dir = env::temp_dir().join(format!("jackal-edit-{}", uuid::Uuid::new_v4()));
+    fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    let copy = dir.join(path.file_name().unwrap_or_default());
+    let edited = match fs::write(&copy, &original) {
+        Ok(()) => edit_until_valid(&copy, uid),
+        Err(err) => Err(err.into()),
+    };
+    let _ = fs::remove_dir_all(&dir);
+
+    match edited? {
+        Some(content) if content != original => {
+            fs::write(&path, &content)?;
+            println!("Saved {}", path.display());
+            if let Some(calendar) = calendar {
+                let entries: Vec<_> = history::Entry::new(
+                    history::new_batch(),
+                    "edited",
+                    &calendar,
+                    uid,
+                    Some(original),
+                    Some(content),
+                )
+                .into_iter()
+                .collect();
+                if let Err(err) = history::record(&entries) {
+                    log::warn!("Could not record the change in the history: {}", err);
+                }
+            }
+        }
+        Some(_) => println!("No changes"),
+        None => println!("Discarded the changes"),
+    }
+    Ok(())
+}
+
+fn export(
+    agenda: &Agenda,
+    calendar_id: &str,
+    xcal: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let calendar = agenda
+        .calendar_iter()
+        .find(|calendar| calendar.id() == calendar_id)
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                &format!("No calendar with id '{}'", calendar_id),
+            )
+        })?;
+
+    let ics = calendar.export_ics();
+    if xcal {
+        print!("{}", xcal::ics_to_xcal(&ics)?);
+    } else {
+        print!("{}", ics);
+    }
+
+    Ok(())
+}
+
+fn list_calendars(agenda: &Agenda) -> Result<(), Box<dyn std::error::Error>> {
+    let now = Local::now();
+
+    for calendar in agenda.calendar_iter() {
+        let num_events = calendar.count_events(EventFilter::default());
+        let next = calendar
+            .next_event_after(&now.with_timezone(calendar.tz()))
+            .map(|event| {
+                format!(
+                    "{} at {}",
+                    event.summary(),
+                    event.begin().with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                )
+            })
+            .unwrap_or_else(|| "none".to_owned());
+        let changed = calendar
+            .last_modified()
+            .map(|modified| {
+                let modified = DateTime::<Local>::from(modified);
+                format_relative(modified.min(now) - now)
+            })
+            .unwrap_or_else(|| "never".to_owned());
+
+        println!(
+            "{} ({}) {} - {} event(s), next: {}, changed: {}{}",
+            calendar.id(),
+            calendar.name(),
+            calendar.color().unwrap_or("no color"),
+            num_events,
+            next,
+            changed,
+            if calendar.is_available() {
+                ""
+            } else {
+                ", unavailable"
+            }
+        );
+    }
+
+    Ok(())
+}
+
+fn tz_shift(
+    agenda: &mut Agenda,
+    calendar: &str,
+    from: Tz,
+    to: Tz,
+    after: Option<NaiveDate>,
+    keep_instant: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut uids: Vec<String> = agenda
+        .calendar_by_id(calendar)
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                &format!("No calendar with id '{}'", calendar),
+            )
+        })?
+        .event_iter()
+        .filter(|event| *event.tz() == from)
+        .filter(|event| after.is_none_or(|after| event.begin().date_naive() >= after))
+        .map(|event| event.uid().to_owned())
+        .collect();
+    // Overrides of recurring events share the UID and file of their series,
+    // which is moved as a whole
+    uids.sort();
+    uids.dedup();
+
+    let change = EventChange::ChangeTimezone {
+        from,
+        to,
+        keep_instant,
+    };
+    let operations: Vec<_> = uids
+        .iter()
+        .map(|uid| {
+            (
+                calendar.to_owned(),
+                EventId::series(uid),
+                EventOperation::Modify(change.clone()),
+            )
+        })
+        .collect();
+    let _suspension = agenda.suspend_watching()?;
+    agenda.apply(&format!("moved to {}", to.name()), &operations)?;
+
+    println!(
+        "Moved {} event(s) from {} to {}",
+        operations.len(),
+        from.name(),
+        to.name()
+    );
+    Ok(())
+}
+
+fn ask_add_anyway() -> io::Result<bool> {
+    let mut tty = termion::get_tty()?;
+    write!(tty, "Add anyway? [y/N] ")?;
+    tty.flush()?;
+
+    let mut answer = String::new();
+    io::BufReader::new(tty).read_line(&mut answer)?;
+    Ok(answer.trim().eq_ignore_ascii_case("y"))
+}
+
+/// Adds the event described by `input` to the first calendar, asking first
+/// if it begins in the past or overlaps other events unless `force`
+fn new_event(
+    agenda: &mut Agenda,
+    input: &str,
+    force: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let calendar_tz = *agenda
+        .calendar_iter()
+        .next()
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "No calendar available"))?
+        .tz();
+    let now = Utc::now().with_timezone(&calendar_tz);
+    let event = datetime::parse_event(input, Local::now().date_naive(), &now)?;
+
+    let warnings = agenda.check_new_event(&event, &now);
+    if !force && !warnings.is_empty() {
+        eprintln!(
+            "'{}' {}",
+            event.summary.as_deref().unwrap_or_default(),
+            warnings
+        );
+        let confirmed = ask_add_anyway().map_err(|err| {
+            Error::new(
+                ErrorKind::Invalid,
+                &format!("Could not ask for confirmation ({}), use --force", err),
+            )
+        })?;
+        if !confirmed {
+            println!("Not added");
+            return Ok(());
+        }
+    }
+
+    let begin = event.begin;
+    agenda.new_event(event)?;
+    println!("Added event at {}", begin.format("%Y-%m-%d %H:%M %Z"));
+    Ok(())
+}
+
+fn convert(input: &str, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    // Times without a zone are meant as local times, UTC is all that is left
+    // if the system does not tell its zone by name
+    let local_tz = tz::local_tz().unwrap_or(Tz::UTC);
+    let time = tz::parse_datetime(input, &Utc::now().with_timezone(&local_tz))?;
+
+    let mut zones: Vec<Tz> = Vec::new();
+    for name in &config.secondary_timezones {
+        match tz::parse_tz(name) {
+            Ok(zone) => zones.push(zone),
+            Err(err) => log::warn!("Ignoring secondary time zone: {}", err),
+        }
+    }
+
+    let width = zones
+        .iter()
+        .map(|zone| zone.name().len())
+        .chain([time.timezone().name().len(), "Local".len()])
+        .max()
+        .unwrap_or(0);
+    let print = |label: &str, time: DateTime<Tz>| {
+        println!(
+            "{:width$}  {}",
+            label,
+            time.format("%Y-%m-%d %H:%M %:z (%Z)"),
+            width = width
+        );
+    };
+
+    print(time.timezone().name(), time);
+    if local_tz != time.timezone() {
+        print("Local", time.with_timezone(&local_tz));
+    }
+    for zone in zones {
+        if zone != time.timezone() && zone != local_tz {
+            print(zone.name(), time.with_timezone(&zone));
+        }
+    }
+    Ok(())
+}
+
+fn doctor(agenda: &mut Agenda, fix: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let conflicts = agenda.uid_conflicts();
+
+    if conflicts.is_empty() {
+        println!("No problems found");
+        return Ok(());
+    }
+
+    for (calendar_id, conflict) in &conflicts {
+        println!("{}: duplicate UID '{}'", calendar_id, conflict.uid);
+        for path in &conflict.paths {
+            println!("    {}", path.display());
+        }
+    }
+
+    if fix {
+        let _suspension = agenda.suspend_watching()?;
+        for (calendar_id, conflict) in conflicts {
+            if let Some(calendar) = agenda.calendar_by_id_mut(&calendar_id) {
+                for path in calendar.resolve_uid_conflict(&conflict.uid)? {
+                    println!("removed {}", path.display());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn publish(agenda: &Agenda, config: &Config, uid: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let spec = config.publish.as_ref().ok_or_else(|| {
+        Error::new(
+            ErrorKind::CalendarMissingKey,
+            "No [publish] section in config",
+        )
+    })?;
+
+    let event = agenda
+        .event_by_uid(uid)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, &format!("No event with UID '{}'", uid)))?;
+
+    println!(
+        "Published to {}",
+        crate::publish::publish(spec, event.event)?
+    );
+    Ok(())
+}
+
+/// E.g. "1d", "2h" or "15m"
+fn describe_amount(duration: Duration) -> String {
+    if duration.num_minutes() % (24 * 60) == 0 {
+        format!("{}d", duration.num_days())
+    } else if duration.num_minutes() % 60 == 0 {
+        format!("{}h", duration.num_hours())
+    } else {
+        format!("{}m", duration.num_minutes())
+    }
+}
+
+/// E.g. "15m before start", "at end", "at 2024-07-03 09:00" or "15m before
+/// start, 3x every 5m"
+fn describe_trigger(alarm: &Alarm) -> String {
+    let point = match alarm.related {
+        AlarmRelation::Start => "start",
+        AlarmRelation::End => "end",
+        AlarmRelation::At(time) => {
+            return describe_repetition(
+                format!("at {}", time.with_timezone(&Local).format("%Y-%m-%d %H:%M")),
+                alarm,
+            )
+        }
+    };
+
+    let trigger = if alarm.offset.is_zero() {
+        format!("at {}", point)
+    } else if alarm.offset < Duration::zero() {
+        format!("{} before {}", describe_amount(alarm.offset.abs()), point)
+    } else {
+        format!("{} after {}", describe_amount(alarm.offset), point)
+    };
+    describe_repetition(trigger, alarm)
+}
+
+fn describe_repetition(trigger: String, alarm: &Alarm) -> String {
+    if alarm.repeat > 0 {
+        format!(
+            "{}, {}x every {}",
+            trigger,
+            alarm.repeat,
+            describe_amount(alarm.interval)
+        )
+    } else {
+        trigger
+    }
+}
+
+impl AlarmCommand {
+    fn run(self, agenda: &mut Agenda, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+        let (uid, change) = match self {
+            AlarmCommand::Plan { window } => {
+                let now = Local::now();
+                for (time, event, alarm) in agenda.alarms_between(now..now + window) {
+                    println!(
+                        "{}  {:<7}  {} ({}, {})  {}",
+                        time.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                        alarm.action,
+                        event.summary(),
+                        event.calendar.id(),
+                        describe_trigger(&alarm),
+                        EventLink::new(&event)
+                    );
+                }
+                return Ok(());
+            }
+            AlarmCommand::Add {
+                uid,
+                trigger,
+                message,
+            } => {
+                let alarm = Alarm {
+                    offset: trigger,
+                    related: AlarmRelation::Start,
+                    action: "DISPLAY".to_owned(),
+                    description: message,
+                    repeat: 0,
+                    interval: Duration::zero(),
+                };
+                (uid, EventChange::AddAlarm(alarm))
+            }
+            AlarmCommand::Remove { uid, trigger } => (uid, EventChange::RemoveAlarm(trigger)),
+            AlarmCommand::Notify => return notify::run_daemon(agenda, config),
+        };
+
+        let calendar = agenda
+            .event_by_uid(&uid)
+            .map(|event| event.calendar.id().to_owned())
+            .ok_or_else(|| {
+                Error::new(ErrorKind::NotFound, &format!("No event with UID '{}'", uid))
+            })?;
+        agenda.apply(
+            "changed alarms of",
+            &[(
+                calendar,
+                EventId::series(&uid),
+                EventOperation::Modify(change),
+            )],
+        )?;
+        Ok(())
+    }
+}
+
+impl CalendarCommand {
+    fn run(self, agenda: &mut Agenda) -> Result<(), Box<dyn std::error::Error>> {
+        let id = match &self {
+            CalendarCommand::Rename { id, .. } | CalendarCommand::SetColor { id, .. } => id,
+        };
+
+        let calendar = agenda.calendar_by_id_mut(id).ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                &format!("No calendar with id '{}'", id),
+            )
+        })?;
+
+        match &self {
+            CalendarCommand::Rename { name, .. } => calendar.rename(name)?,
+            CalendarCommand::SetColor { color, .. } => calendar.set_color(color)?,
+        }
+
+        Ok(())
+    }
+}
@@ -1,4 +1,8 @@
+use crate::credentials::Secret;
+use crate::locale::Locale;
+use chrono::{Datelike, NaiveDate, NaiveTime, Weekday};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::io;
@@ -8,7 +12,7 @@ use toml;
 
 const CONFIG_PATH_ENV_VAR: &str = "JACKAL_CONFIG_FILE";
 
-pub(crate) fn find_configfile() -> io::Result<PathBuf> {
+pub fn find_configfile() -> io::Result<PathBuf> {
     if let Ok(path) = env::var(CONFIG_PATH_ENV_VAR) {
         return Ok(PathBuf::from(path));
     }
@@ -45,17 +49,357 @@ pub struct CollectionSpec {
     pub calendars: Vec<CalendarSpec>,
 }
 
+/// A calendar that shows the events of several other calendars as one, e.g.
+///
+/// ```toml
+/// [[merged]]
+/// id = "family"
+/// color = "#ff8800"
+/// calendars = ["personal", "partner"]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergedCalendarSpec {
+    pub id: String,
+    pub name: Option<String>,
+    pub color: Option<String>,
+    /// Ids of the member calendars, new events are added to the first one
+    pub calendars: Vec<String>,
+}
+
+/// A WebDAV collection single events can be published to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishSpec {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+}
+
+fn default_sync_interval_minutes() -> u32 {
+    15
+}
+
+/// How `jk sync` brings the calendars up to date with a server, e.g.
+///
+/// ```toml
+/// [sync]
+/// command = "vdirsyncer sync"
+/// interval_minutes = 15
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncSpec {
+    /// Run by `sh -c`
+    pub command: String,
+    /// Time between two runs of `jk sync --daemon`
+    #[serde(default = "default_sync_interval_minutes")]
+    pub interval_minutes: u32,
+}
+
+fn default_map_url() -> String {
+    "https://www.openstreetmap.org/?mlat={lat}&mlon={lon}#map=16/{lat}/{lon}".to_owned()
+}
+
+fn default_snooze_minutes() -> Vec<u32> {
+    vec![5, 10, 30]
+}
+
+/// How `jk alarm notify` shows alarms, e.g.
+///
+/// ```toml
+/// [notifications]
+/// snooze_minutes = [5, 10, 30]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationSpec {
+    /// Offered as actions of each notification to show it again later
+    #[serde(default = "default_snooze_minutes")]
+    pub snooze_minutes: Vec<u32>,
+}
+
+impl Default for NotificationSpec {
+    fn default() -> Self {
+        NotificationSpec {
+            snooze_minutes: default_snooze_minutes(),
+        }
+    }
+}
+
+mod time_of_day {
+    use chrono::NaiveTime;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    const FORMAT: &str = "%H:%M";
+
+    pub fn serialize<S: Serializer>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time.format(FORMAT).to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        NaiveTime::parse_from_str(&s, FORMAT).map_err(D::Error::custom)
+    }
+}
+
+mod date {
+    use chrono::NaiveDate;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    const FORMAT: &str = "%Y-%m-%d";
+
+    pub fn serialize<S: Serializer>(date: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&date.format(FORMAT).to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        NaiveDate::parse_from_str(&s, FORMAT).map_err(D::Error::custom)
+    }
+}
+
+mod weekdays {
+    use chrono::Weekday;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(days: &[Weekday], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(days.iter().map(|day| day.to_string()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Weekday>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|day| {
+                day.parse::<Weekday>()
+                    .map_err(|_| D::Error::custom(format!("invalid weekday '{}'", day)))
+            })
+            .collect()
+    }
+}
+
+/// How event titles that are too long for the event list are shown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TitleOverflow {
+    /// Cut off with an ellipsis
+    #[default]
+    Truncate,
+    /// Continued on the next lines, indented to the start of the title
+    Wrap,
+}
+
+/// Colors of the TUI
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+    #[default]
+    Default,
+    /// Bold, underline and inverted text only, e.g. for terminals without
+    /// colors. Also used if NO_COLOR is set.
+    Monochrome,
+}
+
+/// How much of each event the event list shows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Density {
+    /// Title and note of every event, the description of the selected one
+    #[default]
+    Normal,
+    /// A single line per event
+    Compact,
+    /// Location, calendar and attendees on lines of their own as well
+    Detailed,
+}
+
+impl Density {
+    pub fn next(self) -> Self {
+        match self {
+            Density::Normal => Density::Compact,
+            Density::Compact => Density::Detailed,
+            Density::Detailed => Density::Normal,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Density::Normal => "normal",
+            Density::Compact => "compact",
+            Density::Detailed => "detailed",
+        }
+    }
+}
+
+/// How times are formatted, e.g.
+///
+/// ```toml
+/// [formatting]
+/// relative_times = true
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FormattingSpec {
+    /// Show e.g. "in 2h" or "3d ago" next to the times of events
+    #[serde(default)]
+    pub relative_times: bool,
+}
+
+/// How events with a certain category are displayed, e.g.
+///
+/// ```toml
+/// [categories.travel]
+/// color = "#00aaff"
+/// icon = "✈"
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CategorySpec {
+    /// `#RRGGBB` color of the event's summary and its day in the month pane
+    pub color: Option<String>,
+    /// Shown in front of the event's summary
+    pub icon: Option<String>,
+}
+
+/// When to show what is up tomorrow above the event list, e.g.
+///
+/// ```toml
+/// [tomorrow]
+/// from = "20:00"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TomorrowSpec {
+    #[serde(with = "time_of_day")]
+    pub from: NaiveTime,
+}
+
+/// A weekly recurring block like office hours. Blocks are no events, they
+/// are only shown as background of the days they apply to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvailabilitySpec {
+    pub name: String,
+    #[serde(with = "weekdays")]
+    pub days: Vec<Weekday>,
+    #[serde(with = "time_of_day")]
+    pub begin: NaiveTime,
+    #[serde(with = "time_of_day")]
+    pub end: NaiveTime,
+}
+
+impl AvailabilitySpec {
+    pub fn applies_to(&self, date: NaiveDate) -> bool {
+        self.days.contains(&date.weekday())
+    }
+}
+
+/// A day that is highlighted in the month pane, with a label shown above
+/// its events, e.g.
+///
+/// ```toml
+/// [[marked_days]]
+/// date = "2024-12-24"
+/// label = "Christmas Eve"
+/// yearly = true
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkedDaySpec {
+    #[serde(with = "date")]
+    pub date: NaiveDate,
+    pub label: Option<String>,
+    /// Mark the same day in every year
+    #[serde(default)]
+    pub yearly: bool,
+}
+
+impl MarkedDaySpec {
+    pub fn applies_to(&self, date: NaiveDate) -> bool {
+        if self.yearly {
+            (self.date.month(), self.date.day()) == (date.month(), date.day())
+        } else {
+            self.date == date
+        }
+    }
+}
+
 fn default_tick_rate() -> Duration {
     Duration::from_secs(60)
 }
 
+fn default_resize_minutes() -> u32 {
+    15
+}
+
+fn default_archive_after_days() -> u32 {
+    365
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Config {
     #[serde(skip)]
     path: PathBuf,
     #[serde(skip, default = "default_tick_rate")]
     pub tick_rate: Duration,
+    /// Minimum duration in minutes that instants and very short events are
+    /// displayed with
+    #[serde(default)]
+    pub min_display_minutes: u32,
+    /// Minutes by which the end of an event is moved when resizing it
+    #[serde(default = "default_resize_minutes")]
+    pub resize_minutes: u32,
+    /// `jk archive` moves events that ended longer ago into the archive
+    #[serde(default = "default_archive_after_days")]
+    pub archive_after_days: u32,
+    /// Show the time until the current event ends or the next one starts,
+    /// updated every second
+    #[serde(default)]
+    pub countdown: bool,
+    /// Days with more hours of timed events are flagged as overbooked in the
+    /// month pane, e.g. 6
+    #[serde(default)]
+    pub overbooked_hours: Option<f64>,
+    #[serde(default)]
+    pub theme: ThemeName,
+    #[serde(default)]
+    pub title_overflow: TitleOverflow,
+    #[serde(default)]
+    pub density: Density,
+    /// Days after the selected one whose events the event list shows as
+    /// well, changed with { and }
+    #[serde(default)]
+    pub lookahead_days: u32,
+    /// Language of month and weekday names, e.g. "de". Taken from the
+    /// environment (LC_TIME etc.) if not set.
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Your email addresses, to find you among the attendees of invitations
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    /// Timezones that `jk convert` shows times in besides the local one,
+    /// e.g. ["America/New_York", "Asia/Tokyo"]
+    #[serde(default)]
+    pub secondary_timezones: Vec<String>,
+    /// Where to show the position (GEO) of an event, `{lat}` and `{lon}` are
+    /// replaced by its coordinates, e.g.
+    /// "https://www.google.com/maps/search/?api=1&query={lat},{lon}"
+    #[serde(default = "default_map_url")]
+    pub map_url: String,
+    #[serde(default)]
+    pub formatting: FormattingSpec,
     pub collections: Vec<CollectionSpec>,
+    #[serde(default)]
+    pub merged: Vec<MergedCalendarSpec>,
+    pub publish: Option<PublishSpec>,
+    pub sync: Option<SyncSpec>,
+    #[serde(default)]
+    pub notifications: NotificationSpec,
+    pub tomorrow: Option<TomorrowSpec>,
+    #[serde(default)]
+    pub availability: Vec<AvailabilitySpec>,
+    /// Display settings by category name, matched case-insensitively
+    #[serde(default)]
+    pub categories: BTreeMap<String, CategorySpec>,
+    /// Ids of calendars whose all-day events are public holidays, their
+    /// days are marked like `marked_days`
+    #[serde(default)]
+    pub holiday_calendars: Vec<String>,
+    #[serde(default)]
+    pub marked_days: Vec<MarkedDaySpec>,
 }
 
 impl Default for Config {
@@ -67,7 +411,30 @@ impl Default for Config {
                 PathBuf::from("jackal.toml")
             },
             tick_rate: Duration::from_secs(60),
+            min_display_minutes: 0,
+            resize_minutes: default_resize_minutes(),
+            archive_after_days: default_archive_after_days(),
+            countdown: false,
+            overbooked_hours: None,
+            theme: ThemeName::default(),
+            title_overflow: TitleOverflow::default(),
+            density: Density::default(),
+            lookahead_days: 0,
+            locale: None,
+            addresses: Vec::new(),
+            secondary_timezones: Vec::new(),
+            map_url: default_map_url(),
+            formatting: FormattingSpec::default(),
             collections: Vec::new(),
+            merged: Vec::new(),
+            publish: None,
+            sync: None,
+            notifications: NotificationSpec::default(),
+            tomorrow: None,
+            availability: Vec::new(),
+            categories: BTreeMap::new(),
+            holiday_calendars: Vec::new(),
+            marked_days: Vec::new(),
         }
     }
 }
@@ -84,6 +451,43 @@ impl Config {
         Ok(())
     }
 
+    /// Sets the top-level `key` in the config file to `value`, leaving the
+    /// rest of the file including comments as it is
+    pub fn store_setting(&self, key: &str, value: toml::Value) -> io::Result<()> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        let mut lines: Vec<String> = content.lines().map(str::to_owned).collect();
+        let setting = format!("{} = {}", key, value);
+
+        // Top-level keys have to precede all tables
+        let num_top_level = lines
+            .iter()
+            .position(|line| line.trim_start().starts_with('['))
+            .unwrap_or(lines.len());
+        match lines[..num_top_level]
+            .iter()
+            .position(|line| line.split('=').next().map(str::trim) == Some(key))
+        {
+            Some(idx) => lines[idx] = setting,
+            None => lines.insert(0, setting),
+        }
+
+        fs::write(&self.path, lines.join("\n") + "\n")
+    }
+
+    pub fn locale(&self) -> Locale {
+        match &self.locale {
+            Some(name) => Locale::from_name(name).unwrap_or_else(|| {
+                log::warn!("Unsupported locale '{}', using the environment's", name);
+                Locale::from_env()
+            }),
+            None => Locale::from_env(),
+        }
+    }
+
     pub fn collection_config_for(&self, id: &str) -> Option<&CollectionSpec> {
         self.collections.iter().find(|c| &c.name == id)
     }
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Local};
+use flexi_logger::writers::LogWriter;
+use flexi_logger::DeferredNow;
+use log::{Level, LevelFilter, Record};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+const CAPACITY: usize = 200;
+
+static ENTRIES: Mutex<VecDeque<LogEntry>> = Mutex::new(VecDeque::new());
+
+#[derive(Clone, Debug)]
+pub struct LogEntry {
+    pub time: DateTime<Local>,
+    pub level: Level,
+    pub message: String,
+}
+
+/// Keeps the most recent warnings and errors in memory so that they can be
+/// shown inside the TUI
+pub struct ConsoleSink;
+
+impl LogWriter for ConsoleSink {
+    fn write(&self, _now: &mut DeferredNow, record: &Record) -> std::io::Result<()> {
+        if record.level() > Level::Warn {
+            return Ok(());
+        }
+
+        if let Ok(mut entries) = ENTRIES.lock() {
+            if entries.len() == CAPACITY {
+                entries.pop_front();
+            }
+            entries.push_back(LogEntry {
+                time: Local::now(),
+                level: record.level(),
+                message: record.args().to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn flush(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn max_log_level(&self) -> LevelFilter {
+        LevelFilter::Warn
+    }
+}
+
+/// The recorded entries, oldest first, whose message contains `filter`
+/// (ignoring case)
+pub fn entries(filter: &str) -> Vec<LogEntry> {
+    let filter = filter.to_lowercase();
+    match ENTRIES.lock() {
+        Ok(entries) => entries
+            .iter()
+            .filter(|entry| entry.message.to_lowercase().contains(&filter))
+            .cloned()
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
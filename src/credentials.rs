@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::io;
+use std::process::Command;
+
+/// A secret referenced from the config file, e.g.
+///
+/// ```toml
+/// password = { command = "pass show calendar/dav" }
+/// password = { env = "JACKAL_DAV_PASSWORD" }
+/// password = { keyring = { service = "jackal", account = "dav" } }
+/// ```
+///
+/// A plain string is accepted as well, but is stored unprotected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Secret {
+    Plain(String),
+    Command {
+        command: String,
+    },
+    Env {
+        env: String,
+    },
+    /// Looked up in the Secret Service (libsecret) by its attributes
+    Keyring {
+        keyring: BTreeMap<String, String>,
+    },
+}
+
+fn run(command: &mut Command, description: &str) -> io::Result<String> {
+    let output = command.output().map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("Could not run {}: {}", description, err),
+        )
+    })?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "{} failed: {}",
+            description,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    // Password managers like pass put the secret on the first line
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().next().unwrap_or_default().to_owned())
+}
+
+impl Secret {
+    pub fn resolve(&self) -> io::Result<String> {
+        match self {
+            Secret::Plain(secret) => Ok(secret.clone()),
+            Secret::Command { command } => run(
+                Command::new("sh").arg("-c").arg(command),
+                &format!("'{}'", command),
+            ),
+            Secret::Env { env: var } => env::var(var).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Environment variable '{}' is not set", var),
+                )
+            }),
+            Secret::Keyring { keyring } => run(
+                Command::new("secret-tool")
+                    .arg("lookup")
+                    .args(keyring.iter().flat_map(|(k, v)| [k, v])),
+                "secret-tool",
+            ),
+        }
+    }
+}
@@ -5,20 +5,86 @@ use std::sync::{
     mpsc, Arc,
 };
 use std::thread;
+use std::time::Duration;
 
 use unsegen::input::Input;
 
+use crate::sync::{self, SyncListener};
 use config::Config;
 
 pub enum Event {
     Input(Input),
     Update,
+    /// The terminal size changed and everything has to be laid out again
+    Resize,
+    /// `jk sync` changed the calendars, see `sync::listen`
+    ExternalModification,
+}
+
+const RESIZE_POLL_INTERVAL: Duration = Duration::from_millis(200);
+const INPUT_POLL_TIMEOUT_MS: i32 = 100;
+
+/// Stdin that is only read while input is not paused. Waiting for input with
+/// a timeout instead of blocking in `read` leaves all keys to programs that
+/// run in the foreground meanwhile, e.g. an editor.
+struct PausableStdin {
+    paused: Arc<AtomicBool>,
+}
+
+impl io::Read for PausableStdin {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.paused.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(INPUT_POLL_TIMEOUT_MS as u64));
+                continue;
+            }
+
+            let mut fd = libc::pollfd {
+                fd: libc::STDIN_FILENO,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            // Safe, the pointer refers to exactly one pollfd
+            let ready = unsafe { libc::poll(&mut fd, 1, INPUT_POLL_TIMEOUT_MS) };
+            if ready < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err);
+            }
+            if ready > 0 {
+                // Unbuffered, so that poll sees everything that was not read
+                let read =
+                    unsafe { libc::read(libc::STDIN_FILENO, buf.as_mut_ptr().cast(), buf.len()) };
+                return if read < 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(read as usize)
+                };
+            }
+        }
+    }
+}
+
+/// Input is not read while this exists, see `Dispatcher::pause_input`
+pub struct InputPause {
+    paused: Arc<AtomicBool>,
+}
+
+impl Drop for InputPause {
+    fn drop(&mut self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
 }
 
 pub struct Dispatcher {
     rx: mpsc::Receiver<Event>,
+    input_paused: Arc<AtomicBool>,
     _input_handle: thread::JoinHandle<()>,
     _update_handle: thread::JoinHandle<()>,
+    _resize_handle: thread::JoinHandle<()>,
+    _sync_listener: Option<SyncListener>,
 }
 
 impl Default for Dispatcher {
@@ -29,13 +95,19 @@ impl Default for Dispatcher {
 
 impl Dispatcher {
     pub fn from_config(config: &Config) -> Dispatcher {
-        let tick_rate = config.tick_rate.clone();
+        let tick_rate = if config.countdown {
+            config.tick_rate.min(Duration::from_secs(1))
+        } else {
+            config.tick_rate
+        };
         let (tx, rx) = mpsc::channel();
+        let input_paused = Arc::new(AtomicBool::new(false));
         let input_handle = {
             let tx = tx.clone();
+            let stdin = PausableStdin {
+                paused: input_paused.clone(),
+            };
             thread::spawn(move || {
-                let stdin = io::stdin();
-                let stdin = stdin.lock();
                 for evt in Input::read_all(stdin) {
                     match evt {
                         Ok(key) => {
@@ -48,6 +120,29 @@ impl Dispatcher {
                 }
             })
         };
+        let resize_handle = {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let mut size = termion::terminal_size().ok();
+                loop {
+                    thread::sleep(RESIZE_POLL_INTERVAL);
+                    let new_size = termion::terminal_size().ok();
+                    if new_size != size {
+                        size = new_size;
+                        if tx.send(Event::Resize).is_err() {
+                            return;
+                        }
+                    }
+                }
+            })
+        };
+        let sync_listener = match sync::listen(tx.clone()) {
+            Ok(listener) => Some(listener),
+            Err(e) => {
+                log::warn!("Could not listen for changes by jk sync: {}", e);
+                None
+            }
+        };
         let update_handle = {
             thread::spawn(move || {
                 let tx = tx.clone();
@@ -59,12 +154,24 @@ impl Dispatcher {
         };
         Dispatcher {
             rx,
+            input_paused,
             _input_handle: input_handle,
             _update_handle: update_handle,
+            _resize_handle: resize_handle,
+            _sync_listener: sync_listener,
         }
     }
 
     pub fn next(&self) -> Result<Event, mpsc::RecvError> {
         self.rx.recv()
     }
+
+    /// Stops reading input until the returned guard is dropped, e.g. while
+    /// an editor runs in the terminal
+    pub fn pause_input(&self) -> InputPause {
+        self.input_paused.store(true, Ordering::SeqCst);
+        InputPause {
+            paused: self.input_paused.clone(),
+        }
+    }
 }
@@ -0,0 +1,348 @@
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+
+use crate::state::{Migration, StateFile};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Version 0 was appended to without a version, its entries are the same
+const MIGRATIONS: &[Migration] = &[Ok];
+/// Older entries are dropped so that the file, which is rewritten with
+/// every change, stays small
+const MAX_ENTRIES: usize = 1000;
+
+/// Changes of these properties are bookkeeping, not changes of the event
+const IGNORED_PROPERTIES: &[&str] = &["DTSTAMP", "LAST-MODIFIED", "SEQUENCE"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Create,
+    Modify,
+    Delete,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// A change of a single event. Entries of the same batch share its id and
+/// are undone together.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    /// RFC 3339
+    pub time: String,
+    pub batch: u64,
+    pub description: String,
+    pub action: Action,
+    pub calendar: String,
+    pub uid: String,
+    /// The batch that this entry reverts if it is part of an undo
+    pub undoes: Option<u64>,
+    /// The event before and after the change as iCalendar data
+    pub before: Option<String>,
+    pub after: Option<String>,
+    #[serde(default)]
+    pub changes: Vec<FieldChange>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct History {
+    #[serde(default)]
+    entries: Vec<Entry>,
+}
+
+fn history_file() -> Result<StateFile> {
+    StateFile::new("history.toml", MIGRATIONS)
+}
+
+/// A new batch id, unique as long as batches are not started within the
+/// same microsecond
+pub fn new_batch() -> u64 {
+    Utc::now().timestamp_micros() as u64
+}
+
+/// The properties of the events and tasks of `ical` by name, e.g. "SUMMARY"
+/// or "VALARM/TRIGGER" for properties of alarms
+fn properties(ical: &str) -> BTreeMap<String, String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ical.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.trim_end().to_owned()),
+        }
+    }
+
+    let mut components: Vec<String> = Vec::new();
+    let mut properties = BTreeMap::<String, String>::new();
+    for line in lines {
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name, value),
+            None => continue,
+        };
+        // Parameters like TZID are part of the field's value
+        let (name, value) = match name.split_once(';') {
+            Some((name, params)) => (name, format!("{} ({})", value, params)),
+            None => (name, value.to_owned()),
+        };
+        match name {
+            "BEGIN" => components.push(value),
+            "END" => {
+                components.pop();
+            }
+            _ if !matches!(
+                components.get(1).map(String::as_str),
+                Some("VEVENT" | "VTODO")
+            ) || IGNORED_PROPERTIES.contains(&name) => {}
+            _ => {
+                // Nested in VCALENDAR and the event or task
+                let field = match &components[2..] {
+                    [] => name.to_owned(),
+                    nested => format!("{}/{}", nested.join("/"), name),
+                };
+                let joined = properties.entry(field).or_default();
+                if !joined.is_empty() {
+                    joined.push_str(", ");
+                }
+                joined.push_str(&value);
+            }
+        }
+    }
+    properties
+}
+
+/// The properties that differ between two versions of an event
+fn field_changes(before: Option<&str>, after: Option<&str>) -> Vec<FieldChange> {
+    let before = before.map(properties).unwrap_or_default();
+    let after = after.map(properties).unwrap_or_default();
+    let fields: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    fields
+        .into_iter()
+        .filter(|field| before.get(*field) != after.get(*field))
+        .map(|field| FieldChange {
+            field: field.clone(),
+            old: before.get(field).cloned(),
+            new: after.get(field).cloned(),
+        })
+        .collect()
+}
+
+impl Entry {
+    /// The entry for changing an event from `before` to `after`, `None` if
+    /// nothing changed
+    pub fn new(
+        batch: u64,
+        description: &str,
+        calendar: &str,
+        uid: &str,
+        before: Option<String>,
+        after: Option<String>,
+    ) -> Option<Self> {
+        let action = match (&before, &after) {
+            (None, Some(_)) => Action::Create,
+            (Some(_), None) => Action::Delete,
+            (Some(before), Some(after)) if before != after => Action::Modify,
+            _ => return None,
+        };
+        Some(Entry {
+            time: Utc::now().to_rfc3339(),
+            batch,
+            description: description.to_owned(),
+            action,
+            calendar: calendar.to_owned(),
+            uid: uid.to_owned(),
+            undoes: None,
+            changes: field_changes(before.as_deref(), after.as_deref()),
+            before,
+            after,
+        })
+    }
+
+    pub fn undoing(mut self, batch: u64) -> Self {
+        self.undoes = Some(batch);
+        self
+    }
+
+    /// Whether the event changed since this entry, given its current
+    /// iCalendar data. Bookkeeping and formatting do not count.
+    pub fn is_outdated(&self, current: Option<&str>) -> bool {
+        match (self.after.as_deref(), current) {
+            (Some(after), Some(current)) => !field_changes(Some(after), Some(current)).is_empty(),
+            (after, current) => after.is_some() != current.is_some(),
+        }
+    }
+}
+
+/// Appends `entries` to the history in the state directory, dropping the
+/// oldest batches beyond `MAX_ENTRIES`
+pub fn record(entries: &[Entry]) -> Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    let file = history_file()?;
+    let mut history: History = file.load()?;
+    history.entries.extend_from_slice(entries);
+    if history.entries.len() > MAX_ENTRIES {
+        let cut = history.entries.len() - MAX_ENTRIES;
+        // Batches are undone as a whole, none is kept in part
+        let partial = history.entries[cut - 1].batch;
+        let cut = cut
+            + history.entries[cut..]
+                .iter()
+                .take_while(|entry| entry.batch == partial)
+                .count();
+        history.entries.drain(..cut);
+    }
+    file.save(&history)
+}
+
+/// All recorded entries, oldest first
+pub fn load() -> Result<Vec<Entry>> {
+    let history: History = history_file()?.load()?;
+    Ok(history.entries)
+}
+
+/// The entries of the latest batch that was neither undone nor is an undo
+/// itself, e.g. of an earlier session
+pub fn last_undoable() -> Result<Vec<Entry>> {
+    let entries = load()?;
+    let undone: BTreeSet<u64> = entries.iter().filter_map(|entry| entry.undoes).collect();
+    let batch = entries
+        .iter()
+        .rev()
+        .find(|entry| entry.undoes.is_none() && !undone.contains(&entry.batch))
+        .map(|entry| entry.batch);
+
+    Ok(entries
+        .into_iter()
+        .filter(|entry| Some(entry.batch) == batch)
+        .collect())
+}
+
+/// Prints the latest `limit` entries, newest first
+pub fn print(limit: usize, out: &mut impl Write) -> Result<()> {
+    let entries = load()?;
+    for entry in entries.iter().rev().take(limit) {
+        let action = match (entry.action, entry.undoes) {
+            (_, Some(_)) => "undo",
+            (Action::Create, None) => "create",
+            (Action::Modify, None) => "modify",
+            (Action::Delete, None) => "delete",
+        };
+        let time = DateTime::parse_from_rfc3339(&entry.time)
+            .map(|time| {
+                time.with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_else(|_| entry.time.clone());
+        writeln!(
+            out,
+            "{}  {:<6}  {}/{}  {}",
+            time, action, entry.calendar, entry.uid, entry.description
+        )?;
+        // The fields of created and deleted events are no news
+        if entry.action != Action::Modify {
+            continue;
+        }
+        for change in &entry.changes {
+            writeln!(
+                out,
+                "    {}: {} -> {}",
+                change.field,
+                change.old.as_deref().unwrap_or("(none)"),
+                change.new.as_deref().unwrap_or("(none)")
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EVENT: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+UID:event-1@jackal\r
+DTSTAMP:20261015T080000Z\r
+DTSTART;TZID=Europe/Berlin:20261015T093000\r
+SUMMARY:Planning\r
+BEGIN:VALARM\r
+TRIGGER:-PT15M\r
+END:VALARM\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    const TODO: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VTODO\r
+UID:todo-1@jackal\r
+DTSTAMP:20261015T080000Z\r
+SUMMARY:Write minutes\r
+STATUS:NEEDS-ACTION\r
+END:VTODO\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn event_changes() {
+        let after = EVENT
+            .replace("SUMMARY:Planning", "SUMMARY:Review")
+            .replace("-PT15M", "-PT5M")
+            .replace("20261015T080000Z", "20261015T090000Z");
+        let changes: Vec<_> = field_changes(Some(EVENT), Some(&after))
+            .into_iter()
+            .map(|change| (change.field, change.old, change.new))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (
+                    "SUMMARY".to_owned(),
+                    Some("Planning".to_owned()),
+                    Some("Review".to_owned())
+                ),
+                (
+                    "VALARM/TRIGGER".to_owned(),
+                    Some("-PT15M".to_owned()),
+                    Some("-PT5M".to_owned())
+                ),
+            ]
+        );
+        assert_eq!(
+            properties(EVENT).get("DTSTART").map(String::as_str),
+            Some("20261015T093000 (TZID=Europe/Berlin)")
+        );
+    }
+
+    #[test]
+    fn todo_changes() {
+        let done = TODO.replace("NEEDS-ACTION", "COMPLETED");
+        let changes = field_changes(Some(TODO), Some(&done));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "STATUS");
+        assert_eq!(changes[0].new.as_deref(), Some("COMPLETED"));
+
+        let entry = Entry::new(
+            1,
+            "marked done",
+            "work",
+            "todo-1@jackal",
+            Some(TODO.to_owned()),
+            Some(done.clone()),
+        )
+        .unwrap();
+        assert!(!entry.is_outdated(Some(&done)));
+        let edited = done.replace("Write minutes", "Send minutes");
+        assert!(entry.is_outdated(Some(&edited)));
+    }
+}
@@ -0,0 +1,37 @@
+//! Jackal's calendars as a library, see [`api`] for the interface meant for
+//! other programs.
+//!
+//! The remaining modules implement the `jk` and `jk-sync` binaries. They are
+//! public only so that the binaries can use them and are not covered by
+//! semantic versioning.
+
+pub mod api;
+
+#[doc(hidden)]
+pub mod agenda;
+mod annotations;
+mod backup;
+#[doc(hidden)]
+pub mod cli;
+#[doc(hidden)]
+pub mod config;
+#[doc(hidden)]
+pub mod console;
+mod credentials;
+#[doc(hidden)]
+pub mod events;
+mod history;
+mod link;
+mod locale;
+mod mail;
+mod notify;
+mod print;
+mod provider;
+mod publish;
+mod state;
+#[doc(hidden)]
+pub mod sync;
+#[doc(hidden)]
+pub mod timing;
+#[doc(hidden)]
+pub mod ui;
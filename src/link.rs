@@ -0,0 +1,185 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::fmt;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use crate::agenda::Agenda;
+use crate::provider::{CalendarEvent, Error, ErrorKind};
+
+const SCHEME: &str = "jackal://";
+
+/// Same as iCalendar UTC times, e.g. 20261016T080000Z
+const START_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Link to a single occurrence of an event that other tools can hand back
+/// to jackal, e.g. `jackal://work/0d5ab8e4%40example.com?start=20261016T080000Z`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventLink {
+    pub calendar: String,
+    pub uid: String,
+    /// Start of the occurrence, any occurrence if `None`
+    pub start: Option<DateTime<Utc>>,
+}
+
+/// Escapes everything but unreserved characters (RFC 3986)
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Fills in the latitude and longitude for `{lat}` and `{lon}` in
+/// `template`, see `Config::map_url`
+pub fn map_link(template: &str, (lat, lon): (f64, f64)) -> String {
+    template
+        .replace("{lat}", &lat.to_string())
+        .replace("{lon}", &lon.to_string())
+}
+
+/// Schemes of the URLs `open_url` accepts. URLs come from events of other
+/// people, which must not open local files or run other handlers.
+const OPENABLE_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Opens `url` with $BROWSER or the desktop's default application
+pub fn open_url(url: &str) -> Result<(), String> {
+    let scheme = url.split_once(':').map(|(scheme, _)| scheme);
+    if !scheme.is_some_and(|scheme| {
+        OPENABLE_SCHEMES
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+    }) {
+        return Err(format!("not opening {}, only web and mail links are", url));
+    }
+    let opener = std::env::var("BROWSER").unwrap_or_else(|_| {
+        if cfg!(target_os = "macos") {
+            "open".to_owned()
+        } else {
+            "xdg-open".to_owned()
+        }
+    });
+    Command::new(&opener)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("could not run {}: {}", opener, e))
+}
+
+fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::new();
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+impl EventLink {
+    pub fn new(event: &CalendarEvent<'_>) -> Self {
+        EventLink {
+            calendar: event.calendar.id().to_owned(),
+            uid: event.uid().to_owned(),
+            start: Some(event.begin().with_timezone(&Utc)),
+        }
+    }
+
+    /// The linked event, of a recurring one the instance at the linked start.
+    /// Events that have moved since the link was created are still found,
+    /// check `matches_start` to tell.
+    pub fn resolve<'a>(&self, agenda: &'a Agenda) -> Option<CalendarEvent<'a>> {
+        let calendar = agenda.calendar_by_id(&self.calendar)?;
+        let mut events = calendar
+            .event_iter()
+            .filter(|event| event.uid() == self.uid)
+            .map(|event| CalendarEvent { calendar, event });
+        let first = events.next()?;
+        if self.matches_start(&first) {
+            return Some(first);
+        }
+        Some(
+            events
+                .find(|event| self.matches_start(event))
+                .unwrap_or(first),
+        )
+    }
+
+    /// Whether `event` begins at the linked start
+    pub fn matches_start(&self, event: &CalendarEvent<'_>) -> bool {
+        self.start
+            .is_none_or(|start| event.begin().with_timezone(&Utc) == start)
+    }
+}
+
+impl fmt::Display for EventLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}/{}",
+            SCHEME,
+            percent_encode(&self.calendar),
+            percent_encode(&self.uid)
+        )?;
+        if let Some(start) = self.start {
+            write!(f, "?start={}", start.format(START_FORMAT))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for EventLink {
+    type Err = Error;
+
+    fn from_str(link: &str) -> Result<Self, Error> {
+        let invalid = |msg: &str| Error::new(ErrorKind::Invalid, &format!("{}: {}", msg, link));
+
+        let rest = link
+            .strip_prefix(SCHEME)
+            .ok_or_else(|| invalid("Not a jackal:// link"))?;
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (rest, None),
+        };
+        let (calendar, uid) = path
+            .split_once('/')
+            .ok_or_else(|| invalid("Missing UID in link"))?;
+
+        let mut start = None;
+        for parameter in query.into_iter().flat_map(|query| query.split('&')) {
+            match parameter.split_once('=') {
+                Some(("start", value)) => {
+                    let time = NaiveDateTime::parse_from_str(value, START_FORMAT)
+                        .map_err(|_| invalid("Invalid start in link"))?;
+                    start = Some(DateTime::from_naive_utc_and_offset(time, Utc));
+                }
+                // Left for future versions
+                _ => log::debug!("Ignoring parameter '{}' of link", parameter),
+            }
+        }
+
+        let decode = |text: &str| {
+            percent_decode(text)
+                .filter(|text| !text.is_empty())
+                .ok_or_else(|| invalid("Invalid link"))
+        };
+        Ok(EventLink {
+            calendar: decode(calendar)?,
+            uid: decode(uid)?,
+            start,
+        })
+    }
+}
@@ -0,0 +1,360 @@
+use chrono::{DateTime, Datelike, Month, NaiveDate, TimeZone, Weekday};
+use std::env;
+use std::fmt::Display;
+
+struct Names {
+    months: [&'static str; 12],
+    months_abbr: [&'static str; 12],
+    /// Starting on Monday
+    weekdays: [&'static str; 7],
+    weekdays_abbr: [&'static str; 7],
+}
+
+/// Languages with their names, the first one is the default
+const LANGUAGES: &[(&str, Names)] = &[
+    (
+        "en",
+        Names {
+            months: [
+                "January",
+                "February",
+                "March",
+                "April",
+                "May",
+                "June",
+                "July",
+                "August",
+                "September",
+                "October",
+                "November",
+                "December",
+            ],
+            months_abbr: [
+                "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+            ],
+            weekdays: [
+                "Monday",
+                "Tuesday",
+                "Wednesday",
+                "Thursday",
+                "Friday",
+                "Saturday",
+                "Sunday",
+            ],
+            weekdays_abbr: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+        },
+    ),
+    (
+        "de",
+        Names {
+            months: [
+                "Januar",
+                "Februar",
+                "März",
+                "April",
+                "Mai",
+                "Juni",
+                "Juli",
+                "August",
+                "September",
+                "Oktober",
+                "November",
+                "Dezember",
+            ],
+            months_abbr: [
+                "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+            ],
+            weekdays: [
+                "Montag",
+                "Dienstag",
+                "Mittwoch",
+                "Donnerstag",
+                "Freitag",
+                "Samstag",
+                "Sonntag",
+            ],
+            weekdays_abbr: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+        },
+    ),
+    (
+        "fr",
+        Names {
+            months: [
+                "janvier",
+                "février",
+                "mars",
+                "avril",
+                "mai",
+                "juin",
+                "juillet",
+                "août",
+                "septembre",
+                "octobre",
+                "novembre",
+                "décembre",
+            ],
+            months_abbr: [
+                "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.",
+                "nov.", "déc.",
+            ],
+            weekdays: [
+                "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+            ],
+            weekdays_abbr: ["lun", "mar", "mer", "jeu", "ven", "sam", "dim"],
+        },
+    ),
+    (
+        "es",
+        Names {
+            months: [
+                "enero",
+                "febrero",
+                "marzo",
+                "abril",
+                "mayo",
+                "junio",
+                "julio",
+                "agosto",
+                "septiembre",
+                "octubre",
+                "noviembre",
+                "diciembre",
+            ],
+            months_abbr: [
+                "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sep", "oct", "nov", "dic",
+            ],
+            weekdays: [
+                "lunes",
+                "martes",
+                "miércoles",
+                "jueves",
+                "viernes",
+                "sábado",
+                "domingo",
+            ],
+            weekdays_abbr: ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"],
+        },
+    ),
+    (
+        "it",
+        Names {
+            months: [
+                "gennaio",
+                "febbraio",
+                "marzo",
+                "aprile",
+                "maggio",
+                "giugno",
+                "luglio",
+                "agosto",
+                "settembre",
+                "ottobre",
+                "novembre",
+                "dicembre",
+            ],
+            months_abbr: [
+                "gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott", "nov", "dic",
+            ],
+            weekdays: [
+                "lunedì",
+                "martedì",
+                "mercoledì",
+                "giovedì",
+                "venerdì",
+                "sabato",
+                "domenica",
+            ],
+            weekdays_abbr: ["lun", "mar", "mer", "gio", "ven", "sab", "dom"],
+        },
+    ),
+    (
+        "nl",
+        Names {
+            months: [
+                "januari",
+                "februari",
+                "maart",
+                "april",
+                "mei",
+                "juni",
+                "juli",
+                "augustus",
+                "september",
+                "oktober",
+                "november",
+                "december",
+            ],
+            months_abbr: [
+                "jan", "feb", "mrt", "apr", "mei", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
+            ],
+            weekdays: [
+                "maandag",
+                "dinsdag",
+                "woensdag",
+                "donderdag",
+                "vrijdag",
+                "zaterdag",
+                "zondag",
+            ],
+            weekdays_abbr: ["ma", "di", "wo", "do", "vr", "za", "zo"],
+        },
+    ),
+    (
+        "pt",
+        Names {
+            months: [
+                "janeiro",
+                "fevereiro",
+                "março",
+                "abril",
+                "maio",
+                "junho",
+                "julho",
+                "agosto",
+                "setembro",
+                "outubro",
+                "novembro",
+                "dezembro",
+            ],
+            months_abbr: [
+                "jan", "fev", "mar", "abr", "mai", "jun", "jul", "ago", "set", "out", "nov", "dez",
+            ],
+            weekdays: [
+                "segunda-feira",
+                "terça-feira",
+                "quarta-feira",
+                "quinta-feira",
+                "sexta-feira",
+                "sábado",
+                "domingo",
+            ],
+            weekdays_abbr: ["seg", "ter", "qua", "qui", "sex", "sáb", "dom"],
+        },
+    ),
+    (
+        "sv",
+        Names {
+            months: [
+                "januari",
+                "februari",
+                "mars",
+                "april",
+                "maj",
+                "juni",
+                "juli",
+                "augusti",
+                "september",
+                "oktober",
+                "november",
+                "december",
+            ],
+            months_abbr: [
+                "jan", "feb", "mar", "apr", "maj", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
+            ],
+            weekdays: [
+                "måndag", "tisdag", "onsdag", "torsdag", "fredag", "lördag", "söndag",
+            ],
+            weekdays_abbr: ["mån", "tis", "ons", "tor", "fre", "lör", "sön"],
+        },
+    ),
+];
+
+/// Month and weekday names of a language. Everything else (order, numbers)
+/// is still given by the format strings.
+#[derive(Clone, Copy)]
+pub struct Locale {
+    names: &'static Names,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale {
+            names: &LANGUAGES[0].1,
+        }
+    }
+}
+
+impl Locale {
+    /// The locale of a POSIX locale name like `de_DE.UTF-8` or just `de`
+    pub fn from_name(name: &str) -> Option<Self> {
+        let language = name
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        if language == "c" || language == "posix" {
+            return Some(Locale::default());
+        }
+
+        LANGUAGES
+            .iter()
+            .find(|(lang, _)| *lang == language)
+            .map(|(_, names)| Locale { names })
+    }
+
+    /// The locale for times as set in the environment, English if it is not
+    /// set or not supported
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_TIME", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Locale::from_name(&value))
+            .unwrap_or_default()
+    }
+
+    pub fn month_name(&self, month: Month) -> &'static str {
+        self.names.months[month.number_from_month() as usize - 1]
+    }
+
+    pub fn weekday_abbr(&self, weekday: Weekday) -> &'static str {
+        self.names.weekdays_abbr[weekday.num_days_from_monday() as usize]
+    }
+
+    /// `fmt` with the names (%A, %a, %B and %b) of `date` filled in
+    fn pattern(&self, fmt: &str, date: &impl Datelike) -> String {
+        let month = date.month0() as usize;
+        let weekday = date.weekday().num_days_from_monday() as usize;
+
+        let mut pattern = String::with_capacity(fmt.len());
+        let mut chars = fmt.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                pattern.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('A') => pattern.push_str(self.names.weekdays[weekday]),
+                Some('a') => pattern.push_str(self.names.weekdays_abbr[weekday]),
+                Some('B') => pattern.push_str(self.names.months[month]),
+                Some('b') => pattern.push_str(self.names.months_abbr[month]),
+                Some(other) => {
+                    pattern.push('%');
+                    pattern.push(other);
+                }
+                None => pattern.push('%'),
+            }
+        }
+        pattern
+    }
+}
+
+/// Formatting like chrono's `format`, but with names in a `Locale`
+pub trait FormatLocalized {
+    fn format_in(&self, locale: &Locale, fmt: &str) -> String;
+}
+
+impl FormatLocalized for NaiveDate {
+    fn format_in(&self, locale: &Locale, fmt: &str) -> String {
+        self.format(&locale.pattern(fmt, self)).to_string()
+    }
+}
+
+impl<Tz: TimeZone> FormatLocalized for DateTime<Tz>
+where
+    Tz::Offset: Display,
+{
+    fn format_in(&self, locale: &Locale, fmt: &str) -> String {
+        self.format(&locale.pattern(fmt, self)).to_string()
+    }
+}
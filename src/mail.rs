@@ -0,0 +1,290 @@
+// Minimal reading of RFC 822 / MIME messages, just enough to get at the
+// calendar data of invitations
+
+/// Parts with these content types are calendar data
+const CALENDAR_TYPES: [&str; 3] = ["text/calendar", "application/ics", "text/x-vcalendar"];
+
+/// Header fields of a message or part with folded lines joined. Names are in
+/// lower case.
+fn split_headers(part: &[u8]) -> (Vec<(String, String)>, &[u8]) {
+    let text = String::from_utf8_lossy(part);
+    let mut headers: Vec<(String, String)> = Vec::new();
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            break;
+        }
+
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+        }
+    }
+
+    // Lossy decoding may have changed the length of the header block
+    let body_start = if text.len() == part.len() {
+        offset
+    } else {
+        find(part, b"\r\n\r\n")
+            .map(|idx| idx + 4)
+            .or_else(|| find(part, b"\n\n").map(|idx| idx + 2))
+            .unwrap_or(part.len())
+    };
+    (headers, &part[body_start.min(part.len())..])
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// The value of a header without parameters in lower case, e.g. "text/plain"
+fn main_value(value: &str) -> String {
+    value
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// The parameter `name` of a header value like `multipart/mixed; boundary="x"`
+fn parameter(value: &str, name: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        if key.trim().eq_ignore_ascii_case(name) {
+            Some(value.trim().trim_matches('"').to_owned())
+        } else {
+            None
+        }
+    })
+}
+
+fn decode_base64(data: &[u8]) -> Vec<u8> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+
+    let mut decoded = Vec::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    // Line breaks, padding and garbage are skipped
+    for sextet in data.iter().filter_map(|c| value(*c)) {
+        buffer = buffer << 6 | sextet as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    decoded
+}
+
+fn decode_quoted_printable(data: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::new();
+    let mut idx = 0;
+    while idx < data.len() {
+        if data[idx] != b'=' {
+            decoded.push(data[idx]);
+            idx += 1;
+            continue;
+        }
+
+        let rest = &data[idx + 1..];
+        if rest.starts_with(b"\r\n") {
+            // Soft line break
+            idx += 3;
+        } else if rest.starts_with(b"\n") {
+            idx += 2;
+        } else if let Some(byte) = rest
+            .get(..2)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            idx += 3;
+        } else {
+            decoded.push(b'=');
+            idx += 1;
+        }
+    }
+    decoded
+}
+
+/// Text of `data` in `charset`, which is assumed to be UTF-8 unless it is
+/// Latin-1
+fn decode_charset(data: &[u8], charset: Option<&str>) -> String {
+    match charset.map(str::to_ascii_lowercase).as_deref() {
+        Some("iso-8859-1") | Some("latin1") | Some("windows-1252") => {
+            data.iter().map(|byte| *byte as char).collect()
+        }
+        _ => String::from_utf8_lossy(data).into_owned(),
+    }
+}
+
+/// The parts of `body` separated by `boundary`, without preamble and epilogue
+fn split_multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = Vec::new();
+    let mut rest = body;
+
+    // Skip the preamble
+    match find(rest, delimiter.as_bytes()) {
+        Some(idx) => rest = &rest[idx + delimiter.len()..],
+        None => return parts,
+    }
+    while !rest.starts_with(b"--") {
+        let end = find(rest, delimiter.as_bytes()).unwrap_or(rest.len());
+        let part = &rest[..end];
+        // The line break before the delimiter belongs to it
+        let part = part.strip_suffix(b"\n").unwrap_or(part);
+        let part = part.strip_suffix(b"\r").unwrap_or(part);
+        // Drop what is left of the delimiter line
+        let part = match find(part, b"\n") {
+            Some(idx) => &part[idx + 1..],
+            None => &[],
+        };
+        parts.push(part);
+
+        if end == rest.len() {
+            break;
+        }
+        rest = &rest[end + delimiter.len()..];
+    }
+    parts
+}
+
+fn collect_calendar_parts(part: &[u8], found: &mut Vec<String>) {
+    let (headers, body) = split_headers(part);
+    let content_type = header(&headers, "content-type").unwrap_or("text/plain");
+    let mime_type = main_value(content_type);
+
+    if mime_type.starts_with("multipart/") {
+        if let Some(boundary) = parameter(content_type, "boundary") {
+            for part in split_multipart(body, &boundary) {
+                collect_calendar_parts(part, found);
+            }
+        }
+        return;
+    }
+
+    if mime_type == "message/rfc822" {
+        collect_calendar_parts(body, found);
+        return;
+    }
+
+    // Some clients attach invitations as application/octet-stream
+    let file_name = header(&headers, "content-disposition")
+        .and_then(|value| parameter(value, "filename"))
+        .or_else(|| parameter(content_type, "name"));
+    let is_ics_file = file_name.is_some_and(|name| name.to_ascii_lowercase().ends_with(".ics"));
+    if !CALENDAR_TYPES.contains(&mime_type.as_str()) && !is_ics_file {
+        return;
+    }
+
+    let data = match header(&headers, "content-transfer-encoding").map(main_value) {
+        Some(encoding) if encoding == "base64" => decode_base64(body),
+        Some(encoding) if encoding == "quoted-printable" => decode_quoted_printable(body),
+        _ => body.to_vec(),
+    };
+    let text = decode_charset(&data, parameter(content_type, "charset").as_deref());
+
+    // Invitations often carry the same data inline and as attachment
+    if text.contains("BEGIN:VCALENDAR") && !found.contains(&text) {
+        found.push(text);
+    }
+}
+
+/// The iCalendar data of all calendar parts of the RFC 822 `message`,
+/// decoded and without duplicates
+pub fn calendar_parts(message: &[u8]) -> Vec<String> {
+    let mut found = Vec::new();
+    collect_calendar_parts(message, &mut found);
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64() {
+        assert_eq!(decode_base64(b"TWFu"), b"Man");
+        assert_eq!(decode_base64(b"TWE="), b"Ma");
+        assert_eq!(decode_base64(b"TQ=="), b"M");
+        // Missing padding and line breaks
+        assert_eq!(decode_base64(b"TWFu\r\nTWE"), b"ManMa");
+    }
+
+    #[test]
+    fn quoted_printable() {
+        assert_eq!(decode_quoted_printable(b"a=3Db"), b"a=b");
+        assert_eq!(decode_quoted_printable(b"caf=C3=A9"), "café".as_bytes());
+        assert_eq!(
+            decode_quoted_printable(b"long=\r\nline=\nend"),
+            b"longlineend"
+        );
+        // Broken escapes are kept as they are
+        assert_eq!(decode_quoted_printable(b"=ZZ and ="), b"=ZZ and =");
+    }
+
+    #[test]
+    fn charsets() {
+        assert_eq!(decode_charset(b"caf\xe9", Some("ISO-8859-1")), "café");
+        assert_eq!(decode_charset("café".as_bytes(), Some("utf-8")), "café");
+        // Unknown charsets are read as UTF-8
+        assert_eq!(decode_charset(b"caf\xe9", Some("x-unknown")), "caf\u{fffd}");
+        assert_eq!(decode_charset("café".as_bytes(), None), "café");
+    }
+
+    #[test]
+    fn calendar_parts_of_multipart_message() {
+        let ics = "BEGIN:VCALENDAR\r\nMETHOD:REQUEST\r\nEND:VCALENDAR\r\n";
+        let message = format!(
+            "From: anna@example.com\r\n\
+             Content-Type: multipart/mixed;\r\n boundary=\"outer\"\r\n\
+             \r\n\
+             preamble\r\n\
+             --outer\r\n\
+             Content-Type: text/plain\r\n\
+             \r\n\
+             BEGIN:VCALENDAR is no calendar here\r\n\
+             --outer\r\n\
+             Content-Type: text/calendar; charset=utf-8; method=REQUEST\r\n\
+             Content-Transfer-Encoding: quoted-printable\r\n\
+             \r\n\
+             BEGIN:VCALENDAR\r\nMETH=\r\nOD:REQ=55EST\r\nEND:VCALENDAR\r\n\
+             \r\n\
+             --outer\r\n\
+             Content-Type: application/octet-stream; name=\"invite.ics\"\r\n\
+             Content-Transfer-Encoding: base64\r\n\
+             \r\n\
+             {}\r\n\
+             --outer--\r\n\
+             epilogue\r\n",
+            "QkVHSU46VkNBTEVOREFSDQpNRVRIT0Q6UkVRVUVTVA0KRU5EOlZDQUxFTkRBUg0K"
+        );
+
+        assert_eq!(calendar_parts(message.as_bytes()), [ics]);
+    }
+}
@@ -1,18 +1,14 @@
-mod agenda;
-mod config;
-mod events;
-mod provider;
-mod ui;
-
-use agenda::Agenda;
-use config::Config;
-use events::Dispatcher;
+use chrono::NaiveDate;
 use flexi_logger::{Duplicate, FileSpec, Logger};
+use jackal::agenda::Agenda;
+use jackal::config::{self, Config};
+use jackal::events::Dispatcher;
+use jackal::ui::app::App;
+use jackal::{cli, console, timing, ui};
 use std::convert::TryFrom;
 use std::io::stdout;
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
-use ui::app::App;
 use unsegen::base::Terminal;
 
 #[derive(Debug, StructOpt)]
@@ -37,16 +33,54 @@ pub struct Args {
         help = "only show calendar non-interactively"
     )]
     pub show: bool,
+
+    #[structopt(
+        long = "plain",
+        help = "line based interface without colors or cursor movement, e.g. for screen readers"
+    )]
+    pub plain: bool,
+
+    #[structopt(
+        long = "archive",
+        help = "also load the events moved to the archive by 'jk archive'"
+    )]
+    pub archive: bool,
+
+    #[structopt(
+        long = "date",
+        help = "start with the cursor on this date (YYYY-MM-DD)",
+        conflicts_with = "uid"
+    )]
+    pub date: Option<NaiveDate>,
+
+    #[structopt(
+        long = "uid",
+        help = "start with the cursor on the next occurrence of the event with this UID"
+    )]
+    pub uid: Option<String>,
+
+    #[structopt(
+        long = "debug-timings",
+        help = "report slow operations on stderr when exiting"
+    )]
+    pub debug_timings: bool,
+
+    #[structopt(subcommand)]
+    pub command: Option<cli::Command>,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     Logger::try_with_env_or_str("info")?
-        .log_to_file(FileSpec::default())
+        .log_to_file_and_writer(FileSpec::default(), Box::new(console::ConsoleSink))
+        .format_for_files(flexi_logger::detailed_format)
         .print_message()
         .duplicate_to_stderr(Duplicate::Warn)
         .start()?;
 
     let args = Args::from_args();
+    if args.debug_timings {
+        timing::enable_report();
+    }
     let config = if let Some(path) = args.configfile {
         Config::load(&path)?
     } else if let Ok(path) = config::find_configfile() {
@@ -55,14 +89,47 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         Config::default()
     };
 
+    let mut calendar = Agenda::from_config(&config)?;
+    if args.archive {
+        calendar.load_archive();
+    }
+
+    if let Some(command) = args.command {
+        let result = command.run(&mut calendar, &config);
+        timing::report();
+        // The message is meant for the user, not the debug representation
+        if let Err(err) = result {
+            log::error!("{}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if args.plain {
+        let result = ui::plain::run_plain(&config, calendar);
+        timing::report();
+        return result;
+    }
+
+    // Resolve the start position before taking over the terminal so that
+    // errors stay readable
+    let mut app = App::new(&config, calendar);
+    if let Some(date) = args.date {
+        app.go_to_date(date);
+    }
+    if let Some(uid) = &args.uid {
+        if let Err(err) = app.go_to_event(uid) {
+            log::error!("{}", err);
+            std::process::exit(1);
+        }
+    }
+
     let dispatcher = Dispatcher::from_config(&config);
     // Setup unsegen terminal
     let stdout = stdout();
     let mut term = Terminal::new(stdout.lock())?;
 
-    let calendar = Agenda::from_config(&config)?;
-
-    let mut app = App::new(&config, calendar);
-
-    app.run(dispatcher, term)
+    let result = app.run(dispatcher, term);
+    timing::report();
+    result
 }
@@ -0,0 +1,262 @@
+use chrono::{DateTime, Duration, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::process::Command;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Instant;
+
+use crate::agenda::Agenda;
+use crate::config::Config;
+use crate::link;
+use crate::state::{Migration, StateFile};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+const SNOOZE_ACTION_PREFIX: &str = "snooze-";
+const MAP_ACTION: &str = "map";
+/// Alarms that fired this long ago and were not answered yet are still
+/// shown, e.g. those of the time the daemon was not running
+const CATCH_UP_MINUTES: i64 = 60;
+/// Answered alarms are forgotten when they are older than this
+const KEEP_ANSWERED_DAYS: i64 = 7;
+/// Version 1 only added the version header
+const MIGRATIONS: &[Migration] = &[Ok];
+
+/// Identifies an alarm firing at `time` for the event with `uid`, so that
+/// the alarm of each instance and repetition is answered separately
+fn alarm_key(uid: &str, time: DateTime<Utc>) -> String {
+    format!("{}@{}", uid, time.timestamp())
+}
+
+/// What a notification shows, kept to show it again when it is snoozed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Notification {
+    key: String,
+    /// When the alarm fired as a Unix timestamp
+    fired: i64,
+    summary: String,
+    body: String,
+    /// Where the event takes place, see `Config::map_url`
+    #[serde(default)]
+    map_link: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Snoozed {
+    /// Unix timestamp of when to show the notification again
+    until: i64,
+    notification: Notification,
+}
+
+/// How a notification was closed
+enum Answer {
+    /// Dismissed or expired
+    Closed(Notification),
+    Snoozed(Notification, DateTime<Utc>),
+    /// Could not be shown
+    Failed(Notification),
+}
+
+impl Answer {
+    fn notification(&self) -> &Notification {
+        match self {
+            Answer::Closed(notification)
+            | Answer::Snoozed(notification, _)
+            | Answer::Failed(notification) => notification,
+        }
+    }
+}
+
+/// What survives a restart of the daemon
+#[derive(Default, Serialize, Deserialize)]
+struct NotifyState {
+    /// Unix timestamps of when the alarms fired by `alarm_key`
+    #[serde(default)]
+    answered: BTreeMap<String, i64>,
+    #[serde(default)]
+    snoozed: Vec<Snoozed>,
+}
+
+/// Notifications already answered or waiting to be shown again, kept in the
+/// state directory so that a restart neither repeats nor loses any
+struct NotifyStore {
+    file: StateFile,
+    state: NotifyState,
+}
+
+impl NotifyStore {
+    fn load() -> Result<Self> {
+        let file = StateFile::new("notifications.toml", MIGRATIONS)?;
+        let state = file.load()?;
+        Ok(NotifyStore { file, state })
+    }
+
+    fn save(&self) {
+        if let Err(e) = self.file.save(&self.state) {
+            log::error!("Could not save the answered notifications: {}", e);
+        }
+    }
+
+    fn is_answered(&self, key: &str) -> bool {
+        self.state.answered.contains_key(key)
+    }
+
+    fn answer(&mut self, answer: Answer, now: DateTime<Utc>) {
+        let notification = answer.notification();
+        self.state
+            .snoozed
+            .retain(|snoozed| snoozed.notification.key != notification.key);
+        self.state
+            .answered
+            .insert(notification.key.clone(), notification.fired);
+        if let Answer::Snoozed(notification, until) = answer {
+            self.state.snoozed.push(Snoozed {
+                until: until.timestamp(),
+                notification,
+            });
+        }
+
+        let forget_before = (now - Duration::days(KEEP_ANSWERED_DAYS)).timestamp();
+        self.state
+            .answered
+            .retain(|_, fired| *fired >= forget_before);
+        self.save();
+    }
+
+    /// Snoozed notifications to show again at `now`
+    fn due(&self, now: DateTime<Utc>) -> impl Iterator<Item = &Notification> {
+        self.state
+            .snoozed
+            .iter()
+            .filter(move |snoozed| snoozed.until <= now.timestamp())
+            .map(|snoozed| &snoozed.notification)
+    }
+}
+
+/// Shows `notification` with notify-send and waits in the background for it
+/// to be closed or for one of the snooze actions, sending the answer back
+/// through `answers`. Opening the map closes the notification as well.
+fn show(notification: Notification, snooze_minutes: &[u32], answers: mpsc::Sender<Answer>) {
+    let mut command = Command::new("notify-send");
+    command.arg("--app-name=jackal").arg("--wait");
+    for minutes in snooze_minutes {
+        command.arg(format!(
+            "--action={}{}=Snooze {} min",
+            SNOOZE_ACTION_PREFIX, minutes, minutes
+        ));
+    }
+    if notification.map_link.is_some() {
+        command.arg(format!("--action={}=Open map", MAP_ACTION));
+    }
+    // Summaries like "-1 day" are no options
+    command
+        .arg("--")
+        .arg(&notification.summary)
+        .arg(&notification.body);
+
+    thread::spawn(move || {
+        // A notification that could not be shown is not answered either. It
+        // is shown again on the next poll while it is due.
+        let output = match command.output() {
+            Ok(output) => output,
+            Err(e) => {
+                log::error!("Could not run notify-send: {}", e);
+                let _ = answers.send(Answer::Failed(notification));
+                return;
+            }
+        };
+        if !output.status.success() {
+            log::error!(
+                "notify-send failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            let _ = answers.send(Answer::Failed(notification));
+            return;
+        }
+        // notify-send prints the action chosen, nothing if dismissed
+        let action = String::from_utf8_lossy(&output.stdout);
+        if let (MAP_ACTION, Some(url)) = (action.trim(), &notification.map_link) {
+            if let Err(e) = link::open_url(url) {
+                log::error!("Could not open the map: {}", e);
+            }
+        }
+        let minutes = action
+            .trim()
+            .strip_prefix(SNOOZE_ACTION_PREFIX)
+            .and_then(|minutes| minutes.parse::<i64>().ok());
+        let answer = match minutes {
+            Some(minutes) => {
+                log::info!("Snoozed '{}' for {} min", notification.summary, minutes);
+                Answer::Snoozed(notification, Utc::now() + Duration::minutes(minutes))
+            }
+            None => Answer::Closed(notification),
+        };
+        let _ = answers.send(answer);
+    });
+}
+
+/// Shows the alarms of all calendars as desktop notifications until killed.
+/// Alarms are shown until they are dismissed or snoozed, even across
+/// restarts, as long as they fired less than an hour ago.
+pub fn run_daemon(agenda: &mut Agenda, config: &Config) -> Result<()> {
+    let spec = &config.notifications;
+    let mut store = NotifyStore::load()?;
+    let (tx, rx) = mpsc::channel::<Answer>();
+    // Keys of the notifications currently shown
+    let mut shown = BTreeSet::new();
+
+    loop {
+        let now = Utc::now();
+        let catch_up = now - Duration::minutes(CATCH_UP_MINUTES);
+        for (time, event, alarm) in agenda.alarms_between(catch_up..now) {
+            let key = alarm_key(event.uid(), time);
+            if store.is_answered(&key) || shown.contains(&key) {
+                continue;
+            }
+
+            let begin = event.begin().with_timezone(&Local);
+            let notification = Notification {
+                key: key.clone(),
+                fired: time.timestamp(),
+                summary: event.summary().to_owned(),
+                body: match &alarm.description {
+                    Some(description) if description != event.summary() => {
+                        format!("{}\n{}", begin.format("%a %d %b %H:%M"), description)
+                    }
+                    _ => begin.format("%a %d %b %H:%M").to_string(),
+                },
+                map_link: event.geo().map(|geo| link::map_link(&config.map_url, geo)),
+            };
+            shown.insert(key);
+            show(notification, &spec.snooze_minutes, tx.clone());
+        }
+
+        let due: Vec<Notification> = store
+            .due(now)
+            .filter(|notification| !shown.contains(&notification.key))
+            .cloned()
+            .collect();
+        for notification in due {
+            shown.insert(notification.key.clone());
+            show(notification, &spec.snooze_minutes, tx.clone());
+        }
+
+        // Record answers right away so that they are not lost if the daemon
+        // is killed before the next poll
+        let next_poll = Instant::now() + POLL_INTERVAL;
+        while let Some(timeout) = next_poll.checked_duration_since(Instant::now()) {
+            match rx.recv_timeout(timeout) {
+                Ok(answer) => {
+                    shown.remove(&answer.notification().key);
+                    if !matches!(answer, Answer::Failed(_)) {
+                        store.answer(answer, Utc::now());
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+        agenda.refresh();
+    }
+}
@@ -0,0 +1,122 @@
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use std::io::{self, Write};
+
+use crate::agenda::Agenda;
+use crate::locale::{FormatLocalized, Locale};
+
+/// Used for calendars without a color of their own
+const DEFAULT_COLOR: &str = "#808080";
+
+const STYLE: &str = "
+@page { size: A4 landscape; margin: 1cm; }
+body { font-family: sans-serif; font-size: 10pt; margin: 0; }
+h1 { font-size: 16pt; margin: 0 0 0.5em 0; }
+table { width: 100%; border-collapse: collapse; table-layout: fixed; }
+th { text-align: left; border-bottom: 2px solid #000; padding: 0.3em; }
+th.weekend, td.weekend { background: #f2f2f2; }
+td { vertical-align: top; height: 14cm; border: 1px solid #999; padding: 0.3em; }
+.event { border-left: 0.4em solid; padding-left: 0.3em; margin-bottom: 0.4em; }
+.time { font-weight: bold; display: block; }
+.legend { margin-top: 0.5em; }
+.legend span { margin-right: 1.5em; }
+.swatch { display: inline-block; width: 0.8em; height: 0.8em; margin-right: 0.3em; }
+* { -webkit-print-color-adjust: exact; print-color-adjust: exact; }
+";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A standalone HTML page with the events of the week containing `date` as
+/// a grid of days, one column per day, followed by a legend of the
+/// calendars
+pub fn write_week_html(
+    agenda: &Agenda,
+    date: NaiveDate,
+    locale: &Locale,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let monday = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+    let sunday = monday + Duration::days(6);
+    let title = format!(
+        "Week {}, {} \u{2013} {}",
+        monday.iso_week().week(),
+        monday.format_in(locale, "%-d %B"),
+        sunday.format_in(locale, "%-d %B %Y")
+    );
+
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", escape(&title))?;
+    writeln!(out, "<style>{}</style>\n</head>\n<body>", STYLE)?;
+    writeln!(out, "<h1>{}</h1>", escape(&title))?;
+
+    writeln!(out, "<table>\n<tr>")?;
+    for day in monday.iter_days().take(7) {
+        writeln!(
+            out,
+            "<th{}>{}</th>",
+            if day.weekday().number_from_monday() > 5 {
+                " class=\"weekend\""
+            } else {
+                ""
+            },
+            day.format_in(locale, "%A %-d")
+        )?;
+    }
+    writeln!(out, "</tr>\n<tr>")?;
+
+    for day in monday.iter_days().take(7) {
+        if day.weekday().number_from_monday() > 5 {
+            writeln!(out, "<td class=\"weekend\">")?;
+        } else {
+            writeln!(out, "<td>")?;
+        }
+
+        let mut events: Vec<_> = agenda.events_of_day(&day).collect();
+        events.sort_by_key(|event| event.begin().with_timezone(&Local));
+
+        for event in events {
+            let occurrence = event.occurrence().clone().with_tz(&Local {});
+            let time = if occurrence.is_allday() {
+                "All day".to_owned()
+            } else if occurrence.is_instant() {
+                occurrence.begin().format("%H:%M").to_string()
+            } else {
+                format!(
+                    "{} \u{2013} {}",
+                    occurrence.begin().format("%H:%M"),
+                    occurrence.end().format("%H:%M")
+                )
+            };
+
+            writeln!(
+                out,
+                "<div class=\"event\" style=\"border-color: {}\"><span class=\"time\">{}</span>{}</div>",
+                escape(event.calendar.color().unwrap_or(DEFAULT_COLOR)),
+                time,
+                escape(event.summary())
+            )?;
+        }
+        writeln!(out, "</td>")?;
+    }
+    writeln!(out, "</tr>\n</table>")?;
+
+    writeln!(out, "<div class=\"legend\">")?;
+    for calendar in agenda.calendar_iter() {
+        let name = match calendar.name() {
+            "" => calendar.id(),
+            name => name,
+        };
+        writeln!(
+            out,
+            "<span><span class=\"swatch\" style=\"background: {}\"></span>{}</span>",
+            escape(calendar.color().unwrap_or(DEFAULT_COLOR)),
+            escape(name)
+        )?;
+    }
+    writeln!(out, "</div>\n</body>\n</html>")
+}
@@ -0,0 +1,491 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Weekday};
+use chrono_tz::Tz;
+
+use super::{Error, ErrorKind, NewEvent, Result};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const WEEK: &[Weekday] = &[
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+const WORKDAYS: &[Weekday] = &[
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+];
+
+/// How an event entered in natural language repeats
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Recurrence {
+    freq: &'static str,
+    days: Vec<Weekday>,
+}
+
+impl Recurrence {
+    fn new(freq: &'static str) -> Self {
+        Recurrence {
+            freq,
+            days: Vec::new(),
+        }
+    }
+
+    fn weekly_on(days: &[Weekday]) -> Self {
+        Recurrence {
+            freq: "WEEKLY",
+            days: days.to_vec(),
+        }
+    }
+
+    fn to_rrule(&self) -> String {
+        if self.days.is_empty() {
+            format!("FREQ={}", self.freq)
+        } else {
+            let days: Vec<_> = self.days.iter().map(|day| weekday_code(*day)).collect();
+            format!("FREQ={};BYDAY={}", self.freq, days.join(","))
+        }
+    }
+}
+
+/// A part of the input that was recognized
+enum Part {
+    Date(NaiveDate),
+    Times(NaiveTime, Option<NaiveTime>),
+    Duration(Duration),
+    Recurrence(Recurrence),
+}
+
+fn weekday_code(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "monday",
+        Weekday::Tue => "tuesday",
+        Weekday::Wed => "wednesday",
+        Weekday::Thu => "thursday",
+        Weekday::Fri => "friday",
+        Weekday::Sat => "saturday",
+        Weekday::Sun => "sunday",
+    }
+}
+
+/// Full names only, abbreviations like "sat" or "sun" are common words
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    let word = word.strip_suffix('s').unwrap_or(word);
+    WEEK.iter().copied().find(|day| word == weekday_name(*day))
+}
+
+/// The first day from `date` on, `date` included, that is one of `days`
+fn next_matching(date: NaiveDate, days: &[Weekday]) -> NaiveDate {
+    date.iter_days()
+        .take(7)
+        .find(|d| days.contains(&d.weekday()))
+        .unwrap_or(date)
+}
+
+fn parse_date(words: &[String], today: NaiveDate) -> Option<(NaiveDate, usize)> {
+    match words {
+        [word, ..] if word == "today" => Some((today, 1)),
+        [word, ..] if word == "tomorrow" => Some((today.succ_opt()?, 1)),
+        [next, day, ..] if next == "next" => {
+            let day = parse_weekday(day)?;
+            Some((next_matching(today.succ_opt()?, &[day]), 2))
+        }
+        [word, ..] => match parse_weekday(word) {
+            Some(day) => Some((next_matching(today, &[day]), 1)),
+            None => Some((NaiveDate::parse_from_str(word, DATE_FORMAT).ok()?, 1)),
+        },
+        [] => None,
+    }
+}
+
+/// Parses times like "9am", "12:30", "9:15pm", "noon". Hours alone are
+/// only accepted with "am"/"pm" or, if given, `meridiem` so that numbers in
+/// the summary are not taken for times.
+fn parse_clock(word: &str, meridiem: Option<&str>) -> Option<NaiveTime> {
+    match word {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return Some(NaiveTime::MIN),
+        _ => {}
+    }
+
+    let (clock, meridiem) = match word.strip_suffix("am").or_else(|| word.strip_suffix("pm")) {
+        Some(clock) => (clock, Some(&word[clock.len()..])),
+        None => (word, meridiem),
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour, minute.parse::<u32>().ok()?),
+        Some(_) => return None,
+        None if meridiem.is_some() => (clock, 0),
+        None => return None,
+    };
+    let hour = hour.parse::<u32>().ok()?;
+
+    let hour = match meridiem {
+        Some(_) if hour == 0 || hour > 12 => return None,
+        Some("am") => hour % 12,
+        Some(_) => hour % 12 + 12,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+fn meridiem_of(word: &str) -> Option<&str> {
+    ["am", "pm"].iter().copied().find(|m| word.ends_with(m))
+}
+
+/// Parses the begin of a range like "9-10am" or "11-1pm", which takes the
+/// meridiem of the end unless it has its own or would begin after the end
+fn parse_range_begin(begin: &str, end: &str, end_time: NaiveTime) -> Option<NaiveTime> {
+    let meridiem = meridiem_of(end);
+    let begin_time = parse_clock(begin, meridiem)?;
+    match meridiem {
+        Some(meridiem) if meridiem_of(begin).is_none() && begin_time > end_time => {
+            let other = if meridiem == "am" { "pm" } else { "am" };
+            parse_clock(begin, Some(other))
+        }
+        _ => Some(begin_time),
+    }
+}
+
+fn parse_times(words: &[String]) -> Option<(NaiveTime, Option<NaiveTime>, usize)> {
+    let word = words.first()?;
+
+    // "12:30-13:30", "9-10am"
+    if let Some((begin, end)) = word.split_once('-') {
+        let end_time = parse_clock(end, None)?;
+        let begin_time = parse_range_begin(begin, end, end_time)?;
+        return Some((begin_time, Some(end_time), 1));
+    }
+
+    // "12:30 to 13:30", "9 - 10am"
+    if let [begin, to, end, ..] = words {
+        if matches!(to.as_str(), "-" | "to" | "until") {
+            if let Some(end_time) = parse_clock(end, None) {
+                if let Some(begin_time) = parse_range_begin(begin, end, end_time) {
+                    return Some((begin_time, Some(end_time), 3));
+                }
+            }
+        }
+    }
+
+    Some((parse_clock(word, None)?, None, 1))
+}
+
+fn too_long() -> Error {
+    Error::new(ErrorKind::TimeParse, "The event would last too long")
+}
+
+/// Parses amounts like "1h30m", "45min" or "2 hours". Fails for amounts
+/// too large to represent, `None` if the words are no amount.
+fn parse_amount(words: &[String]) -> Result<Option<(Duration, usize)>> {
+    let unit = |unit: &str| match unit {
+        "m" | "min" | "mins" | "minute" | "minutes" => Some(Duration::minutes(1)),
+        "h" | "hr" | "hrs" | "hour" | "hours" => Some(Duration::hours(1)),
+        "d" | "day" | "days" => Some(Duration::days(1)),
+        _ => None,
+    };
+    let amount = |digits: &str, name: &str| -> Option<Result<Duration>> {
+        let unit = unit(name)?;
+        // All digits, so only overflows fail to parse
+        let amount = digits.parse::<i32>().map_err(|_| too_long());
+        Some(amount.and_then(|amount| unit.checked_mul(amount).ok_or_else(too_long)))
+    };
+
+    if let [digits, name, ..] = words {
+        if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+            if let Some(duration) = amount(digits, name) {
+                return Ok(Some((duration?, 2)));
+            }
+        }
+    }
+
+    let mut rest = match words.first() {
+        Some(word) => word.as_str(),
+        None => return Ok(None),
+    };
+    let mut total = Duration::zero();
+    while !rest.is_empty() {
+        let digits = match rest.find(|c: char| !c.is_ascii_digit()) {
+            Some(digits) if digits > 0 => digits,
+            _ => return Ok(None),
+        };
+        let letters = rest[digits..]
+            .find(|c: char| c.is_ascii_digit())
+            .map_or(rest.len(), |end| digits + end);
+        let duration = match amount(&rest[..digits], &rest[digits..letters]) {
+            Some(duration) => duration?,
+            None => return Ok(None),
+        };
+        total = total.checked_add(&duration).ok_or_else(too_long)?;
+        rest = &rest[letters..];
+    }
+    Ok(Some((total, 1)))
+}
+
+fn parse_recurrence(words: &[String]) -> Option<(Recurrence, usize)> {
+    let single = |word: &str| match word {
+        "daily" => Some(Recurrence::new("DAILY")),
+        "weekly" => Some(Recurrence::new("WEEKLY")),
+        "monthly" => Some(Recurrence::new("MONTHLY")),
+        "yearly" | "annually" => Some(Recurrence::new("YEARLY")),
+        "weekdays" => Some(Recurrence::weekly_on(WORKDAYS)),
+        _ => None,
+    };
+
+    match words {
+        [every, unit, ..] if every == "every" => {
+            let recurrence = match unit.as_str() {
+                "day" => Recurrence::new("DAILY"),
+                "week" => Recurrence::new("WEEKLY"),
+                "month" => Recurrence::new("MONTHLY"),
+                "year" => Recurrence::new("YEARLY"),
+                "weekday" => Recurrence::weekly_on(WORKDAYS),
+                day => Recurrence::weekly_on(&[parse_weekday(day)?]),
+            };
+            Some((recurrence, 2))
+        }
+        [word, ..] => Some((single(word)?, 1)),
+        [] => None,
+    }
+}
+
+/// Number of connectives like "at" in front of `words`, they are only
+/// dropped if something is recognized after them
+fn connectives(words: &[String]) -> usize {
+    match words.first().map(String::as_str) {
+        Some("at" | "on" | "from") => 1,
+        _ => 0,
+    }
+}
+
+/// Whether `words` begin with anything but summary words
+fn starts_part(words: &[String], today: NaiveDate) -> bool {
+    !matches!(parse_part(&words[connectives(words)..], today), Ok(None))
+}
+
+fn parse_part(words: &[String], today: NaiveDate) -> Result<Option<(Part, usize)>> {
+    if let Some((recurrence, n)) = parse_recurrence(words) {
+        // "weekly review" is a summary, "review weekly" repeats
+        let describes_next = n == 1 && words.len() > 1 && !starts_part(&words[1..], today);
+        if !describes_next {
+            return Ok(Some((Part::Recurrence(recurrence), n)));
+        }
+    }
+    if let Some((date, n)) = parse_date(words, today) {
+        return Ok(Some((Part::Date(date), n)));
+    }
+    if let Some((begin, end, n)) = parse_times(words) {
+        return Ok(Some((Part::Times(begin, end), n)));
+    }
+    match words {
+        [word, rest @ ..] if word == "for" => {
+            Ok(parse_amount(rest)?.map(|(duration, n)| (Part::Duration(duration), n + 1)))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Parses a new event described in natural language, e.g. "lunch with Ana
+/// tomorrow 12:30-13:30", "standup every weekday 9am" or "review friday
+/// 3pm for 1h".
+///
+/// Words that are not understood as date, time, duration or recurrence make
+/// up the summary. Relative days like "tomorrow" or "friday" are relative to
+/// `today`, a missing date or time is taken from `reference`. Recurring
+/// events without a date begin on the first day the rule matches.
+pub fn parse_event(
+    input: &str,
+    today: NaiveDate,
+    reference: &DateTime<Tz>,
+) -> Result<NewEvent<Tz>> {
+    let original: Vec<&str> = input.split_whitespace().collect();
+    let words: Vec<String> = original
+        .iter()
+        .map(|word| word.trim_end_matches(',').to_lowercase())
+        .collect();
+
+    let mut date = None;
+    let mut times = None;
+    let mut duration = None;
+    let mut recurrence: Option<Recurrence> = None;
+    let mut summary = Vec::new();
+
+    let mut i = 0;
+    while i < words.len() {
+        let skip = connectives(&words[i..]);
+        match parse_part(&words[i + skip..], today)? {
+            Some((part, n)) => {
+                match part {
+                    Part::Date(d) => date = Some(d),
+                    Part::Times(begin, end) => times = Some((begin, end)),
+                    Part::Duration(d) => duration = Some(d),
+                    Part::Recurrence(r) => recurrence = Some(r),
+                }
+                i += skip + n;
+            }
+            _ => {
+                summary.push(original[i]);
+                i += 1;
+            }
+        }
+    }
+
+    if summary.is_empty() {
+        return Err(Error::new(ErrorKind::EventMissingKey, "Missing summary"));
+    }
+
+    let date = match (date, &recurrence) {
+        (Some(date), _) => date,
+        (None, Some(recurrence)) if !recurrence.days.is_empty() => {
+            next_matching(reference.date_naive(), &recurrence.days)
+        }
+        (None, _) => reference.date_naive(),
+    };
+    let (begin_time, end_time) = times.unwrap_or((reference.time(), None));
+
+    let tz = reference.timezone();
+    let at = |date: NaiveDate, time: NaiveTime| {
+        tz.from_local_datetime(&date.and_time(time))
+            .earliest()
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::TimeParse,
+                    &format!("{} {} does not exist in {}", date, time, tz.name()),
+                )
+            })
+    };
+
+    let mut event = NewEvent::new(at(date, begin_time)?);
+    event.end = match (end_time, duration) {
+        // A range like "23:00-01:00" ends on the next day
+        (Some(end), _) if end <= begin_time => Some(at(date.succ_opt().unwrap_or(date), end)?),
+        (Some(end), _) => Some(at(date, end)?),
+        (None, Some(duration)) => Some(
+            event
+                .begin
+                .checked_add_signed(duration)
+                .ok_or_else(too_long)?,
+        ),
+        (None, None) => None,
+    };
+    event.summary = Some(summary.join(" "));
+    event.rrule = recurrence.map(|recurrence| recurrence.to_rrule());
+
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        // A Thursday
+        NaiveDate::from_ymd_opt(2026, 10, 15).unwrap()
+    }
+
+    fn parse(input: &str) -> Result<NewEvent<Tz>> {
+        let reference = Tz::Europe__Berlin
+            .with_ymd_and_hms(2026, 10, 15, 10, 0, 0)
+            .unwrap();
+        parse_event(input, today(), &reference)
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Tz> {
+        Tz::Europe__Berlin
+            .with_ymd_and_hms(2026, 10, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn date_and_time_range() {
+        let event = parse("lunch with Ana tomorrow 12:30-13:30").unwrap();
+        assert_eq!(event.summary.as_deref(), Some("lunch with Ana"));
+        assert_eq!(event.begin, at(16, 12, 30));
+        assert_eq!(event.end, Some(at(16, 13, 30)));
+        assert_eq!(event.rrule, None);
+    }
+
+    #[test]
+    fn recurrence() {
+        let event = parse("standup every weekday 9am").unwrap();
+        assert_eq!(event.summary.as_deref(), Some("standup"));
+        assert_eq!(event.begin, at(15, 9, 0));
+        assert_eq!(
+            event.rrule.as_deref(),
+            Some("FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR")
+        );
+    }
+
+    #[test]
+    fn duration() {
+        let event = parse("review friday 3pm for 1h").unwrap();
+        assert_eq!(event.summary.as_deref(), Some("review"));
+        assert_eq!(event.begin, at(16, 15, 0));
+        assert_eq!(event.end, Some(at(16, 16, 0)));
+    }
+
+    #[test]
+    fn meridiem_of_range_end() {
+        let event = parse("call 9-10am").unwrap();
+        assert_eq!(
+            (event.begin, event.end),
+            (at(15, 9, 0), Some(at(15, 10, 0)))
+        );
+
+        let event = parse("call 11-1pm").unwrap();
+        assert_eq!(
+            (event.begin, event.end),
+            (at(15, 11, 0), Some(at(15, 13, 0)))
+        );
+
+        let event = parse("party 11 to 1am").unwrap();
+        assert_eq!(
+            (event.begin, event.end),
+            (at(15, 23, 0), Some(at(16, 1, 0)))
+        );
+    }
+
+    #[test]
+    fn recurrence_words_in_summary() {
+        let event = parse("weekly review friday 3pm").unwrap();
+        assert_eq!(event.summary.as_deref(), Some("weekly review"));
+        assert_eq!(event.rrule, None);
+
+        let event = parse("review weekly at 3pm").unwrap();
+        assert_eq!(event.summary.as_deref(), Some("review"));
+        assert_eq!(event.rrule.as_deref(), Some("FREQ=WEEKLY"));
+    }
+
+    #[test]
+    fn too_long_durations() {
+        let too_long = |input: &str| {
+            matches!(
+                parse(input),
+                Err(Error {
+                    kind: ErrorKind::TimeParse,
+                    ..
+                })
+            )
+        };
+        assert!(too_long("x for 99999999d"));
+        assert!(too_long(&format!("x for {}", "2147483647d".repeat(60))));
+        assert!(too_long("x for 99999999999 days"));
+    }
+}
@@ -2,11 +2,16 @@ use std::convert::From;
 use std::error;
 use std::fmt;
 use std::io;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
 pub struct Error {
     pub kind: ErrorKind,
     pub message: Option<String>,
+    /// The file the error was found in
+    pub path: Option<PathBuf>,
+    /// The iCalendar property the error was found in, e.g. DTSTART
+    pub property: Option<String>,
 }
 
 #[derive(Debug)]
@@ -18,6 +23,17 @@ pub enum ErrorKind {
     TimeParse,
     DateParse,
     DurationParse,
+    ColorParse,
+    /// Well-formed data that is not acceptable, e.g. an event ending before it
+    /// begins
+    Invalid,
+    NotFound,
+    /// The change collides with existing data
+    Conflict,
+    /// Valid data or requests that jackal can not handle
+    Unsupported,
+    /// The data is out of reach for now, e.g. on an unmounted drive
+    Unavailable,
     IOError(io::Error),
 }
 
@@ -26,6 +42,8 @@ impl Error {
         Error {
             kind,
             message: Some(msg.to_owned()),
+            path: None,
+            property: None,
         }
     }
 
@@ -33,6 +51,20 @@ impl Error {
         self.message = Some(message.to_owned());
         self
     }
+
+    /// Records the file the error was found in unless a more specific one is
+    /// known already
+    pub fn in_file(mut self, path: &Path) -> Self {
+        self.path.get_or_insert_with(|| path.to_owned());
+        self
+    }
+
+    /// Records the property the error was found in unless a more specific one
+    /// is known already
+    pub fn in_property(mut self, name: &str) -> Self {
+        self.property.get_or_insert_with(|| name.to_owned());
+        self
+    }
 }
 
 impl From<ErrorKind> for Error {
@@ -40,6 +72,8 @@ impl From<ErrorKind> for Error {
         Error {
             kind,
             message: None,
+            path: None,
+            property: None,
         }
     }
 }
@@ -67,22 +101,41 @@ impl From<io::Error> for Error {
 
 impl From<Error> for io::Error {
     fn from(err: Error) -> Self {
-        if let ErrorKind::IOError(err) = err.kind {
-            err
-        } else {
-            io::Error::new(
-                io::ErrorKind::InvalidInput,
-                err.message.unwrap_or("invalid format".to_owned()),
-            )
-        }
+        let kind = match err.kind {
+            ErrorKind::IOError(err) => return err,
+            ErrorKind::NotFound => io::ErrorKind::NotFound,
+            ErrorKind::Conflict => io::ErrorKind::AlreadyExists,
+            ErrorKind::Unsupported => io::ErrorKind::Unsupported,
+            _ => io::ErrorKind::InvalidInput,
+        };
+        io::Error::new(kind, err.to_string())
     }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match &self.message {
-            Some(msg) => write!(f, "{}: {}", self.kind.as_str(), msg),
-            None => write!(f, "{}", self.kind.as_str()),
+        let description = match (&self.kind, &self.message) {
+            // The message says it all
+            (
+                ErrorKind::Invalid
+                | ErrorKind::NotFound
+                | ErrorKind::Conflict
+                | ErrorKind::Unsupported
+                | ErrorKind::Unavailable,
+                Some(msg),
+            ) => msg.clone(),
+            (kind, Some(msg)) => format!("{}: {}", kind.as_str(), msg),
+            (kind, None) => kind.as_str(),
+        };
+
+        // E.g. "DTSTART in work/1234.ics: invalid date format: ..."
+        match (&self.property, &self.path) {
+            (Some(property), Some(path)) => {
+                write!(f, "{} in {}: {}", property, path.display(), description)
+            }
+            (Some(property), None) => write!(f, "{}: {}", property, description),
+            (None, Some(path)) => write!(f, "{}: {}", path.display(), description),
+            (None, None) => write!(f, "{}", description),
         }
     }
 }
@@ -99,6 +152,12 @@ impl ErrorKind {
             ErrorKind::TimeParse => "invalid time format".to_owned(),
             ErrorKind::DateParse => "invalid date format".to_owned(),
             ErrorKind::DurationParse => "invalid duration format".to_owned(),
+            ErrorKind::ColorParse => "invalid color format".to_owned(),
+            ErrorKind::Invalid => "invalid value".to_owned(),
+            ErrorKind::NotFound => "not found".to_owned(),
+            ErrorKind::Conflict => "conflict".to_owned(),
+            ErrorKind::Unsupported => "unsupported".to_owned(),
+            ErrorKind::Unavailable => "unavailable".to_owned(),
             ErrorKind::IOError(err) => err.to_string(),
         }
     }
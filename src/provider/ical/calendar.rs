@@ -11,15 +11,18 @@ use nom::{
     sequence::{preceded, terminated, tuple},
     IResult,
 };
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::{From, TryFrom};
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::SystemTime;
 
 use ::ical::parser::ical::IcalParser;
-use ::ical::parser::ical::{component::IcalCalendar, component::IcalEvent};
+use ::ical::parser::ical::{
+    component::IcalAlarm, component::IcalCalendar, component::IcalEvent, component::IcalTimeZone,
+};
 use ::ical::parser::Component;
 use ::ical::property::Property;
 
@@ -27,12 +30,21 @@ use uuid;
 
 use crate::config::CalendarSpec;
 use crate::provider::*;
+use crate::timing;
 
+use super::series::{self, Series, INHERITED_PROPERTIES};
 use super::{
-    Error, ErrorKind, PropertyList, Result, ICAL_FILE_EXT, ISO8601_2004_LOCAL_FORMAT,
-    ISO8601_2004_LOCAL_FORMAT_DATE,
+    html, Error, ErrorKind, EventBuilder, PropertyList, Result, Todo, ICAL_FILE_EXT,
+    ISO8601_2004_LOCAL_FORMAT, ISO8601_2004_LOCAL_FORMAT_DATE, METADATA_COLOR_FILE,
+    METADATA_DISPLAYNAME_FILE, PINNED_PROPERTY, SUSPEND_MARKER_PREFIX,
 };
 
+/// Subdirectory of a calendar with events moved out of the way by `archive`
+const ARCHIVE_DIR: &str = "archive";
+
+/// Suspensions of watching older than this are assumed to be left over
+const MAX_WATCH_SUSPENSION: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
 #[derive(Default, Clone, PartialEq, PartialOrd, Eq, Ord)]
 pub struct IcalDuration {
     sign: i8,
@@ -127,12 +139,38 @@ impl IcalDuration {
         }
     }
 
+    /// The property value of this duration in the `dur-value` format
+    pub fn to_value(&self) -> String {
+        let mut value = if self.sign < 0 { "-P" } else { "P" }.to_owned();
+        if self.weeks > 0 {
+            value += &format!("{}W", self.weeks);
+        }
+        if self.days > 0 {
+            value += &format!("{}D", self.days);
+        }
+        if self.hours > 0 || self.minutes > 0 || self.seconds > 0 {
+            value.push('T');
+            for (amount, designator) in
+                [(self.hours, 'H'), (self.minutes, 'M'), (self.seconds, 'S')]
+            {
+                if amount > 0 {
+                    value += &format!("{}{}", amount, designator);
+                }
+            }
+        }
+        if value.ends_with('P') {
+            value += "T0S";
+        }
+        value
+    }
+
     fn as_chrono_duration(&self) -> chrono::Duration {
         chrono::Duration::seconds(
             self.sign as i64
                 * ((self.years * 12 * 30 * 24 * 60 * 60)
                     + (self.months * 30 * 24 * 60 * 60)
                     + (self.weeks * 7 * 24 * 60 * 60)
+                    + (self.days * 24 * 60 * 60)
                     + (self.hours * 60 * 60)
                     + (self.minutes * 60)
                     + (self.seconds)),
@@ -140,30 +178,32 @@ impl IcalDuration {
     }
 }
 
+/// Durations are exact, so they are expressed in hours rather than nominal days
+impl From<Duration> for IcalDuration {
+    fn from(duration: Duration) -> Self {
+        let seconds = duration.num_seconds().abs();
+        Self {
+            sign: if duration < Duration::zero() { -1 } else { 1 },
+            hours: seconds / (60 * 60),
+            minutes: seconds / 60 % 60,
+            seconds: seconds % 60,
+            ..Default::default()
+        }
+    }
+}
+
 impl FromStr for IcalDuration {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self> {
         let (rest, sign) = Self::parse_sign(s)
-            .or_else(|err| {
-                return Err(Self::Err::new(
-                    ErrorKind::DurationParse,
-                    &format!("{}", err),
-                ));
-            })
-            .unwrap();
+            .map_err(|err| Self::Err::new(ErrorKind::DurationParse, &format!("{}", err)))?;
 
         let (_, mut duration) = (all_consuming(preceded(
             char('P'),
             alt((Self::parse_week_format, Self::parse_datetime_format)),
         ))(rest))
-        .or_else(|err| {
-            return Err(Self::Err::new(
-                ErrorKind::DurationParse,
-                &format!("{}", err),
-            ));
-        })
-        .unwrap();
+        .map_err(|err| Self::Err::new(ErrorKind::DurationParse, &format!("{}", err)))?;
 
         duration.sign = if let Some(sign) = sign {
             if sign == '-' {
@@ -186,9 +226,14 @@ impl TryFrom<&Property> for IcalDuration {
         let val = value
             .value
             .as_ref()
-            .ok_or(Error::new(ErrorKind::EventParse, "Empty duration property"))?;
+            .ok_or(Error::new(
+                ErrorKind::DurationParse,
+                "Empty duration property",
+            ))
+            .map_err(|err| err.in_property(&value.name))?;
 
         val.parse::<Self>()
+            .map_err(|err| err.in_property(&value.name))
     }
 }
 
@@ -210,10 +255,16 @@ impl TryFrom<&Property> for IcalDateTime {
     type Error = Error;
 
     fn try_from(value: &Property) -> Result<Self> {
+        Self::from_property(value).map_err(|err| err.in_property(&value.name))
+    }
+}
+
+impl IcalDateTime {
+    fn from_property(value: &Property) -> Result<Self> {
         let val = value
             .value
             .as_ref()
-            .ok_or(Self::Error::from(ErrorKind::DateParse).with_msg("Missing datetime value"))?;
+            .ok_or(Error::from(ErrorKind::DateParse).with_msg("Missing datetime value"))?;
 
         let has_options = value.params.is_some();
         let mut tz: Option<Tz> = None;
@@ -249,7 +300,9 @@ impl TryFrom<&Property> for IcalDateTime {
             };
         }
 
-        if let Ok(dt) = NaiveDateTime::parse_from_str(val, ISO8601_2004_LOCAL_FORMAT) {
+        if let Ok(dt) =
+            NaiveDateTime::parse_from_str(val.trim_end_matches('Z'), ISO8601_2004_LOCAL_FORMAT)
+        {
             if let Some(tz) = tz {
                 Ok(Self::Local(tz.from_local_datetime(&dt).earliest().unwrap()))
             } else {
@@ -327,6 +380,17 @@ impl IcalDateTime {
         }
     }
 
+    /// The property value of this datetime. The TZID of local datetimes is
+    /// not part of the value but a parameter.
+    pub fn to_value(&self) -> String {
+        match self {
+            IcalDateTime::Date(date) => date.format(ISO8601_2004_LOCAL_FORMAT_DATE).to_string(),
+            IcalDateTime::Floating(dt) => dt.format(ISO8601_2004_LOCAL_FORMAT).to_string(),
+            IcalDateTime::Utc(dt) => format!("{}Z", dt.format(ISO8601_2004_LOCAL_FORMAT)),
+            IcalDateTime::Local(dt) => dt.format(ISO8601_2004_LOCAL_FORMAT).to_string(),
+        }
+    }
+
     pub fn and_duration(self, duration: chrono::Duration) -> Self {
         match self {
             IcalDateTime::Date(dt) => IcalDateTime::Date(dt + duration),
@@ -337,21 +401,25 @@ impl IcalDateTime {
     }
 }
 
+/// One event component of a file. Files of recurring events may hold
+/// overrides of single instances besides the master event, each of them is
+/// an `Event` of its own sharing the file's `ical`.
 #[derive(Clone)]
 pub struct Event {
     path: PathBuf,
     occurrence: Occurrence<Tz>,
     ical: IcalCalendar,
+    /// Index of this event's component in `ical.events`
+    component: usize,
+    /// Index of the master event if this is an override
+    master: Option<usize>,
     tz: Tz,
 }
 
 impl Event {
     pub fn new(path: &Path, occurrence: Occurrence<Tz>) -> Result<Self> {
         if path.is_file() && path.exists() {
-            return Err(Error::new(
-                ErrorKind::EventParse,
-                &format!("File '{}' already exists", path.display()),
-            ));
+            return Err(Error::new(ErrorKind::Conflict, "File already exists").in_file(path));
         }
 
         let uid = if path.is_file() {
@@ -396,10 +464,12 @@ impl Event {
             path: if path.is_file() {
                 path.to_owned()
             } else {
-                path.join(&uid.to_string()).with_extension(ICAL_FILE_EXT)
+                path.join(format!("{}{}", uid, ICAL_FILE_EXT))
             },
             occurrence,
             ical: ical_calendar,
+            component: 0,
+            master: None,
             tz,
         })
     }
@@ -428,56 +498,38 @@ impl Event {
         Ok(event)
     }
 
-    pub fn from_file(path: &Path) -> Result<Self> {
-        let buf = io::BufReader::new(fs::File::open(path)?);
-
-        let mut reader = IcalParser::new(buf);
-
-        let ical: IcalCalendar = match reader.next() {
-            Some(cal) => match cal {
-                Ok(c) => c,
-                Err(e) => {
-                    return Err(Error::from(io::Error::new(
-                        io::ErrorKind::InvalidInput,
-                        format!(
-                            "No calendar could be read from '{p}': {e}",
-                            p = path.display(),
-                            e = e
-                        ),
-                    )))
-                }
-            },
-            None => {
-                return Err(Error::from(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    format!("No calendar found in '{}'", path.display()),
-                )))
-            }
-        };
-
-        Self::from_ical(path, ical)
+    /// The events of a file: a single event or the instances of a recurring
+    /// event that are known without expanding its rule
+    pub fn series_from_ical(path: &Path, ical: IcalCalendar) -> Result<Vec<Self>> {
+        Self::parse_series(path, ical).map_err(|err| err.in_file(path))
     }
 
-    pub fn from_ical(path: &Path, mut ical: IcalCalendar) -> Result<Self> {
-        if ical.events.len() > 1 {
-            return Err(Error::from(ErrorKind::CalendarParse).with_msg(&format!(
-                "Calendar '{}' has more than one event entry",
-                path.display()
-            )));
-        }
-
-        if ical.events.is_empty() {
-            return Err(Error::from(ErrorKind::CalendarParse)
-                .with_msg(&format!("Calendar '{}' has no event entry", path.display())));
-        }
+    fn parse_series(path: &Path, ical: IcalCalendar) -> Result<Vec<Self>> {
+        let series = Series::of(&ical)?;
+        let master = series.master();
+        series
+            .instances(&ical)?
+            .into_iter()
+            .map(|component| {
+                let master = master.filter(|master| *master != component);
+                Self::parse_component(path, ical.clone(), component, master)
+            })
+            .collect()
+    }
 
-        let event = ical.events.first().unwrap();
+    fn parse_component(
+        path: &Path,
+        ical: IcalCalendar,
+        component: usize,
+        master: Option<usize>,
+    ) -> Result<Self> {
+        let event = &ical.events[component];
 
         let dtstart = event
             .properties
             .iter()
             .find(|p| p.name == "DTSTART")
-            .ok_or(Error::new(ErrorKind::EventMissingKey, "No DTSTART found"))?;
+            .ok_or(Error::from(ErrorKind::EventMissingKey).in_property("DTSTART"))?;
 
         let dtend = event.properties.iter().find(|p| p.name == "DTEND");
         // Check if DURATION is set
@@ -503,9 +555,10 @@ impl Event {
                         Occurrence::Allday(tz.from_utc_date(&bdate), Some(tz.from_utc_date(&date)))
                     } else {
                         return Err(Error::new(
-                            ErrorKind::DateParse,
-                            "DTEND must also be of type 'DATE' if DTSTART is",
-                        ));
+                            ErrorKind::Invalid,
+                            "Must also be of type 'DATE' if DTSTART is",
+                        )
+                        .in_property("DTEND"));
                     }
                 }
                 dt @ _ => Occurrence::Onetime(TimeSpan::from_start_and_end(
@@ -538,24 +591,71 @@ impl Event {
             path: path.into(),
             occurrence,
             ical,
+            component,
+            master,
             tz,
         })
     }
 
-    fn get_property_value(&self, name: &str) -> Option<&str> {
-        if let Some(prop) = self.ical.events[0]
+    /// All events of the iCalendar data `ics` without storing them anywhere
+    pub fn parse_all(path: &Path, ics: &str) -> Result<Vec<Self>> {
+        let mut events = Vec::new();
+        for ical in IcalParser::new(ics.as_bytes()) {
+            let ical = ical.map_err(|e| {
+                Error::new(
+                    ErrorKind::CalendarParse,
+                    &format!("Could not read calendar: {}", e),
+                )
+                .in_file(path)
+            })?;
+
+            let groups = super::import::split_by_uid(ical);
+            for (_, group) in groups.into_iter().filter(|(_, g)| !g.events.is_empty()) {
+                events.extend(Event::series_from_ical(path, group)?);
+            }
+        }
+        events.sort_by_key(|event| event.begin());
+        Ok(events)
+    }
+
+    fn component(&self) -> &IcalEvent {
+        &self.ical.events[self.component]
+    }
+
+    fn component_mut(&mut self) -> &mut IcalEvent {
+        &mut self.ical.events[self.component]
+    }
+
+    /// Whether this event replaces a single instance of a recurring event
+    pub fn is_override(&self) -> bool {
+        super::import::is_override(self.component())
+    }
+
+    /// The properties `name` of this event, taken from the master event if
+    /// this override does not set them and they are inherited
+    fn properties_named(&self, name: &str) -> Vec<&Property> {
+        let has_own = self.component().properties.iter().any(|p| p.name == name);
+        let source = match self.master {
+            Some(master) if !has_own && INHERITED_PROPERTIES.contains(&name) => {
+                &self.ical.events[master]
+            }
+            _ => self.component(),
+        };
+        source
             .properties
             .iter()
-            .find(|prop| prop.name == name)
-        {
-            prop.value.as_deref()
-        } else {
-            None
-        }
+            .filter(|p| p.name == name)
+            .collect()
+    }
+
+    fn get_property_value(&self, name: &str) -> Option<&str> {
+        self.properties_named(name)
+            .first()
+            .and_then(|prop| prop.value.as_deref())
     }
 
     fn get_property_mut(&mut self, name: &str) -> Option<&mut Property> {
-        self.ical.events[0]
+        self.component_mut()
             .properties
             .iter_mut()
             .find(|prop| prop.name == name)
@@ -580,7 +680,319 @@ impl Event {
     }
 
     pub fn ical_event(&self) -> &IcalEvent {
-        &self.ical.events[0]
+        self.component()
+    }
+
+    pub fn sequence(&self) -> u32 {
+        self.get_property_value("SEQUENCE")
+            .and_then(|seq| seq.parse().ok())
+            .unwrap_or(0)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::write(&self.path, self.raw_ical())?;
+        Ok(())
+    }
+
+    /// Adds the definition of a time zone the event's times refer to
+    pub fn add_timezone(&mut self, timezone: IcalTimeZone) {
+        self.ical.timezones.push(timezone);
+    }
+
+    /// The file of this override without it and with its instance excluded
+    /// from the master, `None` if no component would be left
+    fn ical_without_override(&self) -> Option<IcalCalendar> {
+        let mut ical = self.ical.clone();
+        let recurrence_id = self
+            .component()
+            .properties
+            .iter()
+            .find(|p| p.name == "RECURRENCE-ID")
+            .cloned();
+        if let (Some(master), Some(recurrence_id)) = (self.master, recurrence_id) {
+            ical.events[master].add_property(Property {
+                name: "EXDATE".to_owned(),
+                ..recurrence_id
+            });
+        }
+        ical.events.remove(self.component);
+        Some(ical).filter(|ical| !ical.events.is_empty())
+    }
+
+    fn set_property(&mut self, name: &str, value: String) {
+        match self.get_property_mut(name) {
+            Some(property) => property.value = Some(value),
+            None => self.component_mut().add_property(Property {
+                name: name.to_owned(),
+                params: None,
+                value: Some(value),
+            }),
+        }
+    }
+
+    /// A copy of the event with `change` applied
+    pub fn with_change(&self, change: &EventChange) -> Result<Event> {
+        let mut changed = self.clone();
+
+        match change {
+            EventChange::Shift(by) => {
+                for property in changed
+                    .component_mut()
+                    .properties
+                    .iter_mut()
+                    .filter(|p| p.name == "DTSTART" || p.name == "DTEND")
+                {
+                    let dt = IcalDateTime::try_from(&*property)?;
+                    if dt.is_date() && by.num_seconds() % Duration::days(1).num_seconds() != 0 {
+                        return Err(Error::new(
+                            ErrorKind::Invalid,
+                            "All-day events can only be shifted by whole days",
+                        ));
+                    }
+                    property.value = Some(dt.and_duration(*by).to_value());
+                }
+                // Rescheduling is a significant change, see RFC 5545 3.8.7.4
+                changed.set_property("SEQUENCE", (self.sequence() + 1).to_string());
+            }
+            EventChange::Resize(by) => {
+                let length = self.occurrence.duration() + *by;
+                if length <= Duration::zero() {
+                    return Err(Error::new(
+                        ErrorKind::Invalid,
+                        "Events can not end before they begin",
+                    ));
+                }
+
+                let event = &mut changed.component_mut();
+                let dtstart = event
+                    .properties
+                    .iter()
+                    .find(|p| p.name == "DTSTART")
+                    .ok_or(Error::from(ErrorKind::EventMissingKey).in_property("DTSTART"))?
+                    .clone();
+                let start = IcalDateTime::try_from(&dtstart)?;
+                if start.is_date() && by.num_seconds() % Duration::days(1).num_seconds() != 0 {
+                    return Err(Error::new(
+                        ErrorKind::Invalid,
+                        "All-day events can only be resized by whole days",
+                    ));
+                }
+
+                if let Some(dtend) = event.properties.iter_mut().find(|p| p.name == "DTEND") {
+                    let end = IcalDateTime::try_from(&*dtend)?;
+                    dtend.value = Some(end.and_duration(*by).to_value());
+                } else if let Some(duration) =
+                    event.properties.iter_mut().find(|p| p.name == "DURATION")
+                {
+                    duration.value = Some(IcalDuration::from(length).to_value());
+                } else {
+                    // The end was implied by DTSTART so far
+                    event.add_property(Property {
+                        name: "DTEND".to_owned(),
+                        params: dtstart.params,
+                        value: Some(start.and_duration(length).to_value()),
+                    });
+                }
+                changed.set_property("SEQUENCE", (self.sequence() + 1).to_string());
+            }
+            EventChange::SetCategories(categories) => {
+                let event = &mut changed.component_mut();
+                event.properties.retain(|p| p.name != "CATEGORIES");
+                if !categories.is_empty() {
+                    let escaped: Vec<String> = categories
+                        .iter()
+                        .map(|c| {
+                            c.replace('\\', "\\\\")
+                                .replace(',', "\\,")
+                                .replace(';', "\\;")
+                        })
+                        .collect();
+                    event.add_property(Property {
+                        name: "CATEGORIES".to_owned(),
+                        params: None,
+                        value: Some(escaped.join(",")),
+                    });
+                }
+            }
+            EventChange::SetPinned(pinned) => {
+                let event = &mut changed.component_mut();
+                event.properties.retain(|p| p.name != PINNED_PROPERTY);
+                if *pinned {
+                    event.add_property(Property {
+                        name: PINNED_PROPERTY.to_owned(),
+                        params: None,
+                        value: Some("TRUE".to_owned()),
+                    });
+                }
+            }
+            EventChange::AddAlarm(alarm) => {
+                let (params, trigger) = match alarm.related {
+                    AlarmRelation::Start => (None, IcalDuration::from(alarm.offset).to_value()),
+                    AlarmRelation::End => (
+                        Some(vec![("RELATED".to_owned(), vec!["END".to_owned()])]),
+                        IcalDuration::from(alarm.offset).to_value(),
+                    ),
+                    // Absolute triggers have to be given in UTC, see RFC 5545 3.8.6.3
+                    AlarmRelation::At(time) => (
+                        Some(vec![("VALUE".to_owned(), vec!["DATE-TIME".to_owned()])]),
+                        IcalDateTime::Utc(time).to_value(),
+                    ),
+                };
+                // DISPLAY alarms require a DESCRIPTION, see RFC 5545 3.6.6
+                let description = alarm
+                    .description
+                    .clone()
+                    .unwrap_or_else(|| self.summary().to_owned());
+
+                let mut valarm = IcalAlarm::new();
+                valarm.add_property(Property {
+                    name: "ACTION".to_owned(),
+                    params: None,
+                    value: Some(alarm.action.clone()),
+                });
+                valarm.add_property(Property {
+                    name: "TRIGGER".to_owned(),
+                    params,
+                    value: Some(trigger),
+                });
+                valarm.add_property(Property {
+                    name: "DESCRIPTION".to_owned(),
+                    params: None,
+                    value: Some(super::xcal::escape_text(&description)),
+                });
+                if alarm.repeat > 0 {
+                    valarm.add_property(Property {
+                        name: "REPEAT".to_owned(),
+                        params: None,
+                        value: Some(alarm.repeat.to_string()),
+                    });
+                    valarm.add_property(Property {
+                        name: "DURATION".to_owned(),
+                        params: None,
+                        value: Some(IcalDuration::from(alarm.interval).to_value()),
+                    });
+                }
+                changed.component_mut().alarms.push(valarm);
+            }
+            EventChange::RemoveAlarm(offset) => {
+                let event = &mut changed.component_mut();
+                let num_alarms = event.alarms.len();
+                event.alarms.retain(|alarm| {
+                    let trigger = match alarm.properties.iter().find(|p| p.name == "TRIGGER") {
+                        Some(trigger) => trigger,
+                        None => return true,
+                    };
+                    let related_to_end = trigger.params.iter().flatten().any(|(name, values)| {
+                        name == "RELATED" && values.first().is_some_and(|v| v == "END")
+                    });
+                    let triggers_at_offset = IcalDuration::try_from(trigger)
+                        .is_ok_and(|duration| Duration::from(duration) == *offset);
+                    related_to_end || !triggers_at_offset
+                });
+
+                if event.alarms.len() == num_alarms {
+                    return Err(Error::new(
+                        ErrorKind::NotFound,
+                        &format!(
+                            "No alarm {} from the start",
+                            IcalDuration::from(*offset).to_value()
+                        ),
+                    ));
+                }
+            }
+            EventChange::ChangeTimezone {
+                from,
+                to,
+                keep_instant,
+            } => {
+                let mut num_changed = 0;
+                // Overrides name their instance by a RECURRENCE-ID in the
+                // time zone of the master, so all components move together
+                for event in changed.ical.events.iter_mut() {
+                    for property in event.properties.iter_mut().filter(|p| {
+                        ["DTSTART", "DTEND", "RECURRENCE-ID", "EXDATE", "RDATE"]
+                            .contains(&p.name.as_str())
+                    }) {
+                        let tzid = property.params.iter_mut().flatten().find(|(name, values)| {
+                            name == "TZID" && values.first().is_some_and(|v| v == from.name())
+                        });
+                        let tzid = match tzid {
+                            Some((_, tzid)) => tzid,
+                            None => continue,
+                        };
+
+                        if *keep_instant {
+                            // EXDATE and RDATE may hold several times
+                            let values = property
+                                .value
+                                .as_deref()
+                                .unwrap_or_default()
+                                .split(',')
+                                .map(|value| {
+                                    let local = NaiveDateTime::parse_from_str(
+                                        value,
+                                        ISO8601_2004_LOCAL_FORMAT,
+                                    )?;
+                                    let dt = from
+                                        .from_local_datetime(&local)
+                                        .earliest()
+                                        .ok_or_else(|| {
+                                            Error::new(
+                                                ErrorKind::DateParse,
+                                                &format!(
+                                                    "{} does not exist in {}",
+                                                    value,
+                                                    from.name()
+                                                ),
+                                            )
+                                        })?;
+                                    Ok(dt
+                                        .with_timezone(to)
+                                        .format(ISO8601_2004_LOCAL_FORMAT)
+                                        .to_string())
+                                })
+                                .collect::<Result<Vec<_>>>()?;
+                            property.value = Some(values.join(","));
+                        }
+                        *tzid = vec![to.name().to_owned()];
+                        num_changed += 1;
+                    }
+                }
+
+                if num_changed == 0 {
+                    return Err(Error::new(
+                        ErrorKind::Invalid,
+                        &format!("Event has no times in {}", from.name()),
+                    ));
+                }
+
+                // Keep the definition of the old time zone while properties
+                // not moved above, e.g. of extensions, still refer to it
+                let still_used = changed.ical.events.iter().any(|event| {
+                    let alarm_properties = event.alarms.iter().flat_map(|a| &a.properties);
+                    event.properties.iter().chain(alarm_properties).any(|p| {
+                        p.params.iter().flatten().any(|(name, values)| {
+                            name == "TZID" && values.first().is_some_and(|v| v == from.name())
+                        })
+                    })
+                });
+                if !still_used {
+                    changed.ical.timezones.retain(|timezone| {
+                        !timezone
+                            .properties
+                            .iter()
+                            .any(|p| p.name == "TZID" && p.value.as_deref() == Some(from.name()))
+                    });
+                }
+                if !keep_instant {
+                    changed.set_property("SEQUENCE", (self.sequence() + 1).to_string());
+                }
+            }
+        }
+
+        changed.set_property("LAST-MODIFIED", super::generate_timestamp());
+        Event::parse_component(&changed.path, changed.ical, self.component, self.master)
+            .map_err(|err| err.in_file(&self.path))
     }
 }
 
@@ -593,7 +1005,7 @@ impl Eventlike for Event {
         if let Some(property) = self.get_property_mut("SUMMARY") {
             property.value = Some(title.to_owned());
         } else {
-            self.ical.events[0].add_property(Property {
+            self.component_mut().add_property(Property {
                 name: "SUMMARY".to_owned(),
                 params: None,
                 value: Some(title.to_owned()),
@@ -605,6 +1017,16 @@ impl Eventlike for Event {
         uuid::Uuid::parse_str(self.get_property_value("UID").unwrap()).unwrap()
     }
 
+    fn uid(&self) -> &str {
+        self.get_property_value("UID").unwrap_or_default()
+    }
+
+    fn recurrence_id(&self) -> Option<DateTime<Utc>> {
+        series::instant(self.component(), "RECURRENCE-ID")
+            .ok()
+            .flatten()
+    }
+
     fn summary(&self) -> &str {
         self.title()
     }
@@ -642,11 +1064,203 @@ impl Eventlike for Event {
     fn duration(&self) -> Duration {
         self.occurrence.duration().into()
     }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn raw_ical(&self) -> String {
+        super::writer::write_calendar(&self.ical)
+    }
+
+    fn categories(&self) -> Vec<String> {
+        self.properties_named("CATEGORIES")
+            .into_iter()
+            .filter_map(|p| p.value.as_deref())
+            .flat_map(split_text_list)
+            .filter(|category| !category.is_empty())
+            .collect()
+    }
+
+    fn location(&self) -> Option<String> {
+        self.get_property_value("LOCATION")
+            .map(super::xcal::unescape_text)
+    }
+
+    fn url(&self) -> Option<String> {
+        self.get_property_value("URL").map(str::to_owned)
+    }
+
+    fn geo(&self) -> Option<(f64, f64)> {
+        let (lat, lon) = self.get_property_value("GEO")?.split_once(';')?;
+        let lat: f64 = lat.trim().parse().ok()?;
+        let lon: f64 = lon.trim().parse().ok()?;
+        ((-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)).then_some((lat, lon))
+    }
+
+    fn attendees(&self) -> Vec<String> {
+        self.properties_named("ATTENDEE")
+            .into_iter()
+            .filter_map(|p| {
+                let name = p
+                    .params
+                    .iter()
+                    .flatten()
+                    .find(|(name, _)| name == "CN")
+                    .and_then(|(_, values)| values.first())
+                    .map(|name| name.trim_matches('"').to_owned());
+                name.or_else(|| {
+                    let address = p.value.as_deref()?;
+                    let address = address
+                        .strip_prefix("mailto:")
+                        .or_else(|| address.strip_prefix("MAILTO:"))
+                        .unwrap_or(address);
+                    Some(address.to_owned())
+                })
+            })
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+
+    fn description(&self) -> Option<String> {
+        let description = self
+            .get_property_value("DESCRIPTION")
+            .map(super::xcal::unescape_text);
+        if description.as_deref().is_some_and(|d| !html::is_garbled(d)) {
+            return description;
+        }
+
+        let alt_description = self
+            .properties_named("X-ALT-DESC")
+            .into_iter()
+            .find(|prop| {
+                prop.params.iter().flatten().any(|(name, values)| {
+                    name.eq_ignore_ascii_case("FMTTYPE")
+                        && values.iter().any(|v| v.eq_ignore_ascii_case("text/html"))
+                })
+            });
+        alt_description
+            .and_then(|prop| prop.value.as_deref())
+            .map(|html| html::html_to_text(&super::xcal::unescape_text(html)))
+            .filter(|text| !text.is_empty())
+            .or(description)
+    }
+
+    fn is_pinned(&self) -> bool {
+        self.get_property_value(PINNED_PROPERTY)
+            .is_some_and(|value| value.eq_ignore_ascii_case("TRUE"))
+    }
+
+    fn is_opaque(&self) -> bool {
+        !self
+            .get_property_value("TRANSP")
+            .is_some_and(|value| value.eq_ignore_ascii_case("TRANSPARENT"))
+    }
+
+    fn alarms(&self) -> Vec<Alarm> {
+        // Overrides without alarms of their own keep those of the series,
+        // their offsets are relative to the overridden start
+        let alarms = match self.master {
+            Some(master) if self.component().alarms.is_empty() => &self.ical.events[master].alarms,
+            _ => &self.component().alarms,
+        };
+        alarms
+            .iter()
+            .filter_map(|alarm| {
+                let property = |name: &str| alarm.properties.iter().find(|p| p.name == name);
+                let trigger = property("TRIGGER")?;
+
+                let absolute = trigger.params.iter().flatten().any(|(name, values)| {
+                    name == "VALUE" && values.first().is_some_and(|v| v == "DATE-TIME")
+                });
+                let related_to_end = trigger.params.iter().flatten().any(|(name, values)| {
+                    name == "RELATED" && values.first().is_some_and(|v| v == "END")
+                });
+                let parsed = if absolute {
+                    IcalDateTime::try_from(trigger).map(|time| {
+                        let time = time.as_datetime(&self.tz).with_timezone(&Utc);
+                        (Duration::zero(), AlarmRelation::At(time))
+                    })
+                } else {
+                    IcalDuration::try_from(trigger).map(|offset| {
+                        let related = if related_to_end {
+                            AlarmRelation::End
+                        } else {
+                            AlarmRelation::Start
+                        };
+                        (offset.into(), related)
+                    })
+                };
+                let (offset, related) = match parsed {
+                    Ok(trigger) => trigger,
+                    Err(_) => {
+                        log::debug!(
+                            "Ignoring alarm of {} with trigger {:?}",
+                            self.uid(),
+                            trigger.value
+                        );
+                        return None;
+                    }
+                };
+
+                // REPEAT and DURATION only count together, see RFC 5545 3.6.6
+                let repeat = property("REPEAT")
+                    .and_then(|p| p.value.as_deref())
+                    .and_then(|value| value.trim().parse::<u32>().ok());
+                let interval = property("DURATION").and_then(|p| IcalDuration::try_from(p).ok());
+                let (repeat, interval) = match (repeat, interval) {
+                    (Some(repeat), Some(interval)) => (repeat, interval.into()),
+                    (None, None) => (0, Duration::zero()),
+                    _ => {
+                        log::debug!(
+                            "Ignoring repetition of alarm of {} without REPEAT or DURATION",
+                            self.uid()
+                        );
+                        (0, Duration::zero())
+                    }
+                };
+
+                Some(Alarm {
+                    offset,
+                    related,
+                    action: property("ACTION")
+                        .and_then(|p| p.value.clone())
+                        .unwrap_or_else(|| "DISPLAY".to_owned()),
+                    description: property("DESCRIPTION")
+                        .and_then(|p| p.value.as_deref())
+                        .map(super::xcal::unescape_text),
+                    repeat,
+                    interval,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Splits a comma separated list of TEXT values and removes their escaping
+fn split_text_list(value: &str) -> Vec<String> {
+    let mut values = vec![String::new()];
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        let current = values.last_mut().unwrap();
+        match c {
+            '\\' => match chars.next() {
+                Some('n') | Some('N') => current.push('\n'),
+                Some(escaped) => current.push(escaped),
+                None => {}
+            },
+            ',' => values.push(String::new()),
+            c => current.push(c),
+        }
+    }
+
+    values
 }
 
 impl From<Event> for IcalEvent {
     fn from(event: Event) -> Self {
-        event.ical.events[0].clone()
+        event.component().clone()
     }
 }
 
@@ -660,8 +1274,204 @@ pub struct Calendar {
     path: PathBuf,
     identifier: String,
     friendly_name: String,
+    color: Option<String>,
     tz: Tz,
     events: BTreeMap<DateTime<Tz>, Vec<Event>>,
+    todos: Vec<Todo>,
+    modified: BTreeMap<PathBuf, SystemTime>,
+    /// Whether the directory was there when last looked at
+    available: bool,
+}
+
+/// The content of a file of a calendar
+enum Item {
+    /// The instances of the event of the file, see `Event::series_from_ical`
+    Events(Vec<Event>),
+    Todo(Box<Todo>),
+}
+
+impl Item {
+    fn from_file(path: &Path) -> Result<Self> {
+        Self::from_ical(path, read_ical_file(path)?)
+    }
+
+    fn from_ical(path: &Path, ical: IcalCalendar) -> Result<Self> {
+        if ical.events.is_empty() && !ical.todos.is_empty() {
+            Ok(Item::Todo(Box::new(Todo::from_ical(path, ical)?)))
+        } else {
+            Ok(Item::Events(Event::series_from_ical(path, ical)?))
+        }
+    }
+}
+
+/// Reads the event or task file at `path` the way calendars load it and
+/// returns its UID, e.g. to check a file before it replaces one in a calendar
+pub fn check_file(path: &Path) -> Result<String> {
+    Ok(match Item::from_file(path)? {
+        Item::Events(events) => events
+            .first()
+            .map(|event| event.uid().to_owned())
+            .unwrap_or_default(),
+        Item::Todo(todo) => todo.uid().to_owned(),
+    })
+}
+
+/// All event and task files in the directory `path` that can be read
+fn read_items(path: &Path) -> Result<Vec<Item>> {
+    Ok(fs::read_dir(path)?
+        .filter(|dir| {
+            dir.as_ref().map_or(true, |file| {
+                file.file_name().to_string_lossy().ends_with(ICAL_FILE_EXT)
+            })
+        })
+        .map(|dir| {
+            dir.map_or_else(
+                |err| -> Result<_> { Err(Error::from(err).in_file(path)) },
+                |file: fs::DirEntry| -> Result<Item> { Item::from_file(file.path().as_path()) },
+            )
+        })
+        .inspect(|res| {
+            if let Err(err) = res {
+                log::warn!("{}", err)
+            }
+        })
+        .filter_map(Result::ok)
+        .collect())
+}
+
+/// The first calendar in the file at `path`
+fn read_ical_file(path: &Path) -> Result<IcalCalendar> {
+    let buf =
+        io::BufReader::new(fs::File::open(path).map_err(|err| Error::from(err).in_file(path))?);
+
+    match IcalParser::new(buf).next() {
+        Some(Ok(ical)) => Ok(ical),
+        Some(Err(e)) => Err(Error::new(
+            ErrorKind::CalendarParse,
+            &format!("No calendar could be read: {}", e),
+        )
+        .in_file(path)),
+        None => Err(Error::new(ErrorKind::CalendarParse, "No calendar found").in_file(path)),
+    }
+}
+
+/// A single calendar with the events of all of `calendars` and each of
+/// their timezones once
+pub(super) fn combined_calendar<'a>(
+    calendars: impl IntoIterator<Item = &'a IcalCalendar>,
+) -> String {
+    let mut combined = IcalCalendar::new();
+    combined.properties = vec![
+        Property {
+            name: "PRODID".to_owned(),
+            params: None,
+            value: Some(super::JACKAL_PRODID.to_owned()),
+        },
+        Property {
+            name: "VERSION".to_owned(),
+            params: None,
+            value: Some(super::JACKAL_CALENDAR_VERSION.to_owned()),
+        },
+    ];
+
+    let tzid = |tz: &IcalTimeZone| {
+        tz.properties
+            .iter()
+            .find(|p| p.name == "TZID")
+            .and_then(|p| p.value.clone())
+    };
+    for calendar in calendars {
+        for timezone in &calendar.timezones {
+            if !combined
+                .timezones
+                .iter()
+                .any(|tz| tzid(tz) == tzid(timezone))
+            {
+                combined.timezones.push(timezone.clone());
+            }
+        }
+        combined.events.extend(calendar.events.iter().cloned());
+    }
+
+    super::writer::write_calendar(&combined)
+}
+
+/// Combines the iCalendar documents `documents`, e.g. the raw data of events
+/// of different calendars, into a single one
+pub fn combine_ics(documents: &[String]) -> Result<String> {
+    let calendars = documents
+        .iter()
+        .map(
+            |document| match IcalParser::new(document.as_bytes()).next() {
+                Some(Ok(ical)) => Ok(ical),
+                Some(Err(e)) => Err(Error::new(
+                    ErrorKind::CalendarParse,
+                    &format!("No calendar could be read: {}", e),
+                )),
+                None => Err(Error::new(ErrorKind::CalendarParse, "No calendar found")),
+            },
+        )
+        .collect::<Result<Vec<_>>>()?;
+    Ok(combined_calendar(&calendars))
+}
+
+fn read_metadata(path: &Path, file: &str) -> Option<String> {
+    fs::read_to_string(path.join(file))
+        .ok()
+        .map(|content| content.trim().to_owned())
+        .filter(|content| !content.is_empty())
+}
+
+/// The suspension markers in `path` and whether they are still in effect.
+/// Markers are ignored once they are too old, in case the operation crashed.
+fn suspend_markers(path: &Path) -> Vec<(PathBuf, bool)> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(SUSPEND_MARKER_PREFIX)
+        })
+        .filter_map(|entry| {
+            let created = entry.metadata().and_then(|m| m.modified()).ok()?;
+            let active = created
+                .elapsed()
+                .map_or(true, |age| age < MAX_WATCH_SUSPENSION);
+            Some((entry.path(), active))
+        })
+        .collect()
+}
+
+/// Whether a bulk operation asked to stop reloading the calendar in `path`
+fn watching_suspended(path: &Path) -> bool {
+    suspend_markers(path).iter().any(|(_, active)| *active)
+}
+
+/// Modification times of all event files in `path`
+fn modification_times(path: &Path) -> Result<BTreeMap<PathBuf, SystemTime>> {
+    Ok(fs::read_dir(path)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(ICAL_FILE_EXT))
+        .map(|entry| {
+            let modified = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            (entry.path(), modified)
+        })
+        .collect())
+}
+
+fn is_valid_color(color: &str) -> bool {
+    // vdirsyncer stores colors as '#RRGGBB' with an optional alpha channel
+    color.strip_prefix('#').is_some_and(|hex| {
+        (hex.len() == 6 || hex.len() == 8) && hex.chars().all(|c| c.is_ascii_hexdigit())
+    })
 }
 
 impl Calendar {
@@ -673,8 +1483,12 @@ impl Calendar {
             path: path.to_owned(),
             identifier: identifier.to_string(),
             friendly_name: friendly_name.to_string(),
+            color: None,
             tz: Tz::UTC,
             events: BTreeMap::new(),
+            todos: Vec::new(),
+            modified: BTreeMap::new(),
+            available: true,
         }
     }
 
@@ -685,39 +1499,66 @@ impl Calendar {
             path: path.to_owned(),
             identifier: identifier.to_string(),
             friendly_name: name,
+            color: None,
+            tz: Tz::UTC,
+            events: BTreeMap::new(),
+            todos: Vec::new(),
+            modified: BTreeMap::new(),
+            available: true,
+        }
+    }
+
+    /// A calendar for the missing directory `path` without any events, which
+    /// loads them once the directory is back
+    pub fn unavailable(path: &Path) -> Self {
+        log::warn!(
+            "Calendar directory {} is missing, the calendar is unavailable until it is back",
+            path.display()
+        );
+        Self {
+            path: path.to_owned(),
+            identifier: path.file_stem().unwrap().to_string_lossy().to_string(),
+            friendly_name: String::new(),
+            color: None,
             tz: Tz::UTC,
             events: BTreeMap::new(),
+            todos: Vec::new(),
+            modified: BTreeMap::new(),
+            available: false,
+        }
+    }
+
+    /// Loads the calendar at `path` or an unavailable one if the directory is
+    /// missing
+    pub fn from_dir_or_unavailable(path: &Path) -> Result<Self> {
+        if path.exists() {
+            Self::from_dir(path)
+        } else {
+            Ok(Self::unavailable(path))
         }
     }
 
     pub fn from_dir(path: &Path) -> Result<Self> {
+        let _span = timing::span("calendar_load", path.display());
         let mut events = BTreeMap::<DateTime<Tz>, Vec<Event>>::new();
+        let mut todos = Vec::new();
 
         if !path.is_dir() {
-            return Err(Error::new(
-                ErrorKind::CalendarParse,
-                &format!("'{}' is not a directory", path.display()),
-            ));
+            return Err(Error::new(ErrorKind::NotFound, "Not a directory").in_file(path));
         }
 
-        let event_file_iter = fs::read_dir(&path)?
-            .map(|dir| {
-                dir.map_or_else(
-                    |_| -> Result<_> { Err(Error::from(ErrorKind::CalendarParse)) },
-                    |file: fs::DirEntry| -> Result<Event> {
-                        Event::from_file(file.path().as_path())
-                    },
-                )
-            })
-            .inspect(|res| {
-                if let Err(err) = res {
-                    log::warn!("{}", err)
-                }
-            })
-            .filter_map(Result::ok);
+        // Taken before reading so that changes during loading are noticed
+        let modified = modification_times(path)?;
 
-        for event in event_file_iter {
-            events.entry(event.begin()).or_default().push(event);
+        for item in read_items(path)? {
+            match item {
+                Item::Events(instances) => {
+                    for event in instances {
+                        events.entry(event.begin()).or_default().push(event);
+                    }
+                }
+                Item::Todo(todo) => todos.push(*todo),
+            }
         }
 
         // TODO: use `BTreeMap::first_entry` once it's stable: https://github.com/rust-lang/rust/issues/62924
@@ -727,13 +1568,124 @@ impl Calendar {
             Tz::UTC
         };
 
-        Ok(Calendar {
+        let calendar = Calendar {
             path: path.to_owned(),
             identifier: path.file_stem().unwrap().to_string_lossy().to_string(),
-            friendly_name: String::default(),
+            friendly_name: read_metadata(path, METADATA_DISPLAYNAME_FILE).unwrap_or_default(),
+            color: read_metadata(path, METADATA_COLOR_FILE),
             tz,
             events,
-        })
+            todos,
+            modified,
+            available: true,
+        };
+
+        for conflict in calendar.uid_conflicts() {
+            log::warn!(
+                "Duplicate UID '{}' in '{}' ({} files)",
+                conflict.uid,
+                calendar.path.display(),
+                conflict.paths.len()
+            );
+        }
+
+        Ok(calendar)
+    }
+
+    /// Converts the naive bounds of `filter` into bounds of the event index
+    fn index_range(&self, filter: &EventFilter) -> (Bound<DateTime<Tz>>, Bound<DateTime<Tz>>) {
+        // TODO: Change once https://github.com/rust-lang/rust/issues/86026 is stable
+        let convert = |bound: &Bound<NaiveDateTime>| match bound {
+            Bound::Included(dt) => {
+                Bound::Included(self.tz().from_local_datetime(dt).earliest().unwrap())
+            }
+            Bound::Excluded(dt) => {
+                Bound::Excluded(self.tz().from_local_datetime(dt).earliest().unwrap())
+            }
+            _ => Bound::Unbounded,
+        };
+
+        (convert(&filter.begin), convert(&filter.end))
+    }
+
+    /// The events by UID, only one per file for recurring events with
+    /// overrides
+    fn events_by_uid(&self) -> BTreeMap<&str, Vec<&Event>> {
+        let mut by_uid = BTreeMap::<&str, Vec<&Event>>::new();
+        for event in self.events.values().flatten() {
+            let events = by_uid.entry(event.uid()).or_default();
+            if !events.iter().any(|ev| ev.path() == event.path()) {
+                events.push(event);
+            }
+        }
+        by_uid
+    }
+
+    fn insert_events(&mut self, events: Vec<Event>) {
+        for event in events {
+            self.events.entry(event.begin()).or_default().push(event);
+        }
+    }
+
+    /// The event `id` names. Without a RECURRENCE-ID that is the master of
+    /// a recurring event unless an override replaced its instance.
+    fn find_event(&self, id: &EventId) -> Result<&Event> {
+        self.events
+            .values()
+            .flatten()
+            .filter(|ev| ev.uid() == id.uid)
+            .filter(|ev| id.recurrence_id.is_none() || ev.recurrence_id() == id.recurrence_id)
+            .min_by_key(|ev| ev.is_override())
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    &format!("No event with UID '{}'", id.uid),
+                )
+            })
+    }
+
+    /// Replaces the events loaded from `path` by those of `ical`, the new
+    /// content of the file
+    fn replace_file_events(&mut self, path: &Path, ical: Option<IcalCalendar>) -> Result<()> {
+        self.events.values_mut().for_each(|events| {
+            events.retain(|ev| ev.path() != path);
+        });
+        self.events.retain(|_, events| !events.is_empty());
+        if let Some(ical) = ical {
+            let events = Event::series_from_ical(path, ical)?;
+            self.insert_events(events);
+        }
+        Ok(())
+    }
+
+    /// Remembers the state of `path` after we wrote or removed it, so that
+    /// `reload` does not read our own changes back as external ones
+    fn record_own_write(&mut self, path: &Path) {
+        match fs::metadata(path).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => {
+                self.modified.insert(path.to_owned(), modified);
+            }
+            Err(_) => {
+                self.modified.remove(path);
+            }
+        }
+    }
+
+    /// Refuses changes while the directory is missing, they could not be
+    /// written anyway
+    fn check_available(&self) -> Result<()> {
+        if self.available {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ErrorKind::Unavailable,
+                &format!(
+                    "Calendar '{}' is unavailable, {} is missing",
+                    self.identifier,
+                    self.path.display()
+                ),
+            ))
+        }
     }
 
     pub fn with_name(mut self, name: String) -> Self {
@@ -747,10 +1699,39 @@ impl Calendar {
 }
 
 impl Calendarlike for Calendar {
+    fn id(&self) -> &str {
+        &self.identifier
+    }
+
     fn name(&self) -> &str {
         &self.friendly_name
     }
 
+    fn rename(&mut self, name: &str) -> Result<()> {
+        self.check_available()?;
+        fs::write(self.path.join(METADATA_DISPLAYNAME_FILE), name)?;
+        self.set_name(name.to_owned());
+        Ok(())
+    }
+
+    fn color(&self) -> Option<&str> {
+        self.color.as_deref()
+    }
+
+    fn set_color(&mut self, color: &str) -> Result<()> {
+        self.check_available()?;
+        if !is_valid_color(color) {
+            return Err(Error::new(
+                ErrorKind::ColorParse,
+                &format!("'{}' is not of the form '#RRGGBB'", color),
+            ));
+        }
+
+        fs::write(self.path.join(METADATA_COLOR_FILE), color)?;
+        self.color = Some(color.to_owned());
+        Ok(())
+    }
+
     fn path(&self) -> &Path {
         &self.path
     }
@@ -763,7 +1744,263 @@ impl Calendarlike for Calendar {
         unimplemented!();
     }
 
-    fn event_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &(dyn Eventlike + 'a)> + 'a> {
+    fn is_available(&self) -> bool {
+        self.available
+    }
+
+    fn uid_conflicts(&self) -> Vec<UidConflict> {
+        self.events_by_uid()
+            .into_iter()
+            .filter(|(_, events)| events.len() > 1)
+            .map(|(uid, events)| UidConflict {
+                uid: uid.to_owned(),
+                paths: events.iter().map(|ev| ev.path().to_owned()).collect(),
+            })
+            .collect()
+    }
+
+    fn resolve_uid_conflict(&mut self, uid: &str) -> Result<Vec<PathBuf>> {
+        self.check_available()?;
+        // Keep the event with the highest SEQUENCE, first loaded wins on ties
+        let keep = self
+            .events_by_uid()
+            .remove(uid)
+            .and_then(|events| {
+                events
+                    .into_iter()
+                    .rev()
+                    .max_by_key(|ev| ev.sequence())
+                    .map(|ev| ev.path().to_owned())
+            })
+            .ok_or_else(|| {
+                Error::new(ErrorKind::NotFound, &format!("No event with UID '{}'", uid))
+            })?;
+
+        let mut removed = Vec::new();
+        for events in self.events.values_mut() {
+            for event in events
+                .iter()
+                .filter(|ev| ev.uid() == uid && ev.path() != keep)
+            {
+                if !removed.iter().any(|path| path == event.path()) {
+                    fs::remove_file(event.path())?;
+                    removed.push(event.path().to_owned());
+                }
+            }
+            events.retain(|ev| ev.uid() != uid || ev.path() == keep);
+        }
+        self.events.retain(|_, events| !events.is_empty());
+        for path in &removed {
+            self.record_own_write(path);
+        }
+
+        Ok(removed)
+    }
+
+    fn remove_event(&mut self, event: &EventId) -> Result<()> {
+        self.check_available()?;
+        if event.recurrence_id.is_some() {
+            let removed = self.find_event(event)?;
+            let path = removed.path().to_owned();
+            let remaining = removed.ical_without_override();
+            match &remaining {
+                Some(ical) => fs::write(&path, super::writer::write_calendar(ical))?,
+                None => fs::remove_file(&path)?,
+            }
+            self.record_own_write(&path);
+            self.replace_file_events(&path, remaining)?;
+            return Ok(());
+        }
+
+        let uid = event.uid.as_str();
+        let paths: BTreeSet<PathBuf> = self
+            .events
+            .values()
+            .flatten()
+            .filter(|ev| ev.uid() == uid)
+            .map(|ev| ev.path().to_owned())
+            .collect();
+
+        if paths.is_empty() {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                &format!("No event with UID '{}'", uid),
+            ));
+        }
+
+        for path in &paths {
+            fs::remove_file(path)?;
+            self.record_own_write(path);
+        }
+
+        self.events.values_mut().for_each(|events| {
+            events.retain(|ev| ev.uid() != uid);
+        });
+        self.events.retain(|_, events| !events.is_empty());
+
+        Ok(())
+    }
+
+    fn modify_event(&mut self, event: &EventId, change: &EventChange) -> Result<()> {
+        self.check_available()?;
+        let changed = self.find_event(event)?.with_change(change)?;
+
+        changed.save()?;
+        let path = changed.path().to_owned();
+        self.record_own_write(&path);
+        self.replace_file_events(&path, Some(changed.ical))?;
+
+        Ok(())
+    }
+
+    fn reload(&mut self) -> Result<bool> {
+        // Everything that changed meanwhile is picked up at once afterwards
+        if watching_suspended(&self.path) {
+            return Ok(false);
+        }
+
+        // Keep what was loaded while the drive or share is gone
+        if !self.path.is_dir() {
+            if self.available {
+                log::warn!(
+                    "Calendar directory {} is missing, '{}' is read-only until it is back",
+                    self.path.display(),
+                    self.identifier
+                );
+                self.available = false;
+            }
+            return Ok(false);
+        }
+        if !self.available {
+            log::info!("Calendar directory {} is back", self.path.display());
+            self.available = true;
+            if self.friendly_name.is_empty() {
+                self.friendly_name =
+                    read_metadata(&self.path, METADATA_DISPLAYNAME_FILE).unwrap_or_default();
+            }
+            if self.color.is_none() {
+                self.color = read_metadata(&self.path, METADATA_COLOR_FILE);
+            }
+        }
+
+        let current = modification_times(&self.path)?;
+        if current == self.modified {
+            return Ok(false);
+        }
+
+        let changed: BTreeSet<PathBuf> = current
+            .keys()
+            .chain(self.modified.keys())
+            .filter(|path| current.get(*path) != self.modified.get(*path))
+            .cloned()
+            .collect();
+
+        self.events.values_mut().for_each(|events| {
+            events.retain(|ev| !changed.contains(ev.path()));
+        });
+        self.events.retain(|_, events| !events.is_empty());
+        self.todos.retain(|todo| !changed.contains(todo.path()));
+
+        for path in changed.iter().filter(|path| current.contains_key(*path)) {
+            match Item::from_file(path) {
+                Ok(Item::Events(events)) => self.insert_events(events),
+                Ok(Item::Todo(todo)) => self.todos.push(*todo),
+                Err(e) => log::warn!("{}", e),
+            }
+        }
+
+        self.modified = current;
+        Ok(true)
+    }
+
+    fn last_modified(&self) -> Option<SystemTime> {
+        // Removing an event file only shows in the directory's time
+        let dir_modified = fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        self.modified.values().copied().chain(dir_modified).max()
+    }
+
+    fn archive(&mut self, before: DateTime<Utc>) -> Result<usize> {
+        self.check_available()?;
+        let archive_dir = self.path.join(ARCHIVE_DIR);
+        let is_archived = |event: &Event| {
+            event.end().with_timezone(&Utc) < before
+                // Recurring events may have occurrences after `before`
+                && event.get_property_value("RRULE").is_none()
+                && event.get_property_value("RDATE").is_none()
+                && !event.is_override()
+                // Archived events loaded by `load_archive` stay where they are
+                && event.path().parent() == Some(self.path.as_path())
+        };
+        let archived: BTreeSet<PathBuf> = self
+            .events
+            .values()
+            .flatten()
+            .filter(|event| is_archived(event))
+            .map(|event| event.path().to_owned())
+            .collect();
+        if archived.is_empty() {
+            return Ok(0);
+        }
+
+        fs::create_dir_all(&archive_dir).map_err(|err| Error::from(err).in_file(&archive_dir))?;
+        for path in &archived {
+            let target = archive_dir.join(path.file_name().unwrap_or_default());
+            fs::rename(path, &target).map_err(|err| Error::from(err).in_file(path))?;
+            self.record_own_write(path);
+        }
+
+        self.events.values_mut().for_each(|events| {
+            events.retain(|ev| !archived.contains(ev.path()));
+        });
+        self.events.retain(|_, events| !events.is_empty());
+        Ok(archived.len())
+    }
+
+    fn load_archive(&mut self) -> Result<usize> {
+        self.check_available()?;
+        let archive_dir = self.path.join(ARCHIVE_DIR);
+        if !archive_dir.is_dir() {
+            return Ok(0);
+        }
+
+        let mut num_events = 0;
+        for item in read_items(&archive_dir)? {
+            match item {
+                Item::Events(events) => {
+                    num_events += events.len();
+                    self.insert_events(events);
+                }
+                Item::Todo(todo) => self.todos.push(*todo),
+            }
+        }
+        Ok(num_events)
+    }
+
+    fn suspend_watching(&self) -> Result<Vec<PathBuf>> {
+        self.check_available()?;
+        for (stale, _) in suspend_markers(&self.path)
+            .into_iter()
+            .filter(|(_, active)| !active)
+        {
+            let _ = fs::remove_file(stale);
+        }
+
+        // Nested or concurrent operations each have a marker of their own,
+        // so that finishing one does not resume watching for the others
+        let marker = self.path.join(format!(
+            "{}-{}-{}",
+            SUSPEND_MARKER_PREFIX,
+            std::process::id(),
+            uuid::Uuid::new_v4().simple()
+        ));
+        fs::write(&marker, std::process::id().to_string())
+            .map_err(|err| Error::from(err).in_file(&marker))?;
+        Ok(vec![marker])
+    }
+
+    fn event_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a (dyn Eventlike + 'a)> + 'a> {
         Box::new(
             self.events
                 .iter()
@@ -775,37 +2012,156 @@ impl Calendarlike for Calendar {
     fn filter_events<'a>(
         &'a self,
         filter: EventFilter,
-    ) -> Box<dyn Iterator<Item = &(dyn Eventlike + 'a)> + 'a> {
-        // TODO: Change once https://github.com/rust-lang/rust/issues/86026 is stable
-        let real_begin = match filter.begin {
-            Bound::Included(dt) => {
-                Bound::Included(self.tz().from_local_datetime(&dt).earliest().unwrap())
-            }
-            Bound::Excluded(dt) => {
-                Bound::Excluded(self.tz().from_local_datetime(&dt).earliest().unwrap())
-            }
-            _ => Bound::Unbounded,
-        };
-        let real_end = match filter.end {
-            Bound::Included(dt) => {
-                Bound::Included(self.tz().from_local_datetime(&dt).earliest().unwrap())
-            }
-            Bound::Excluded(dt) => {
-                Bound::Excluded(self.tz().from_local_datetime(&dt).earliest().unwrap())
-            }
-            _ => Bound::Unbounded,
-        };
+    ) -> Box<dyn Iterator<Item = &'a (dyn Eventlike + 'a)> + 'a> {
+        if !filter.includes_calendar(self) {
+            return Box::new(std::iter::empty());
+        }
 
         Box::new(
             self.events
-                .range((real_begin, real_end))
+                .range(self.index_range(&filter))
                 .flat_map(|(_, v)| v.iter())
                 .map(|ev| (ev as &dyn Eventlike)),
         )
     }
 
-    fn new_event(&mut self) {
-        unimplemented!()
+    fn import_ics(&mut self, ics: &str) -> Result<ImportSummary> {
+        self.check_available()?;
+        let mut summary = ImportSummary::default();
+
+        for ical in IcalParser::new(ics.as_bytes()) {
+            let ical = ical.map_err(|e| {
+                Error::new(
+                    ErrorKind::CalendarParse,
+                    &format!("Could not read calendar: {}", e),
+                )
+            })?;
+
+            for (uid, group) in super::import::split_by_uid(ical) {
+                let path = self
+                    .events
+                    .values()
+                    .flatten()
+                    .find(|ev| ev.uid() == uid)
+                    .map(|ev| ev.path().to_owned())
+                    .or_else(|| {
+                        self.todos
+                            .iter()
+                            .find(|todo| todo.uid() == uid)
+                            .map(|todo| todo.path().to_owned())
+                    })
+                    .unwrap_or_else(|| {
+                        self.path.join(format!(
+                            "{}{}",
+                            super::import::file_stem_for_uid(&uid),
+                            ICAL_FILE_EXT
+                        ))
+                    });
+
+                let num_overrides = group
+                    .events
+                    .iter()
+                    .filter(|ev| super::import::is_override(ev))
+                    .count();
+
+                fs::write(&path, super::writer::write_calendar(&group))?;
+                self.record_own_write(&path);
+                summary.files += 1;
+                summary.events += group.events.len() - num_overrides;
+                summary.overrides += num_overrides;
+
+                self.events.values_mut().for_each(|events| {
+                    events.retain(|ev| ev.uid() != uid);
+                });
+                self.events.retain(|_, events| !events.is_empty());
+                self.todos.retain(|todo| todo.uid() != uid);
+
+                match Item::from_ical(&path, group) {
+                    Ok(Item::Events(events)) => self.insert_events(events),
+                    Ok(Item::Todo(todo)) => self.todos.push(*todo),
+                    Err(e) => log::warn!("Imported '{}' but could not load it: {}", uid, e),
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    fn export_ics(&self) -> String {
+        let mut exported = BTreeSet::new();
+        combined_calendar(
+            self.events
+                .values()
+                .flatten()
+                // The instances of a recurring event share their file
+                .filter(|event| exported.insert(event.path()))
+                .map(|event| &event.ical),
+        )
+    }
+
+    fn todo_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a (dyn Todolike + 'a)> + 'a> {
+        Box::new(self.todos.iter().map(|todo| todo as &dyn Todolike))
+    }
+
+    fn set_todo_done(&mut self, uid: &str, done: bool) -> Result<()> {
+        self.check_available()?;
+        let todo = self
+            .todos
+            .iter_mut()
+            .find(|todo| todo.uid() == uid)
+            .ok_or_else(|| {
+                Error::new(ErrorKind::NotFound, &format!("No task with UID '{}'", uid))
+            })?;
+
+        *todo = todo.with_done(done);
+        todo.save()?;
+        let path = todo.path().to_owned();
+        self.record_own_write(&path);
+        Ok(())
+    }
+
+    fn count_events(&self, filter: EventFilter) -> usize {
+        if !filter.includes_calendar(self) {
+            return 0;
+        }
+
+        self.events
+            .range(self.index_range(&filter))
+            .map(|(_, v)| v.len())
+            .sum()
+    }
+
+    fn next_event_after<'a>(&'a self, dt: &DateTime<Tz>) -> Option<&'a (dyn Eventlike + 'a)> {
+        self.events
+            .range((Bound::Excluded(dt), Bound::Unbounded))
+            .flat_map(|(_, v)| v.iter())
+            .map(|ev| ev as &dyn Eventlike)
+            .next()
+    }
+
+    fn new_event(&mut self, new_event: NewEvent<Tz>) -> Result<String> {
+        self.check_available()?;
+        let mut builder = EventBuilder::new(&self.path, new_event.begin);
+        if let Some(end) = new_event.end {
+            builder.set_end(end);
+        }
+        if let Some(summary) = new_event.summary {
+            builder.set_description(summary);
+        }
+        if let Some(location) = new_event.location {
+            builder.set_location(location);
+        }
+        if let Some(rrule) = new_event.rrule {
+            builder.set_rrule(rrule);
+        }
+
+        let event = builder.finish()?;
+        event.save()?;
+        self.record_own_write(event.path());
+        let uid = event.uid().to_owned();
+        self.events.entry(event.begin()).or_default().push(event);
+
+        Ok(uid)
     }
 }
 
@@ -813,21 +2169,33 @@ pub struct Collection {
     path: PathBuf,
     friendly_name: String,
     calendars: Vec<Calendar>,
+    /// Whether the directory was missing when loading, so that the calendars
+    /// in it are not known yet
+    missing: bool,
 }
 
 impl Collection {
     pub fn from_dir(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            log::warn!(
+                "Collection directory {} is missing, its calendars are loaded once it is back",
+                path.display()
+            );
+            return Ok(Collection {
+                path: path.to_owned(),
+                friendly_name: path.file_stem().unwrap().to_string_lossy().to_string(),
+                calendars: Vec::new(),
+                missing: true,
+            });
+        }
         if !path.is_dir() {
-            return Err(Error::new(
-                ErrorKind::CalendarParse,
-                &format!("'{}' is not a directory", path.display()),
-            ));
+            return Err(Error::new(ErrorKind::NotFound, "Not a directory").in_file(path));
         }
 
         let calendars: Vec<Calendar> = fs::read_dir(&path)?
             .map(|dir| {
                 dir.map_or_else(
-                    |_| -> Result<_> { Err(Error::from(io::ErrorKind::InvalidData)) },
+                    |err| -> Result<_> { Err(Error::from(err).in_file(path)) },
                     |file: fs::DirEntry| -> Result<Calendar> {
                         Calendar::from_dir(file.path().as_path())
                     },
@@ -845,15 +2213,15 @@ impl Collection {
             path: path.to_owned(),
             friendly_name: path.file_stem().unwrap().to_string_lossy().to_string(),
             calendars,
+            missing: false,
         })
     }
 
     pub fn calendars_from_dir(path: &Path, calendar_specs: &[CalendarSpec]) -> Result<Self> {
-        if !path.is_dir() {
-            return Err(Error::new(
-                ErrorKind::CalendarParse,
-                &format!("'{}' is not a directory", path.display()),
-            ));
+        // Configured calendars of a missing directory are known, so they are
+        // loaded as unavailable below
+        if path.exists() && !path.is_dir() {
+            return Err(Error::new(ErrorKind::NotFound, "Not a directory").in_file(path));
         }
 
         if calendar_specs.is_empty() {
@@ -862,16 +2230,22 @@ impl Collection {
 
         let calendars: Vec<Calendar> = calendar_specs
             .into_iter()
-            .filter_map(|spec| match Calendar::from_dir(&path.join(&spec.id)) {
-                Ok(calendar) => Some(calendar.with_name(spec.name.clone())),
-                Err(_) => None,
-            })
+            .filter_map(
+                |spec| match Calendar::from_dir_or_unavailable(&path.join(&spec.id)) {
+                    Ok(calendar) => Some(calendar.with_name(spec.name.clone())),
+                    Err(err) => {
+                        log::warn!("{}", err);
+                        None
+                    }
+                },
+            )
             .collect();
 
         Ok(Collection {
             path: path.to_owned(),
             friendly_name: path.file_stem().unwrap().to_string_lossy().to_string(),
             calendars,
+            missing: false,
         })
     }
 }
@@ -885,15 +2259,49 @@ impl Collectionlike for Collection {
         &self.path
     }
 
-    fn calendar_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &(dyn Calendarlike + 'a)> + 'a> {
+    fn calendar_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a (dyn Calendarlike + 'a)> + 'a> {
         Box::new(self.calendars.iter().map(|c| c as &dyn Calendarlike))
     }
 
-    fn event_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &(dyn Eventlike + 'a)> + 'a> {
+    fn calendar_iter_mut<'a>(
+        &'a mut self,
+    ) -> Box<dyn Iterator<Item = &'a mut (dyn Calendarlike + 'a)> + 'a> {
+        Box::new(
+            self.calendars
+                .iter_mut()
+                .map(|c| c as &mut dyn Calendarlike),
+        )
+    }
+
+    fn event_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a (dyn Eventlike + 'a)> + 'a> {
         Box::new(self.calendars.iter().flat_map(|c| c.event_iter()))
     }
 
     fn new_calendar(&mut self) {
         unimplemented!();
     }
+
+    fn load_missing(&mut self) -> bool {
+        if !self.missing || !self.path.is_dir() {
+            return false;
+        }
+
+        log::info!("Collection directory {} is back", self.path.display());
+        match Self::from_dir(&self.path) {
+            Ok(collection) => {
+                self.calendars = collection.calendars;
+                self.missing = false;
+                !self.calendars.is_empty()
+            }
+            Err(err) => {
+                log::warn!("{}", err);
+                false
+            }
+        }
+    }
+
+    fn take_calendar(&mut self, id: &str) -> Option<Box<dyn Calendarlike>> {
+        let index = self.calendars.iter().position(|c| c.id() == id)?;
+        Some(Box::new(self.calendars.remove(index)))
+    }
 }
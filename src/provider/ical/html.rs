@@ -0,0 +1,172 @@
+// Outlook puts the actual description of events into an HTML X-ALT-DESC and
+// leaves only a garbled version in DESCRIPTION
+
+/// Elements whose content is not text
+const INVISIBLE_ELEMENTS: [&str; 4] = ["head", "style", "script", "title"];
+
+/// Elements that are on lines of their own
+const BLOCK_ELEMENTS: [&str; 6] = ["div", "li", "tr", "ul", "ol", "table"];
+
+/// Elements that are separated from the surrounding text by a blank line
+const PARAGRAPH_ELEMENTS: [&str; 7] = ["p", "h1", "h2", "h3", "h4", "h5", "h6"];
+
+/// Whether `description` is unlikely to be readable, i.e. empty, markup or
+/// mostly punctuation
+pub(super) fn is_garbled(description: &str) -> bool {
+    let visible: Vec<char> = description.chars().filter(|c| !c.is_whitespace()).collect();
+    let alphanumeric = visible.iter().filter(|c| c.is_alphanumeric()).count();
+
+    visible.is_empty()
+        || description.contains("<html")
+        || description.contains("<p>")
+        || alphanumeric * 2 < visible.len()
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let number = entity.strip_prefix('#')?;
+            let code = match number.strip_prefix(&['x', 'X'][..]) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+/// Readable text of an HTML document: tags are dropped, block elements end
+/// lines, list items start with "- " and entities are decoded
+pub(super) fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut invisible: Option<&str> = None;
+    let mut rest = html;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            let end = match rest.find('>') {
+                Some(end) => end,
+                None => break,
+            };
+            let tag = rest[1..end].trim();
+            rest = &rest[end + 1..];
+
+            let closing = tag.starts_with('/');
+            let name = tag
+                .trim_start_matches('/')
+                .split(|c: char| c.is_whitespace() || c == '/')
+                .next()
+                .unwrap_or("")
+                .to_ascii_lowercase();
+
+            match invisible {
+                Some(element) if closing && name == element => invisible = None,
+                Some(_) => {}
+                None if !closing && !tag.ends_with('/') => {
+                    invisible = INVISIBLE_ELEMENTS.iter().copied().find(|e| *e == name);
+                }
+                None => {}
+            }
+            if invisible.is_some() {
+                continue;
+            }
+
+            if name == "br" {
+                text.push('\n');
+            } else if PARAGRAPH_ELEMENTS.contains(&name.as_str()) {
+                text.push_str("\n\n");
+            } else if BLOCK_ELEMENTS.contains(&name.as_str()) && !text.ends_with('\n') {
+                text.push('\n');
+            }
+            if name == "li" && !closing {
+                text.push_str("- ");
+            }
+            continue;
+        }
+
+        rest = &rest[c.len_utf8()..];
+        if invisible.is_some() {
+            continue;
+        }
+
+        if c == '&' {
+            if let Some(end) = rest.find(';').filter(|end| *end <= 8) {
+                if let Some(decoded) = decode_entity(&rest[..end]) {
+                    text.push(decoded);
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+        }
+
+        // Line breaks in the source are insignificant
+        text.push(if c.is_whitespace() { ' ' } else { c });
+    }
+
+    // Collapse the whitespace left over from the markup
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        // Items without text
+        if line == "-" {
+            continue;
+        }
+        if !line.is_empty() || lines.last().is_some_and(|last| !last.is_empty()) {
+            lines.push(line);
+        }
+    }
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entities() {
+        assert_eq!(
+            html_to_text("Fish &amp; chips&nbsp;&lt;3 &#8364;&#x20ac; &quot;ok&apos;"),
+            "Fish & chips <3 €€ \"ok'"
+        );
+        // Unknown or unterminated entities are kept
+        assert_eq!(html_to_text("&bogus; & &amp"), "&bogus; & &amp");
+    }
+
+    #[test]
+    fn line_breaks_and_paragraphs() {
+        assert_eq!(
+            html_to_text("<p>Hello,</p>\r\n<p>see you\r\nat<br>the <b>office</b>.</p>"),
+            "Hello,\n\nsee you at\nthe office."
+        );
+        assert_eq!(html_to_text("one<br/>two<BR />three"), "one\ntwo\nthree");
+    }
+
+    #[test]
+    fn nested_tags() {
+        let html = "<html><head><title>Invite</title><style>p { color: red; }</style></head>\
+            <body><div>Agenda:<ul><li>Budget <i>and <b>plans</b></i></li><li></li>\
+            <li><span>Questions</span></li></ul></div><script>alert('<p>')</script>\
+            <div><div>Bye</div></div></body></html>";
+        assert_eq!(
+            html_to_text(html),
+            "Agenda:\n- Budget and plans\n- Questions\nBye"
+        );
+    }
+
+    #[test]
+    fn garbled_descriptions() {
+        assert!(is_garbled(" \n"));
+        assert!(is_garbled("<html><body>Hi</body></html>"));
+        assert!(is_garbled("*~*~*~*~*~*~*~*~*~*"));
+        assert!(!is_garbled("Planning meeting, room 3"));
+    }
+}
@@ -0,0 +1,153 @@
+use ::ical::parser::ical::component::{IcalCalendar, IcalEvent, IcalTimeZone, IcalTodo};
+use ::ical::property::Property;
+use std::collections::BTreeMap;
+
+fn property_value<'a>(properties: &'a [Property], name: &str) -> Option<&'a str> {
+    properties
+        .iter()
+        .find(|p| p.name == name)
+        .and_then(|p| p.value.as_deref())
+}
+
+pub fn is_override(event: &IcalEvent) -> bool {
+    property_value(&event.properties, "RECURRENCE-ID").is_some()
+}
+
+fn referenced_tzids<'a>(properties: impl Iterator<Item = &'a Property>) -> Vec<&'a str> {
+    properties
+        .flat_map(|p| p.params.iter().flatten())
+        .filter(|(name, _)| name == "TZID")
+        .flat_map(|(_, values)| values.iter().map(String::as_str))
+        .collect()
+}
+
+fn timezone_id(timezone: &IcalTimeZone) -> Option<&str> {
+    property_value(&timezone.properties, "TZID")
+}
+
+/// A calendar with the properties of `calendar` and the timezones of it
+/// that `properties` refer to
+fn group_calendar<'a>(
+    calendar: &IcalCalendar,
+    properties: impl Iterator<Item = &'a Property>,
+) -> IcalCalendar {
+    let tzids = referenced_tzids(properties);
+    let mut group = IcalCalendar::new();
+    group.properties = calendar
+        .properties
+        .iter()
+        .filter(|p| p.name != "METHOD")
+        .cloned()
+        .collect();
+    group.timezones = calendar
+        .timezones
+        .iter()
+        .filter(|tz| timezone_id(tz).is_some_and(|id| tzids.contains(&id)))
+        .cloned()
+        .collect();
+    group
+}
+
+/// Splits `calendar` into one calendar per UID. Recurrence overrides stay
+/// grouped with their master event, tasks get a calendar of their own and
+/// only the timezones referenced by a group are copied into it.
+pub fn split_by_uid(mut calendar: IcalCalendar) -> Vec<(String, IcalCalendar)> {
+    let mut groups = BTreeMap::<String, Vec<IcalEvent>>::new();
+    for event in std::mem::take(&mut calendar.events) {
+        match property_value(&event.properties, "UID") {
+            Some(uid) => groups.entry(uid.to_owned()).or_default().push(event),
+            None => log::warn!("Skipping event without UID"),
+        }
+    }
+    let mut todos = BTreeMap::<String, IcalTodo>::new();
+    for todo in std::mem::take(&mut calendar.todos) {
+        match property_value(&todo.properties, "UID") {
+            Some(uid) => {
+                todos.insert(uid.to_owned(), todo);
+            }
+            None => log::warn!("Skipping task without UID"),
+        }
+    }
+
+    let events = groups.into_iter().map(|(uid, mut events)| {
+        // Keep the master event first
+        events.sort_by_key(is_override);
+
+        let mut group = group_calendar(
+            &calendar,
+            events.iter().flat_map(|event| event.properties.iter()),
+        );
+        group.events = events;
+        (uid, group)
+    });
+    let todos = todos.into_iter().map(|(uid, todo)| {
+        let mut group = group_calendar(&calendar, todo.properties.iter());
+        group.todos = vec![todo];
+        (uid, group)
+    });
+    events.chain(todos).collect()
+}
+
+/// A file name for `uid` that is safe to use on all platforms
+pub fn file_stem_for_uid(uid: &str) -> String {
+    uid.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "-_.@".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// The UIDs of the events and tasks in `ics`, each once. Calendars that can
+/// not be parsed are skipped, importing them reports the error.
+pub fn uids(ics: &str) -> Vec<String> {
+    let mut uids: Vec<String> = ::ical::IcalParser::new(ics.as_bytes())
+        .filter_map(|calendar| calendar.ok())
+        .flat_map(|calendar| {
+            let events = calendar.events.into_iter().map(|event| event.properties);
+            let todos = calendar.todos.into_iter().map(|todo| todo.properties);
+            events.chain(todos).collect::<Vec<_>>()
+        })
+        .filter_map(|properties| property_value(&properties, "UID").map(str::to_owned))
+        .collect();
+    uids.sort_unstable();
+    uids.dedup();
+    uids
+}
+
+/// Combines several iCalendar documents into one, keeping every timezone
+/// only once. Documents that can not be parsed are skipped.
+pub fn merge_calendars(documents: &[String]) -> IcalCalendar {
+    let mut merged = IcalCalendar::new();
+
+    for document in documents {
+        for calendar in ::ical::IcalParser::new(document.as_bytes()) {
+            let calendar = match calendar {
+                Ok(calendar) => calendar,
+                Err(e) => {
+                    log::warn!("Skipping calendar that could not be read: {}", e);
+                    continue;
+                }
+            };
+
+            if merged.properties.is_empty() {
+                merged.properties = calendar.properties;
+            }
+            for timezone in calendar.timezones {
+                if !merged
+                    .timezones
+                    .iter()
+                    .any(|tz| timezone_id(tz) == timezone_id(&timezone))
+                {
+                    merged.timezones.push(timezone);
+                }
+            }
+            merged.events.extend(calendar.events);
+        }
+    }
+
+    merged
+}
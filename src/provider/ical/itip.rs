@@ -0,0 +1,253 @@
+use ::ical::parser::ical::component::{IcalCalendar, IcalEvent};
+use ::ical::parser::ical::IcalParser;
+use ::ical::property::Property;
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Error, ErrorKind, Result};
+
+/// The answer of an attendee to an invitation (PARTSTAT, RFC 5545 3.2.12)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PartStat {
+    Accepted,
+    Declined,
+    Tentative,
+}
+
+impl PartStat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PartStat::Accepted => "ACCEPTED",
+            PartStat::Declined => "DECLINED",
+            PartStat::Tentative => "TENTATIVE",
+        }
+    }
+}
+
+impl FromStr for PartStat {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input.to_ascii_lowercase().as_str() {
+            "accept" | "accepted" => Ok(PartStat::Accepted),
+            "decline" | "declined" => Ok(PartStat::Declined),
+            "tentative" | "maybe" => Ok(PartStat::Tentative),
+            _ => Err(Error::new(
+                ErrorKind::Invalid,
+                &format!(
+                    "Unknown answer '{}', expected accepted, declined or tentative",
+                    input
+                ),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PartStat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.as_str().to_ascii_lowercase())
+    }
+}
+
+/// The organizer or an attendee of an event
+pub struct Participant {
+    /// Email address without `mailto:`
+    pub address: String,
+    pub name: Option<String>,
+    pub partstat: Option<String>,
+}
+
+fn param<'a>(property: &'a Property, name: &str) -> Option<&'a str> {
+    property
+        .params
+        .iter()
+        .flatten()
+        .find(|(param, _)| param.eq_ignore_ascii_case(name))
+        .and_then(|(_, values)| values.first())
+        .map(String::as_str)
+}
+
+fn mail_address(property: &Property) -> String {
+    let value = property.value.as_deref().unwrap_or_default();
+    value
+        .get(..7)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("mailto:"))
+        .map_or(value, |_| &value[7..])
+        .to_owned()
+}
+
+fn participant(property: &Property) -> Participant {
+    Participant {
+        address: mail_address(property),
+        name: param(property, "CN").map(|name| name.trim_matches('"').to_owned()),
+        partstat: param(property, "PARTSTAT").map(str::to_owned),
+    }
+}
+
+/// An iTIP message (RFC 5546), e.g. an invitation as sent by mail
+pub struct Message {
+    ical: IcalCalendar,
+}
+
+impl Message {
+    pub fn parse(ics: &str) -> Result<Self> {
+        let ical = IcalParser::new(ics.as_bytes())
+            .next()
+            .ok_or_else(|| Error::new(ErrorKind::CalendarParse, "No calendar found"))?
+            .map_err(|e| {
+                Error::new(
+                    ErrorKind::CalendarParse,
+                    &format!("Could not read calendar: {}", e),
+                )
+            })?;
+        if ical.events.is_empty() {
+            return Err(Error::new(ErrorKind::CalendarParse, "No event entry"));
+        }
+        Ok(Message { ical })
+    }
+
+    fn calendar_property(&self, name: &str) -> Option<&str> {
+        self.ical
+            .properties
+            .iter()
+            .find(|p| p.name == name)
+            .and_then(|p| p.value.as_deref())
+    }
+
+    /// The event the message is about. Overrides of single instances come
+    /// after it if there are any.
+    fn event(&self) -> &IcalEvent {
+        self.ical
+            .events
+            .iter()
+            .find(|event| !super::import::is_override(event))
+            .unwrap_or(&self.ical.events[0])
+    }
+
+    fn event_property(&self, name: &str) -> Option<&Property> {
+        self.event().properties.iter().find(|p| p.name == name)
+    }
+
+    pub fn method(&self) -> Option<&str> {
+        self.calendar_property("METHOD")
+    }
+
+    pub fn uid(&self) -> &str {
+        self.event_property("UID")
+            .and_then(|p| p.value.as_deref())
+            .unwrap_or_default()
+    }
+
+    pub fn summary(&self) -> String {
+        self.event_property("SUMMARY")
+            .and_then(|p| p.value.as_deref())
+            .map(super::xcal::unescape_text)
+            .unwrap_or_default()
+    }
+
+    pub fn sequence(&self) -> u32 {
+        self.event_property("SEQUENCE")
+            .and_then(|p| p.value.as_deref())
+            .and_then(|seq| seq.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    pub fn organizer(&self) -> Option<Participant> {
+        self.event_property("ORGANIZER").map(participant)
+    }
+
+    pub fn attendees(&self) -> Vec<Participant> {
+        self.event()
+            .properties
+            .iter()
+            .filter(|p| p.name == "ATTENDEE")
+            .map(participant)
+            .collect()
+    }
+
+    /// The first of `addresses` that is invited, compared ignoring case
+    pub fn find_attendee<'a>(&self, addresses: &'a [String]) -> Option<&'a str> {
+        let attendees = self.attendees();
+        addresses
+            .iter()
+            .find(|address| {
+                attendees
+                    .iter()
+                    .any(|attendee| attendee.address.eq_ignore_ascii_case(address))
+            })
+            .map(String::as_str)
+    }
+
+    /// Sets the PARTSTAT of the attendee `address` in all events of
+    /// `calendar`. RSVP is dropped as the answer is given.
+    fn set_partstat(calendar: &mut IcalCalendar, address: &str, partstat: PartStat) {
+        let attendees = calendar
+            .events
+            .iter_mut()
+            .flat_map(|event| event.properties.iter_mut())
+            .filter(|p| p.name == "ATTENDEE" && mail_address(p).eq_ignore_ascii_case(address));
+        for attendee in attendees {
+            let params = attendee.params.get_or_insert_with(Vec::new);
+            params.retain(|(name, _)| {
+                !name.eq_ignore_ascii_case("PARTSTAT") && !name.eq_ignore_ascii_case("RSVP")
+            });
+            params.push(("PARTSTAT".to_owned(), vec![partstat.as_str().to_owned()]));
+        }
+    }
+
+    /// The event as it is stored in a calendar once `address` answered with
+    /// `partstat`. The METHOD only belongs to the message.
+    pub fn to_event(&self, address: &str, partstat: PartStat) -> String {
+        let mut calendar = self.ical.clone();
+        calendar.properties.retain(|p| p.name != "METHOD");
+        Self::set_partstat(&mut calendar, address, partstat);
+        super::writer::write_calendar(&calendar)
+    }
+
+    /// The REPLY telling the organizer that `address` answered with
+    /// `partstat`
+    pub fn reply(&self, address: &str, partstat: PartStat) -> String {
+        let mut event = IcalEvent::new();
+        for name in ["UID", "RECURRENCE-ID", "SEQUENCE", "ORGANIZER", "SUMMARY"] {
+            if let Some(property) = self.event_property(name) {
+                event.properties.push(property.clone());
+            }
+        }
+        event.properties.push(Property {
+            name: "DTSTAMP".to_owned(),
+            params: None,
+            value: Some(super::generate_timestamp()),
+        });
+        // Only the answering attendee is part of a reply
+        event.properties.extend(
+            self.event()
+                .properties
+                .iter()
+                .filter(|p| p.name == "ATTENDEE" && mail_address(p).eq_ignore_ascii_case(address))
+                .take(1)
+                .cloned(),
+        );
+
+        let mut calendar = IcalCalendar::new();
+        calendar.properties = vec![
+            Property {
+                name: "PRODID".to_owned(),
+                params: None,
+                value: Some(super::JACKAL_PRODID.to_owned()),
+            },
+            Property {
+                name: "VERSION".to_owned(),
+                params: None,
+                value: Some(super::JACKAL_CALENDAR_VERSION.to_owned()),
+            },
+            Property {
+                name: "METHOD".to_owned(),
+                params: None,
+                value: Some("REPLY".to_owned()),
+            },
+        ];
+        calendar.events.push(event);
+        Self::set_partstat(&mut calendar, address, partstat);
+        super::writer::write_calendar(&calendar)
+    }
+}
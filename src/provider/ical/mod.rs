@@ -1,12 +1,23 @@
 pub mod calendar;
+mod html;
+pub mod import;
+pub mod itip;
+mod series;
+pub mod todo;
+pub mod writer;
+pub mod xcal;
 pub use calendar::{Calendar, Collection, Event};
 use calendar::{IcalDateTime, IcalDuration};
+pub use todo::Todo;
 
 use super::{Error, ErrorKind, Occurrence, Result, TimeSpan};
 
-use chrono::{DateTime, Local, Month, NaiveDate, Utc};
+use chrono::{DateTime, Duration, Month, NaiveDate, NaiveTime, Offset, TimeZone, Utc};
 use chrono_tz::Tz;
-use ical::parser::{ical::component::IcalEvent, Component};
+use ical::parser::{
+    ical::component::{IcalCalendar, IcalEvent, IcalTimeZone, IcalTimeZoneTransition},
+    Component,
+};
 use ical::property::Property;
 use std::path::{Path, PathBuf};
 
@@ -20,6 +31,17 @@ const ISO8601_2004_LOCAL_FORMAT_DATE: &'static str = "%Y%m%d";
 
 const ICAL_FILE_EXT: &'static str = ".ics";
 
+/// Non-standard property marking pinned events
+const PINNED_PROPERTY: &str = "X-JACKAL-PINNED";
+
+// vdirsyncer compatible calendar metadata files
+const METADATA_DISPLAYNAME_FILE: &str = "displayname";
+const METADATA_COLOR_FILE: &str = "color";
+
+/// Start of the files present in a calendar directory while bulk operations
+/// change it, one per operation
+const SUSPEND_MARKER_PREFIX: &str = ".jackal-suspend";
+
 pub fn days_of_month(month: &Month, year: i32) -> u64 {
     if month.number_from_month() == 12 {
         NaiveDate::from_ymd(year + 1, 1, 1)
@@ -34,11 +56,138 @@ pub fn days_of_month(month: &Month, year: i32) -> u64 {
     .num_days() as u64
 }
 
+fn datetime_property(name: &str, dt: &DateTime<Tz>) -> Property {
+    if dt.timezone() == chrono_tz::UTC {
+        Property {
+            name: name.to_owned(),
+            params: None,
+            value: Some(format!("{}Z", dt.format(ISO8601_2004_LOCAL_FORMAT))),
+        }
+    } else {
+        Property {
+            name: name.to_owned(),
+            params: Some(vec![(
+                "TZID".to_owned(),
+                vec![dt.timezone().name().to_owned()],
+            )]),
+            value: Some(dt.format(ISO8601_2004_LOCAL_FORMAT).to_string()),
+        }
+    }
+}
+
+/// Formats an offset from UTC as in TZOFFSETFROM, e.g. `+0130`
+fn utc_offset(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    let (hours, minutes) = (seconds / 3600, seconds / 60 % 60);
+    match seconds % 60 {
+        0 => format!("{}{:02}{:02}", sign, hours, minutes),
+        seconds => format!("{}{:02}{:02}{:02}", sign, hours, minutes, seconds),
+    }
+}
+
+/// A VTIMEZONE defining `tz` by its transitions from a year before until
+/// ten years after `around`, later times keep the last offset. chrono-tz
+/// only knows the transitions, not the rules they follow.
+pub fn vtimezone(tz: Tz, around: DateTime<Utc>) -> IcalTimeZone {
+    let offset_at = |time: DateTime<Utc>| tz.offset_from_utc_datetime(&time.naive_utc());
+    let seconds_at = |time: DateTime<Utc>| offset_at(time).fix().local_minus_utc();
+    let property = |name: &str, value: String| Property {
+        name: name.to_owned(),
+        params: None,
+        value: Some(value),
+    };
+    let transition = |at: DateTime<Utc>, from: i32| {
+        let to = offset_at(at);
+        let mut transition = IcalTimeZoneTransition::new();
+        transition.properties = vec![
+            // In the local time before the transition
+            property(
+                "DTSTART",
+                (at.naive_utc() + Duration::seconds(from.into()))
+                    .format(ISO8601_2004_LOCAL_FORMAT)
+                    .to_string(),
+            ),
+            property("TZOFFSETFROM", utc_offset(from)),
+            property("TZOFFSETTO", utc_offset(to.fix().local_minus_utc())),
+            property("TZNAME", to.to_string()),
+        ];
+        transition
+    };
+
+    let mut timezone = IcalTimeZone::new();
+    timezone.add_property(property("TZID", tz.name().to_owned()));
+    let midnight = Utc.from_utc_datetime(&around.date_naive().and_time(NaiveTime::MIN));
+    let mut day = midnight - Duration::days(366);
+    while day < midnight + Duration::days(366 * 10) {
+        let next = day + Duration::days(1);
+        let before = seconds_at(day);
+        if before != seconds_at(next) {
+            // The first minute of the day with the new offset
+            let (mut same, mut changed) = (0, 24 * 60);
+            while changed - same > 1 {
+                let middle = (same + changed) / 2;
+                if seconds_at(day + Duration::minutes(middle)) == before {
+                    same = middle;
+                } else {
+                    changed = middle;
+                }
+            }
+            timezone
+                .transitions
+                .push(transition(day + Duration::minutes(changed), before));
+        }
+        day = next;
+    }
+    if timezone.transitions.is_empty() {
+        let epoch = Utc.timestamp_opt(0, 0).unwrap();
+        timezone
+            .transitions
+            .push(transition(epoch, seconds_at(epoch)));
+    }
+    timezone
+}
+
 fn generate_timestamp() -> String {
     let tstamp = Utc::now();
     format!("{}Z", tstamp.format(ISO8601_2004_LOCAL_FORMAT))
 }
 
+/// A new all-day event from `first` to `last` as iCalendar data
+pub fn allday_event_ics(summary: &str, first: NaiveDate, last: NaiveDate) -> String {
+    let date_property = |name: &str, date: NaiveDate| Property {
+        name: name.to_owned(),
+        params: Some(vec![("VALUE".to_owned(), vec!["DATE".to_owned()])]),
+        value: Some(date.format(ISO8601_2004_LOCAL_FORMAT_DATE).to_string()),
+    };
+
+    let mut event = IcalEvent::new();
+    event.properties = vec![
+        Property {
+            name: "UID".to_owned(),
+            params: None,
+            value: Some(uuid::Uuid::new_v4().hyphenated().to_string()),
+        },
+        Property {
+            name: "DTSTAMP".to_owned(),
+            params: None,
+            value: Some(generate_timestamp()),
+        },
+        date_property("DTSTART", first),
+        // DTEND of all-day events is exclusive
+        date_property("DTEND", last.succ_opt().unwrap_or(last)),
+        Property {
+            name: "SUMMARY".to_owned(),
+            params: None,
+            value: Some(xcal::escape_text(summary)),
+        },
+    ];
+
+    let mut ical = IcalCalendar::new();
+    ical.events.push(event);
+    calendar::combined_calendar(&[ical])
+}
+
 pub struct EventBuilder {
     path: PathBuf,
     start: DateTime<Tz>,
@@ -113,27 +262,44 @@ impl EventBuilder {
         self
     }
 
-    pub fn finish(self) -> Result<Event> {
-        let mut event = if let Some(dtspec) = self.end {
-            Event::new_with_ical_properties(
-                &self.path,
-                Occurrence::Onetime(TimeSpan::TimePoints(self.start, dtspec)),
-                self.ical.properties,
-            )
+    pub fn set_rrule(&mut self, rrule: String) {
+        self.ical.add_property(Property {
+            name: "RRULE".to_owned(),
+            params: None,
+            value: Some(rrule),
+        });
+    }
+
+    pub fn with_rrule(mut self, rrule: String) -> Self {
+        self.set_rrule(rrule);
+        self
+    }
+
+    pub fn finish(mut self) -> Result<Event> {
+        self.ical
+            .add_property(datetime_property("DTSTART", &self.start));
+        if let Some(end) = &self.end {
+            self.ical.add_property(datetime_property("DTEND", end));
+        }
+
+        // Times given with a TZID need the definition of the time zone
+        let mut zones = vec![self.start.timezone()];
+        zones.extend(self.end.map(|end| end.timezone()));
+        zones.retain(|tz| *tz != chrono_tz::UTC);
+        zones.dedup();
+
+        let occurrence = if let Some(dtspec) = self.end {
+            Occurrence::Onetime(TimeSpan::TimePoints(self.start, dtspec))
         } else if let Some(durspec) = self.duration {
-            Event::new_with_ical_properties(
-                &self.path,
-                Occurrence::Onetime(TimeSpan::Duration(self.start, durspec.into())),
-                self.ical.properties,
-            )
+            Occurrence::Onetime(TimeSpan::Duration(self.start, durspec.into()))
         } else {
-            Event::new_with_ical_properties(
-                &self.path,
-                Occurrence::Instant(self.start),
-                self.ical.properties,
-            )
+            Occurrence::Instant(self.start)
         };
-
-        event
+        let mut event =
+            Event::new_with_ical_properties(&self.path, occurrence, self.ical.properties)?;
+        for tz in zones {
+            event.add_timezone(vtimezone(tz, self.start.with_timezone(&Utc)));
+        }
+        Ok(event)
     }
 }
@@ -0,0 +1,98 @@
+use ::ical::parser::ical::component::{IcalCalendar, IcalEvent};
+use chrono::Utc;
+use std::convert::TryFrom;
+
+use super::import::is_override;
+use super::{Error, ErrorKind, IcalDateTime, Result};
+
+/// Properties that overrides take from their master event if they do not
+/// set them themselves. Times, recurrence rules and bookkeeping like
+/// SEQUENCE belong to each component alone.
+pub const INHERITED_PROPERTIES: &[&str] = &[
+    "SUMMARY",
+    "DESCRIPTION",
+    "X-ALT-DESC",
+    "LOCATION",
+    "CATEGORIES",
+    "ATTENDEE",
+    super::PINNED_PROPERTY,
+];
+
+/// The event components of a file: a recurring event (the master) and the
+/// overrides of single instances of it, which share its UID and name the
+/// instance they replace by RECURRENCE-ID. Files of single events are
+/// series without overrides.
+pub struct Series {
+    master: Option<usize>,
+    overrides: Vec<usize>,
+}
+
+fn uid(event: &IcalEvent) -> Option<&str> {
+    event
+        .properties
+        .iter()
+        .find(|p| p.name == "UID")
+        .and_then(|p| p.value.as_deref())
+}
+
+/// The time of the property `name` of `event` for comparing instances
+pub fn instant(event: &IcalEvent, name: &str) -> Result<Option<chrono::DateTime<Utc>>> {
+    event
+        .properties
+        .iter()
+        .find(|p| p.name == name)
+        .map(|p| IcalDateTime::try_from(p).map(|dt| dt.as_datetime(&Utc)))
+        .transpose()
+}
+
+impl Series {
+    pub fn of(ical: &IcalCalendar) -> Result<Self> {
+        let first = ical
+            .events
+            .first()
+            .ok_or_else(|| Error::new(ErrorKind::CalendarParse, "No event entry"))?;
+        if ical.events.iter().any(|event| uid(event) != uid(first)) {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "Events with different UIDs in a file",
+            ));
+        }
+
+        let (overrides, masters): (Vec<usize>, Vec<usize>) =
+            (0..ical.events.len()).partition(|idx| is_override(&ical.events[*idx]));
+        if masters.len() > 1 {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "More than one event entry without RECURRENCE-ID in a file",
+            ));
+        }
+
+        Ok(Series {
+            master: masters.first().copied(),
+            overrides,
+        })
+    }
+
+    /// The component overrides inherit from, `None` for overrides of
+    /// instances of events that were not shared with us
+    pub fn master(&self) -> Option<usize> {
+        self.master
+    }
+
+    /// The components to show as events: all overrides and the master
+    /// unless an override replaces the instance at its start
+    pub fn instances(&self, ical: &IcalCalendar) -> Result<Vec<usize>> {
+        let mut instances = self.overrides.clone();
+        if let Some(master) = self.master {
+            let start = instant(&ical.events[master], "DTSTART")?;
+            let mut replaced = false;
+            for idx in &self.overrides {
+                replaced |= instant(&ical.events[*idx], "RECURRENCE-ID")? == start;
+            }
+            if !replaced {
+                instances.insert(0, master);
+            }
+        }
+        Ok(instances)
+    }
+}
@@ -0,0 +1,149 @@
+use chrono::DateTime;
+use chrono_tz::Tz;
+use ical::parser::ical::component::IcalCalendar;
+use ical::property::Property;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{Error, ErrorKind, IcalDateTime, Result};
+use crate::provider::{TodoStatus, Todolike};
+
+/// A task (VTODO) stored in a file of its own
+#[derive(Clone)]
+pub struct Todo {
+    path: PathBuf,
+    ical: IcalCalendar,
+    due: Option<IcalDateTime>,
+    tz: Tz,
+}
+
+impl Todo {
+    pub fn from_ical(path: &Path, ical: IcalCalendar) -> Result<Self> {
+        Self::parse_ical(path, ical).map_err(|err| err.in_file(path))
+    }
+
+    fn parse_ical(path: &Path, ical: IcalCalendar) -> Result<Self> {
+        if ical.todos.len() != 1 || !ical.events.is_empty() {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "Tasks have to be in a file of their own",
+            ));
+        }
+
+        let due = ical.todos[0]
+            .properties
+            .iter()
+            .find(|p| p.name == "DUE")
+            .map(IcalDateTime::try_from)
+            .transpose()?;
+        let tz = match &due {
+            Some(IcalDateTime::Local(dt)) => dt.timezone(),
+            _ => Tz::UTC,
+        };
+
+        Ok(Todo {
+            path: path.to_owned(),
+            ical,
+            due,
+            tz,
+        })
+    }
+
+    fn properties(&self) -> &[Property] {
+        &self.ical.todos[0].properties
+    }
+
+    fn property_value(&self, name: &str) -> Option<&str> {
+        self.properties()
+            .iter()
+            .find(|p| p.name == name)
+            .and_then(|p| p.value.as_deref())
+    }
+
+    fn set_property(&mut self, name: &str, value: Option<String>) {
+        let properties = &mut self.ical.todos[0].properties;
+        properties.retain(|p| p.name != name);
+        if let Some(value) = value {
+            properties.push(Property {
+                name: name.to_owned(),
+                params: None,
+                value: Some(value),
+            });
+        }
+    }
+
+    /// A copy of the task marked as completed or as needing action again
+    pub fn with_done(&self, done: bool) -> Todo {
+        let mut changed = self.clone();
+        if done {
+            changed.set_property("STATUS", Some("COMPLETED".to_owned()));
+            changed.set_property("PERCENT-COMPLETE", Some("100".to_owned()));
+            changed.set_property("COMPLETED", Some(super::generate_timestamp()));
+        } else {
+            changed.set_property("STATUS", Some("NEEDS-ACTION".to_owned()));
+            changed.set_property("PERCENT-COMPLETE", None);
+            changed.set_property("COMPLETED", None);
+        }
+        changed.set_property("LAST-MODIFIED", Some(super::generate_timestamp()));
+        changed
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::write(&self.path, super::writer::write_calendar(&self.ical))?;
+        Ok(())
+    }
+}
+
+impl Todolike for Todo {
+    fn uid(&self) -> &str {
+        self.property_value("UID").unwrap_or("")
+    }
+
+    fn summary(&self) -> &str {
+        self.property_value("SUMMARY").unwrap_or("")
+    }
+
+    fn due(&self) -> Option<DateTime<Tz>> {
+        self.due.as_ref().map(|due| due.as_datetime(&self.tz))
+    }
+
+    fn has_due_time(&self) -> bool {
+        self.due.as_ref().is_some_and(|due| !due.is_date())
+    }
+
+    fn status(&self) -> TodoStatus {
+        match self.property_value("STATUS") {
+            Some("COMPLETED") => TodoStatus::Completed,
+            Some("IN-PROCESS") => TodoStatus::InProcess,
+            Some("CANCELLED") => TodoStatus::Cancelled,
+            // Some clients only set the completion date
+            _ if self.property_value("COMPLETED").is_some() => TodoStatus::Completed,
+            _ => TodoStatus::NeedsAction,
+        }
+    }
+
+    fn percent_complete(&self) -> u8 {
+        self.property_value("PERCENT-COMPLETE")
+            .and_then(|percent| percent.trim().parse::<u8>().ok())
+            .map(|percent| percent.min(100))
+            .unwrap_or(match self.status() {
+                TodoStatus::Completed => 100,
+                _ => 0,
+            })
+    }
+
+    fn priority(&self) -> Option<u8> {
+        self.property_value("PRIORITY")
+            .and_then(|priority| priority.trim().parse::<u8>().ok())
+            .filter(|priority| (1..=9).contains(priority))
+    }
+
+    fn raw_ical(&self) -> String {
+        super::writer::write_calendar(&self.ical)
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
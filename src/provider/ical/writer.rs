@@ -0,0 +1,212 @@
+use ::ical::parser::ical::component::{
+    IcalAlarm, IcalCalendar, IcalEvent, IcalTimeZone, IcalTimeZoneTransition, IcalTodo,
+};
+use ::ical::property::Property;
+use std::fmt::Write;
+
+// RFC 5545 3.1: lines should not be longer than 75 octets
+const MAX_LINE_OCTETS: usize = 75;
+
+fn write_folded(out: &mut String, line: &str) {
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            // Readers trim the end of each line, so whitespace in front of
+            // the fold moves to the continuation line
+            let kept = out.trim_end_matches([' ', '\t']).len();
+            let moved = if out.len() - kept + 1 + c.len_utf8() <= MAX_LINE_OCTETS {
+                out.split_off(kept)
+            } else {
+                String::new()
+            };
+            out.push_str("\r\n ");
+            out.push_str(&moved);
+            // The leading space of a continuation line counts as well
+            octets = 1 + moved.len();
+        }
+        out.push(c);
+        octets += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn needs_quoting(param_value: &str) -> bool {
+    param_value.contains([':', ';', ','])
+}
+
+fn write_property(out: &mut String, property: &Property) {
+    let mut line = property.name.clone();
+
+    for (name, values) in property.params.iter().flatten() {
+        let values: Vec<String> = values
+            .iter()
+            .map(|v| {
+                if needs_quoting(v) {
+                    format!("\"{}\"", v)
+                } else {
+                    v.clone()
+                }
+            })
+            .collect();
+        write!(line, ";{}={}", name, values.join(",")).unwrap();
+    }
+
+    write!(line, ":{}", property.value.as_deref().unwrap_or_default()).unwrap();
+    write_folded(out, &line);
+}
+
+fn write_component<F: FnOnce(&mut String)>(
+    out: &mut String,
+    name: &str,
+    properties: &[Property],
+    sub_components: F,
+) {
+    write_folded(out, &format!("BEGIN:{}", name));
+    for property in properties {
+        write_property(out, property);
+    }
+    sub_components(out);
+    write_folded(out, &format!("END:{}", name));
+}
+
+fn write_alarm(out: &mut String, alarm: &IcalAlarm) {
+    write_component(out, "VALARM", &alarm.properties, |_| {});
+}
+
+fn write_event(out: &mut String, event: &IcalEvent) {
+    write_component(out, "VEVENT", &event.properties, |out| {
+        event
+            .alarms
+            .iter()
+            .for_each(|alarm| write_alarm(out, alarm))
+    });
+}
+
+fn write_todo(out: &mut String, todo: &IcalTodo) {
+    write_component(out, "VTODO", &todo.properties, |out| {
+        todo.alarms.iter().for_each(|alarm| write_alarm(out, alarm))
+    });
+}
+
+pub(super) fn transition_kind(transition: &IcalTimeZoneTransition) -> &'static str {
+    // The parser does not keep track of the transition kind, so we derive
+    // it from the offsets: moving the clock forward means daylight saving
+    let offset = |name: &str| {
+        transition
+            .properties
+            .iter()
+            .find(|p| p.name == name)
+            .and_then(|p| p.value.as_deref())
+            .and_then(|v| v.replace(['+', ':'], "").parse::<i32>().ok())
+    };
+
+    match (offset("TZOFFSETFROM"), offset("TZOFFSETTO")) {
+        (Some(from), Some(to)) if to > from => "DAYLIGHT",
+        _ => "STANDARD",
+    }
+}
+
+fn write_timezone(out: &mut String, timezone: &IcalTimeZone) {
+    write_component(out, "VTIMEZONE", &timezone.properties, |out| {
+        for transition in &timezone.transitions {
+            write_component(
+                out,
+                transition_kind(transition),
+                &transition.properties,
+                |_| {},
+            );
+        }
+    });
+}
+
+/// Serializes `calendar` into its iCalendar text representation
+pub fn write_calendar(calendar: &IcalCalendar) -> String {
+    let mut out = String::new();
+
+    write_component(&mut out, "VCALENDAR", &calendar.properties, |out| {
+        calendar
+            .timezones
+            .iter()
+            .for_each(|tz| write_timezone(out, tz));
+        calendar
+            .events
+            .iter()
+            .for_each(|event| write_event(out, event));
+        calendar.todos.iter().for_each(|todo| write_todo(out, todo));
+    });
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::ical::IcalParser;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//jackal//test//EN\r
+BEGIN:VTIMEZONE\r
+TZID:Europe/Berlin\r
+BEGIN:DAYLIGHT\r
+TZOFFSETFROM:+0100\r
+TZOFFSETTO:+0200\r
+DTSTART:19700329T020000\r
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\r
+END:DAYLIGHT\r
+BEGIN:STANDARD\r
+TZOFFSETFROM:+0200\r
+TZOFFSETTO:+0100\r
+DTSTART:19701025T030000\r
+RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r
+END:STANDARD\r
+END:VTIMEZONE\r
+BEGIN:VEVENT\r
+UID:event-1@jackal\r
+DTSTART;TZID=Europe/Berlin:20261015T093000\r
+DTEND;TZID=Europe/Berlin:20261015T103000\r
+SUMMARY:Planning\\, part 2\r
+ATTENDEE;CN=\"Doe, Jane\";ROLE=REQ-PARTICIPANT:mailto:jane@example.com\r
+BEGIN:VALARM\r
+ACTION:DISPLAY\r
+TRIGGER:-PT15M\r
+DESCRIPTION:Planning\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VTODO\r
+UID:todo-1@jackal\r
+SUMMARY:Write minutes\r
+DUE;VALUE=DATE:20261016\r
+END:VTODO\r
+END:VCALENDAR\r
+";
+
+    fn parse(ics: &str) -> IcalCalendar {
+        IcalParser::new(ics.as_bytes()).next().unwrap().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(write_calendar(&parse(CALENDAR)), CALENDAR);
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        let description = "Äpfel und Birnen ".repeat(10).trim_end().to_owned();
+        let mut event = IcalEvent::new();
+        event.properties.push(Property {
+            name: "DESCRIPTION".to_owned(),
+            params: None,
+            value: Some(description.clone()),
+        });
+        let mut calendar = IcalCalendar::new();
+        calendar.events.push(event);
+
+        let written = write_calendar(&calendar);
+        assert!(written
+            .split("\r\n")
+            .all(|line| line.len() <= MAX_LINE_OCTETS));
+        let value = parse(&written).events[0].properties[0].value.clone();
+        assert_eq!(value, Some(description));
+    }
+}
@@ -0,0 +1,896 @@
+//! Conversion between iCalendar and its XML representation xCal (RFC 6321)
+
+use ::ical::parser::ical::component::{
+    IcalAlarm, IcalCalendar, IcalEvent, IcalTimeZone, IcalTimeZoneTransition, IcalTodo,
+};
+use ::ical::parser::ical::IcalParser;
+use ::ical::property::Property;
+use std::fmt::Write;
+
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_until, take_while1},
+    character::complete::{char, multispace0, multispace1},
+    combinator::{map, value, verify},
+    multi::many0,
+    sequence::{delimited, pair, preceded, tuple},
+    IResult,
+};
+
+use super::writer::{transition_kind, write_calendar};
+use crate::provider::{Error, ErrorKind, Result};
+
+const XCAL_NAMESPACE: &str = "urn:ietf:params:xml:ns:icalendar-2.0";
+
+#[derive(Clone, Copy, PartialEq)]
+enum ValueType {
+    Binary,
+    Boolean,
+    CalAddress,
+    Date,
+    DateTime,
+    Duration,
+    Float,
+    Integer,
+    Period,
+    Recur,
+    Text,
+    Time,
+    Uri,
+    UtcOffset,
+    Unknown,
+}
+
+const VALUE_TYPES: &[(&str, ValueType)] = &[
+    ("binary", ValueType::Binary),
+    ("boolean", ValueType::Boolean),
+    ("cal-address", ValueType::CalAddress),
+    ("date", ValueType::Date),
+    ("date-time", ValueType::DateTime),
+    ("duration", ValueType::Duration),
+    ("float", ValueType::Float),
+    ("integer", ValueType::Integer),
+    ("period", ValueType::Period),
+    ("recur", ValueType::Recur),
+    ("text", ValueType::Text),
+    ("time", ValueType::Time),
+    ("uri", ValueType::Uri),
+    ("utc-offset", ValueType::UtcOffset),
+    ("unknown", ValueType::Unknown),
+];
+
+impl ValueType {
+    fn from_name(name: &str) -> Option<Self> {
+        VALUE_TYPES
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, t)| *t)
+    }
+
+    fn name(self) -> &'static str {
+        VALUE_TYPES.iter().find(|(_, t)| *t == self).unwrap().0
+    }
+
+    /// The value type of property `name` if no VALUE parameter is given
+    fn default_for(name: &str) -> Self {
+        match name {
+            "DTSTART" | "DTEND" | "DUE" | "RECURRENCE-ID" | "EXDATE" | "RDATE" | "DTSTAMP"
+            | "CREATED" | "LAST-MODIFIED" | "COMPLETED" => ValueType::DateTime,
+            "DURATION" | "TRIGGER" => ValueType::Duration,
+            "SEQUENCE" | "PRIORITY" | "PERCENT-COMPLETE" | "REPEAT" => ValueType::Integer,
+            "RRULE" | "EXRULE" => ValueType::Recur,
+            "TZOFFSETFROM" | "TZOFFSETTO" => ValueType::UtcOffset,
+            "ATTENDEE" | "ORGANIZER" => ValueType::CalAddress,
+            "URL" | "TZURL" | "ATTACH" | "SOURCE" => ValueType::Uri,
+            "FREEBUSY" => ValueType::Period,
+            name if name.starts_with("X-") => ValueType::Unknown,
+            _ => ValueType::Text,
+        }
+    }
+
+    fn is_multi_valued(self, property: &str) -> bool {
+        match self {
+            ValueType::Date | ValueType::DateTime | ValueType::Period | ValueType::Time => true,
+            ValueType::Text => matches!(property, "CATEGORIES" | "RESOURCES"),
+            _ => false,
+        }
+    }
+}
+
+fn parameter_value_type(name: &str) -> ValueType {
+    match name {
+        "ALTREP" | "DIR" => ValueType::Uri,
+        "DELEGATED-FROM" | "DELEGATED-TO" | "MEMBER" | "SENT-BY" => ValueType::CalAddress,
+        _ => ValueType::Text,
+    }
+}
+
+// iCalendar text values escape some characters which xCal leaves as they are
+
+pub(super) fn unescape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n' | 'N') => out.push('\n'),
+                Some(c) => out.push(c),
+                None => out.push('\\'),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+pub(super) fn escape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn split_unescaped(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == separator {
+            parts.push(&value[start..i]);
+            start = i + 1;
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+// Date and time values use the extended ISO 8601 format in xCal
+
+fn date_to_xcal(value: &str) -> String {
+    if value.len() == 8 && value.is_ascii() {
+        format!("{}-{}-{}", &value[..4], &value[4..6], &value[6..])
+    } else {
+        value.to_owned()
+    }
+}
+
+fn time_to_xcal(value: &str) -> String {
+    if value.len() >= 6 && value.is_ascii() {
+        format!(
+            "{}:{}:{}{}",
+            &value[..2],
+            &value[2..4],
+            &value[4..6],
+            &value[6..]
+        )
+    } else {
+        value.to_owned()
+    }
+}
+
+fn datetime_to_xcal(value: &str) -> String {
+    match value.split_once('T') {
+        Some((date, time)) => format!("{}T{}", date_to_xcal(date), time_to_xcal(time)),
+        None => date_to_xcal(value),
+    }
+}
+
+fn utc_offset_to_xcal(value: &str) -> String {
+    if value.len() >= 5 && value.is_ascii() {
+        let seconds = if value.len() > 5 {
+            format!(":{}", &value[5..])
+        } else {
+            String::new()
+        };
+        format!("{}:{}{}", &value[..3], &value[3..5], seconds)
+    } else {
+        value.to_owned()
+    }
+}
+
+fn datetime_from_xcal(value: &str) -> String {
+    value.replace(['-', ':'], "")
+}
+
+fn utc_offset_from_xcal(value: &str) -> String {
+    value.replace(':', "")
+}
+
+/// A minimal XML element tree, sufficient for xCal documents
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn new(name: &str) -> Self {
+        Element {
+            // Namespace prefixes are irrelevant as we only read xCal
+            name: name.rsplit(':').next().unwrap_or(name).to_owned(),
+            ..Element::default()
+        }
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    fn children_of<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a Element> {
+        self.child(name).into_iter().flat_map(|c| c.children.iter())
+    }
+}
+
+enum Content {
+    Element(Element),
+    Text(String),
+    Skip,
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "lt" => '<',
+                "gt" => '>',
+                "amp" => '&',
+                "quot" => '"',
+                "apos" => '\'',
+                entity => {
+                    let code = match entity.strip_prefix("#x") {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => entity.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn xml_name(input: &str) -> IResult<&str, &str> {
+    take_while1(|c: char| c.is_alphanumeric() || "-_.:".contains(c))(input)
+}
+
+fn xml_attribute(input: &str) -> IResult<&str, ()> {
+    value(
+        (),
+        tuple((
+            multispace1,
+            xml_name,
+            multispace0,
+            char('='),
+            multispace0,
+            alt((
+                delimited(char('"'), take_until("\""), char('"')),
+                delimited(char('\''), take_until("'"), char('\'')),
+            )),
+        )),
+    )(input)
+}
+
+fn xml_comment(input: &str) -> IResult<&str, &str> {
+    delimited(tag("<!--"), take_until("-->"), tag("-->"))(input)
+}
+
+fn xml_misc(input: &str) -> IResult<&str, ()> {
+    value(
+        (),
+        many0(alt((
+            multispace1,
+            xml_comment,
+            delimited(tag("<?"), take_until("?>"), tag("?>")),
+            delimited(tag("<!DOCTYPE"), take_until(">"), char('>')),
+        ))),
+    )(input)
+}
+
+fn xml_content(input: &str) -> IResult<&str, Content> {
+    alt((
+        map(xml_comment, |_| Content::Skip),
+        map(
+            delimited(tag("<![CDATA["), take_until("]]>"), tag("]]>")),
+            |text: &str| Content::Text(text.to_owned()),
+        ),
+        map(xml_element, Content::Element),
+        map(take_while1(|c| c != '<'), |text| {
+            Content::Text(decode_entities(text))
+        }),
+    ))(input)
+}
+
+fn xml_element(input: &str) -> IResult<&str, Element> {
+    let (input, name) = preceded(char('<'), xml_name)(input)?;
+    let (input, _) = pair(many0(xml_attribute), multispace0)(input)?;
+
+    let mut element = Element::new(name);
+
+    if let Ok((input, _)) = tag::<_, _, nom::error::Error<&str>>("/>")(input) {
+        return Ok((input, element));
+    }
+    let (mut input, _) = char('>')(input)?;
+
+    loop {
+        let end_tag: IResult<&str, &str> = delimited(
+            tag("</"),
+            verify(xml_name, |n: &str| n == name),
+            pair(multispace0, char('>')),
+        )(input);
+        if let Ok((rest, _)) = end_tag {
+            return Ok((rest, element));
+        }
+
+        let (rest, content) = xml_content(input)?;
+        match content {
+            Content::Element(child) => element.children.push(child),
+            Content::Text(text) => element.text.push_str(&text),
+            Content::Skip => {}
+        }
+        input = rest;
+    }
+}
+
+fn xml_document(input: &str) -> IResult<&str, Element> {
+    delimited(xml_misc, xml_element, xml_misc)(input)
+}
+
+struct XmlWriter {
+    out: String,
+    depth: usize,
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+impl XmlWriter {
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+    }
+
+    fn start(&mut self, name: &str) {
+        self.indent();
+        writeln!(self.out, "<{}>", name).unwrap();
+        self.depth += 1;
+    }
+
+    fn end(&mut self, name: &str) {
+        self.depth -= 1;
+        self.indent();
+        writeln!(self.out, "</{}>", name).unwrap();
+    }
+
+    fn leaf(&mut self, name: &str, text: &str) {
+        self.indent();
+        writeln!(self.out, "<{0}>{1}</{0}>", name, escape_xml(text)).unwrap();
+    }
+}
+
+fn write_value(w: &mut XmlWriter, value_type: ValueType, value: &str) {
+    match value_type {
+        ValueType::Date => w.leaf("date", &date_to_xcal(value)),
+        ValueType::DateTime => w.leaf("date-time", &datetime_to_xcal(value)),
+        ValueType::Time => w.leaf("time", &time_to_xcal(value)),
+        ValueType::UtcOffset => w.leaf("utc-offset", &utc_offset_to_xcal(value)),
+        ValueType::Boolean => w.leaf("boolean", &value.to_lowercase()),
+        ValueType::Text => w.leaf("text", &unescape_text(value)),
+        ValueType::Period => {
+            let (start, end) = value.split_once('/').unwrap_or((value, ""));
+            w.start("period");
+            w.leaf("start", &datetime_to_xcal(start));
+            if end.starts_with(['P', '+', '-']) {
+                w.leaf("duration", end);
+            } else {
+                w.leaf("end", &datetime_to_xcal(end));
+            }
+            w.end("period");
+        }
+        ValueType::Recur => {
+            w.start("recur");
+            for part in value.split(';') {
+                let (key, values) = part.split_once('=').unwrap_or((part, ""));
+                let key = key.to_lowercase();
+                for v in values.split(',') {
+                    if key == "until" {
+                        w.leaf(&key, &datetime_to_xcal(v));
+                    } else {
+                        w.leaf(&key, v);
+                    }
+                }
+            }
+            w.end("recur");
+        }
+        other => w.leaf(other.name(), value),
+    }
+}
+
+fn write_property(w: &mut XmlWriter, property: &Property) {
+    let name = property.name.to_lowercase();
+    let value = property.value.as_deref().unwrap_or_default();
+
+    let mut value_type = ValueType::default_for(&property.name);
+    let mut params = Vec::new();
+    for (param, values) in property.params.iter().flatten() {
+        if param == "VALUE" {
+            if let Some(t) = values.first().and_then(|v| ValueType::from_name(v)) {
+                value_type = t;
+            }
+        } else {
+            params.push((param, values));
+        }
+    }
+
+    w.start(&name);
+
+    if !params.is_empty() {
+        w.start("parameters");
+        for (param, values) in params {
+            let param_name = param.to_lowercase();
+            w.start(&param_name);
+            for v in values {
+                w.leaf(parameter_value_type(param).name(), v);
+            }
+            w.end(&param_name);
+        }
+        w.end("parameters");
+    }
+
+    match property.name.as_str() {
+        "GEO" => {
+            let (latitude, longitude) = value.split_once(';').unwrap_or((value, ""));
+            w.leaf("latitude", latitude);
+            w.leaf("longitude", longitude);
+        }
+        "REQUEST-STATUS" => {
+            let mut parts = split_unescaped(value, ';').into_iter();
+            w.leaf("code", parts.next().unwrap_or_default());
+            w.leaf(
+                "description",
+                &unescape_text(parts.next().unwrap_or_default()),
+            );
+            if let Some(data) = parts.next() {
+                w.leaf("data", &unescape_text(data));
+            }
+        }
+        _ if value_type.is_multi_valued(&property.name) => {
+            for v in split_unescaped(value, ',') {
+                write_value(w, value_type, v);
+            }
+        }
+        _ => write_value(w, value_type, value),
+    }
+
+    w.end(&name);
+}
+
+fn write_component<F: FnOnce(&mut XmlWriter)>(
+    w: &mut XmlWriter,
+    name: &str,
+    properties: &[Property],
+    sub_components: Option<F>,
+) {
+    w.start(name);
+    w.start("properties");
+    properties.iter().for_each(|p| write_property(w, p));
+    w.end("properties");
+    if let Some(sub_components) = sub_components {
+        w.start("components");
+        sub_components(w);
+        w.end("components");
+    }
+    w.end(name);
+}
+
+fn write_alarms(w: &mut XmlWriter, alarms: &[IcalAlarm]) {
+    for alarm in alarms {
+        write_component(w, "valarm", &alarm.properties, None::<fn(&mut XmlWriter)>);
+    }
+}
+
+fn non_empty<T>(components: &[T]) -> Option<&[T]> {
+    if components.is_empty() {
+        None
+    } else {
+        Some(components)
+    }
+}
+
+/// Serializes `calendar` into its xCal representation
+pub fn write_xcal(calendar: &IcalCalendar) -> String {
+    let mut w = XmlWriter {
+        out: "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n".to_owned(),
+        depth: 1,
+    };
+    writeln!(w.out, "<icalendar xmlns=\"{}\">", XCAL_NAMESPACE).unwrap();
+
+    write_component(
+        &mut w,
+        "vcalendar",
+        &calendar.properties,
+        Some(|w: &mut XmlWriter| {
+            for tz in &calendar.timezones {
+                write_component(
+                    w,
+                    "vtimezone",
+                    &tz.properties,
+                    non_empty(&tz.transitions).map(|transitions| {
+                        move |w: &mut XmlWriter| {
+                            for transition in transitions {
+                                write_component(
+                                    w,
+                                    &transition_kind(transition).to_lowercase(),
+                                    &transition.properties,
+                                    None::<fn(&mut XmlWriter)>,
+                                );
+                            }
+                        }
+                    }),
+                );
+            }
+            for event in &calendar.events {
+                write_component(
+                    w,
+                    "vevent",
+                    &event.properties,
+                    non_empty(&event.alarms)
+                        .map(|alarms| move |w: &mut XmlWriter| write_alarms(w, alarms)),
+                );
+            }
+            for todo in &calendar.todos {
+                write_component(
+                    w,
+                    "vtodo",
+                    &todo.properties,
+                    non_empty(&todo.alarms)
+                        .map(|alarms| move |w: &mut XmlWriter| write_alarms(w, alarms)),
+                );
+            }
+        }),
+    );
+
+    w.out.push_str("</icalendar>\n");
+    w.out
+}
+
+fn read_value(value_type: ValueType, element: &Element) -> String {
+    match value_type {
+        ValueType::Date | ValueType::DateTime | ValueType::Time => {
+            datetime_from_xcal(&element.text)
+        }
+        ValueType::UtcOffset => utc_offset_from_xcal(&element.text),
+        ValueType::Boolean => element.text.to_uppercase(),
+        ValueType::Text => escape_text(&element.text),
+        ValueType::Period => {
+            let part = |name| element.child(name).map(|e| e.text.as_str());
+            let start = datetime_from_xcal(part("start").unwrap_or_default());
+            match (part("end"), part("duration")) {
+                (Some(end), _) => format!("{}/{}", start, datetime_from_xcal(end)),
+                (None, Some(duration)) => format!("{}/{}", start, duration),
+                (None, None) => start,
+            }
+        }
+        ValueType::Recur => {
+            let mut parts: Vec<(String, Vec<String>)> = Vec::new();
+            for part in &element.children {
+                let v = if part.name == "until" {
+                    datetime_from_xcal(&part.text)
+                } else {
+                    part.text.clone()
+                };
+                let key = part.name.to_uppercase();
+                match parts.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, values)) => values.push(v),
+                    None => parts.push((key, vec![v])),
+                }
+            }
+            parts
+                .into_iter()
+                .map(|(key, values)| format!("{}={}", key, values.join(",")))
+                .collect::<Vec<_>>()
+                .join(";")
+        }
+        _ => element.text.clone(),
+    }
+}
+
+fn read_property(element: &Element) -> Property {
+    let name = element.name.to_uppercase();
+    let default_type = ValueType::default_for(&name);
+
+    let mut params: Vec<(String, Vec<String>)> = element
+        .children_of("parameters")
+        .map(|param| {
+            (
+                param.name.to_uppercase(),
+                param.children.iter().map(|v| v.text.clone()).collect(),
+            )
+        })
+        .collect();
+
+    let value_elements: Vec<&Element> = element
+        .children
+        .iter()
+        .filter(|c| c.name != "parameters")
+        .collect();
+
+    let value = match name.as_str() {
+        "GEO" | "REQUEST-STATUS" => value_elements
+            .iter()
+            .map(|e| match e.name.as_str() {
+                "code" | "latitude" | "longitude" => e.text.clone(),
+                _ => escape_text(&e.text),
+            })
+            .collect::<Vec<_>>()
+            .join(";"),
+        _ => {
+            let value_type = value_elements
+                .first()
+                .and_then(|e| ValueType::from_name(&e.name))
+                .unwrap_or(default_type);
+            if value_type != default_type && value_type != ValueType::Unknown {
+                params.push(("VALUE".to_owned(), vec![value_type.name().to_uppercase()]));
+            }
+            value_elements
+                .iter()
+                .map(|e| read_value(value_type, e))
+                .collect::<Vec<_>>()
+                .join(",")
+        }
+    };
+
+    Property {
+        name,
+        params: if params.is_empty() {
+            None
+        } else {
+            Some(params)
+        },
+        value: Some(value),
+    }
+}
+
+fn read_properties(element: &Element) -> Vec<Property> {
+    element
+        .children_of("properties")
+        .map(read_property)
+        .collect()
+}
+
+fn read_alarms(element: &Element) -> Vec<IcalAlarm> {
+    element
+        .children_of("components")
+        .filter(|c| c.name == "valarm")
+        .map(|c| {
+            let mut alarm = IcalAlarm::new();
+            alarm.properties = read_properties(c);
+            alarm
+        })
+        .collect()
+}
+
+fn read_calendar(element: &Element) -> IcalCalendar {
+    let mut calendar = IcalCalendar::new();
+    calendar.properties = read_properties(element);
+
+    for component in element.children_of("components") {
+        match component.name.as_str() {
+            "vevent" => {
+                let mut event = IcalEvent::new();
+                event.properties = read_properties(component);
+                event.alarms = read_alarms(component);
+                calendar.events.push(event);
+            }
+            "vtodo" => {
+                let mut todo = IcalTodo::new();
+                todo.properties = read_properties(component);
+                todo.alarms = read_alarms(component);
+                calendar.todos.push(todo);
+            }
+            "vtimezone" => {
+                let mut timezone = IcalTimeZone::new();
+                timezone.properties = read_properties(component);
+                timezone.transitions = component
+                    .children_of("components")
+                    .map(|c| {
+                        let mut transition = IcalTimeZoneTransition::new();
+                        transition.properties = read_properties(c);
+                        transition
+                    })
+                    .collect();
+                calendar.timezones.push(timezone);
+            }
+            other => log::warn!("Skipping unsupported xCal component '{}'", other),
+        }
+    }
+
+    calendar
+}
+
+/// Whether `content` looks like an XML document rather than iCalendar text
+pub fn is_xcal(content: &str) -> bool {
+    content.trim_start().starts_with('<')
+}
+
+/// Parses an xCal document. Every `vcalendar` element of the document is
+/// returned as a separate calendar.
+pub fn parse_xcal(content: &str) -> Result<Vec<IcalCalendar>> {
+    let (_, root) = xml_document(content).map_err(|e| {
+        Error::new(
+            ErrorKind::CalendarParse,
+            &format!("Could not read xCal document: {}", e),
+        )
+    })?;
+
+    if root.name != "icalendar" {
+        return Err(Error::new(
+            ErrorKind::CalendarParse,
+            &format!("Expected 'icalendar' element, found '{}'", root.name),
+        ));
+    }
+
+    Ok(root
+        .children
+        .iter()
+        .filter(|c| c.name == "vcalendar")
+        .map(read_calendar)
+        .collect())
+}
+
+/// Converts an xCal document into iCalendar text
+pub fn xcal_to_ics(content: &str) -> Result<String> {
+    Ok(parse_xcal(content)?.iter().map(write_calendar).collect())
+}
+
+/// Converts iCalendar text into an xCal document
+pub fn ics_to_xcal(ics: &str) -> Result<String> {
+    let mut calendars = IcalParser::new(ics.as_bytes());
+    let calendar = calendars
+        .next()
+        .ok_or_else(|| Error::new(ErrorKind::CalendarParse, "No calendar found"))?
+        .map_err(|e| {
+            Error::new(
+                ErrorKind::CalendarParse,
+                &format!("Could not read calendar: {}", e),
+            )
+        })?;
+
+    Ok(write_xcal(&calendar))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//jackal//test//EN\r
+BEGIN:VTIMEZONE\r
+TZID:Europe/Berlin\r
+BEGIN:STANDARD\r
+TZOFFSETFROM:+0200\r
+TZOFFSETTO:+0100\r
+DTSTART:19701025T030000\r
+RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r
+END:STANDARD\r
+END:VTIMEZONE\r
+BEGIN:VEVENT\r
+UID:event-1@jackal\r
+DTSTAMP:20261001T120000Z\r
+DTSTART;TZID=Europe/Berlin:20261015T093000\r
+DURATION:PT1H\r
+RRULE:FREQ=WEEKLY;UNTIL=20261231T000000Z;BYDAY=TU,TH\r
+EXDATE;TZID=Europe/Berlin:20261020T093000,20261022T093000\r
+SUMMARY:Planning\\, part 2\r
+DESCRIPTION:Agenda:\\n1. <budget> & \"plans\"\r
+CATEGORIES:work,planning\r
+GEO:52.52;13.405\r
+ATTENDEE;CN=Jane Doe;ROLE=REQ-PARTICIPANT:mailto:jane@example.com\r
+BEGIN:VALARM\r
+ACTION:DISPLAY\r
+TRIGGER:-PT15M\r
+DESCRIPTION:Planning\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:event-2@jackal\r
+DTSTART;VALUE=DATE:20261016\r
+SUMMARY:Holiday\r
+END:VEVENT\r
+BEGIN:VTODO\r
+UID:todo-1@jackal\r
+SUMMARY:Write minutes\r
+PERCENT-COMPLETE:50\r
+END:VTODO\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn round_trip() {
+        let xcal = ics_to_xcal(CALENDAR).unwrap();
+        assert!(is_xcal(&xcal));
+        assert_eq!(xcal_to_ics(&xcal).unwrap(), CALENDAR);
+    }
+
+    #[test]
+    fn reads_xml_syntax() {
+        let xcal = r#"<?xml version="1.0" encoding="utf-8"?>
+<!-- exported by hand -->
+<x:icalendar xmlns:x="urn:ietf:params:xml:ns:icalendar-2.0">
+  <x:vcalendar>
+    <x:properties>
+      <x:version><x:text>2.0</x:text></x:version>
+    </x:properties>
+    <x:components>
+      <x:vevent>
+        <x:properties>
+          <x:uid><x:text>event-1@jackal</x:text></x:uid>
+          <x:dtstart><x:date-time>2026-10-15T09:30:00Z</x:date-time></x:dtstart>
+          <x:summary><x:text>Fish &amp; chips &#233;&#x20AC;</x:text></x:summary>
+          <x:x-jackal-note><x:unknown>kept</x:unknown></x:x-jackal-note>
+        </x:properties>
+      </x:vevent>
+    </x:components>
+  </x:vcalendar>
+</x:icalendar>"#;
+
+        let calendars = parse_xcal(xcal).unwrap();
+        assert_eq!(calendars.len(), 1);
+        let properties: Vec<(&str, &str)> = calendars[0].events[0]
+            .properties
+            .iter()
+            .map(|p| (p.name.as_str(), p.value.as_deref().unwrap_or_default()))
+            .collect();
+        assert_eq!(
+            properties,
+            [
+                ("UID", "event-1@jackal"),
+                ("DTSTART", "20261015T093000Z"),
+                ("SUMMARY", "Fish & chips é€"),
+                ("X-JACKAL-NOTE", "kept"),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert!(parse_xcal("<html><body/></html>").is_err());
+        assert!(parse_xcal("<icalendar><vcalendar>").is_err());
+    }
+}
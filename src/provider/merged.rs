@@ -0,0 +1,254 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::ical::{import, writer};
+use super::*;
+use crate::config::MergedCalendarSpec;
+
+/// A calendar defined in the config as the union of several others. The
+/// member calendars are only reachable through it.
+pub struct MergedCalendar {
+    id: String,
+    name: String,
+    color: Option<String>,
+    /// Never empty, new events go to the first member
+    members: Vec<Box<dyn Calendarlike>>,
+}
+
+impl MergedCalendar {
+    pub fn new(spec: &MergedCalendarSpec, members: Vec<Box<dyn Calendarlike>>) -> Result<Self> {
+        if members.is_empty() {
+            return Err(Error::new(
+                ErrorKind::Invalid,
+                &format!("Merged calendar '{}' has no calendars", spec.id),
+            ));
+        }
+
+        Ok(MergedCalendar {
+            id: spec.id.clone(),
+            name: spec.name.clone().unwrap_or_else(|| spec.id.clone()),
+            color: spec.color.clone(),
+            members,
+        })
+    }
+
+    fn defined_in_config(&self) -> Error {
+        Error::new(
+            ErrorKind::Unsupported,
+            &format!(
+                "'{}' is a merged calendar, change it in the config file",
+                self.id
+            ),
+        )
+    }
+
+    fn member_with_uid(&mut self, uid: &str) -> Result<&mut Box<dyn Calendarlike>> {
+        self.members
+            .iter_mut()
+            .find(|member| member.event_iter().any(|event| event.uid() == uid))
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, &format!("No event with UID '{}'", uid)))
+    }
+
+    /// `filter` for `member`, whose time zone may differ, and no longer
+    /// restricted to the merged calendar
+    fn member_filter(&self, filter: &EventFilter, member: &dyn Calendarlike) -> EventFilter {
+        let convert = |bound: &Bound<NaiveDateTime>| {
+            bound.map(|dt| match self.tz().from_local_datetime(&dt).earliest() {
+                Some(dt) => dt.with_timezone(member.tz()).naive_local(),
+                None => dt,
+            })
+        };
+
+        EventFilter {
+            begin: convert(&filter.begin),
+            end: convert(&filter.end),
+            calendars: None,
+        }
+    }
+}
+
+impl Calendarlike for MergedCalendar {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn rename(&mut self, _name: &str) -> Result<()> {
+        Err(self.defined_in_config())
+    }
+
+    fn color(&self) -> Option<&str> {
+        self.color.as_deref()
+    }
+
+    fn set_color(&mut self, _color: &str) -> Result<()> {
+        Err(self.defined_in_config())
+    }
+
+    fn path(&self) -> &Path {
+        self.members[0].path()
+    }
+
+    fn tz(&self) -> &Tz {
+        self.members[0].tz()
+    }
+
+    fn set_tz(&mut self, tz: &Tz) {
+        for member in &mut self.members {
+            member.set_tz(tz);
+        }
+    }
+
+    fn is_available(&self) -> bool {
+        self.members.iter().all(|member| member.is_available())
+    }
+
+    fn uid_conflicts(&self) -> Vec<UidConflict> {
+        self.members
+            .iter()
+            .flat_map(|member| member.uid_conflicts())
+            .collect()
+    }
+
+    fn resolve_uid_conflict(&mut self, uid: &str) -> Result<Vec<PathBuf>> {
+        let mut removed = Vec::new();
+        for member in &mut self.members {
+            if member.uid_conflicts().iter().any(|c| c.uid == uid) {
+                removed.extend(member.resolve_uid_conflict(uid)?);
+            }
+        }
+        Ok(removed)
+    }
+
+    fn reload(&mut self) -> Result<bool> {
+        let mut reloaded = false;
+        for member in &mut self.members {
+            reloaded |= member.reload()?;
+        }
+        Ok(reloaded)
+    }
+
+    fn last_modified(&self) -> Option<SystemTime> {
+        self.members
+            .iter()
+            .filter_map(|member| member.last_modified())
+            .max()
+    }
+
+    fn archive(&mut self, before: DateTime<Utc>) -> Result<usize> {
+        let mut num_events = 0;
+        for member in &mut self.members {
+            num_events += member.archive(before)?;
+        }
+        Ok(num_events)
+    }
+
+    fn load_archive(&mut self) -> Result<usize> {
+        let mut num_events = 0;
+        for member in &mut self.members {
+            num_events += member.load_archive()?;
+        }
+        Ok(num_events)
+    }
+
+    fn suspend_watching(&self) -> Result<Vec<PathBuf>> {
+        let mut markers = Vec::new();
+        for member in &self.members {
+            markers.extend(member.suspend_watching()?);
+        }
+        Ok(markers)
+    }
+
+    fn event_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a (dyn Eventlike + 'a)> + 'a> {
+        Box::new(self.members.iter().flat_map(|member| member.event_iter()))
+    }
+
+    fn filter_events<'a>(
+        &'a self,
+        filter: EventFilter,
+    ) -> Box<dyn Iterator<Item = &'a (dyn Eventlike + 'a)> + 'a> {
+        if !filter.includes_calendar(self) {
+            return Box::new(std::iter::empty());
+        }
+
+        let mut events: Vec<_> = self
+            .members
+            .iter()
+            .flat_map(|member| member.filter_events(self.member_filter(&filter, member.as_ref())))
+            .collect();
+
+        // Keep the order of a single calendar
+        events.sort_by_key(|event| event.begin());
+        Box::new(events.into_iter())
+    }
+
+    fn count_events(&self, filter: EventFilter) -> usize {
+        if !filter.includes_calendar(self) {
+            return 0;
+        }
+
+        self.members
+            .iter()
+            .map(|member| member.count_events(self.member_filter(&filter, member.as_ref())))
+            .sum()
+    }
+
+    fn next_event_after<'a>(&'a self, dt: &DateTime<Tz>) -> Option<&'a (dyn Eventlike + 'a)> {
+        self.members
+            .iter()
+            .filter_map(|member| member.next_event_after(dt))
+            .min_by_key(|event| event.begin())
+    }
+
+    fn new_event(&mut self, event: NewEvent<Tz>) -> Result<String> {
+        self.members[0].new_event(event)
+    }
+
+    fn remove_event(&mut self, event: &EventId) -> Result<()> {
+        self.member_with_uid(&event.uid)?.remove_event(event)
+    }
+
+    fn modify_event(&mut self, event: &EventId, change: &EventChange) -> Result<()> {
+        self.member_with_uid(&event.uid)?
+            .modify_event(event, change)
+    }
+
+    fn todo_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a (dyn Todolike + 'a)> + 'a> {
+        Box::new(self.members.iter().flat_map(|member| member.todo_iter()))
+    }
+
+    fn set_todo_done(&mut self, uid: &str, done: bool) -> Result<()> {
+        self.members
+            .iter_mut()
+            .find(|member| member.todo_iter().any(|todo| todo.uid() == uid))
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, &format!("No task with UID '{}'", uid)))?
+            .set_todo_done(uid, done)
+    }
+
+    fn import_ics(&mut self, ics: &str) -> Result<ImportSummary> {
+        // Replace existing events in the member they are stored in
+        let existing = import::uids(ics).into_iter().find_map(|uid| {
+            self.members.iter().position(|member| {
+                member.event_iter().any(|event| event.uid() == uid)
+                    || member.todo_iter().any(|todo| todo.uid() == uid)
+            })
+        });
+
+        self.members[existing.unwrap_or(0)].import_ics(ics)
+    }
+
+    fn export_ics(&self) -> String {
+        let documents: Vec<String> = self
+            .members
+            .iter()
+            .map(|member| member.export_ics())
+            .collect();
+        writer::write_calendar(&import::merge_calendars(&documents))
+    }
+}
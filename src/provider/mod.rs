@@ -1,16 +1,18 @@
-use chrono::{
-    Date, DateTime, Duration, Local, Month, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
-};
+use chrono::{Date, DateTime, Duration, Month, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
 use chrono_tz::Tz;
-use std::collections::BTreeMap;
-use std::convert::From;
+use std::collections::BTreeSet;
+use std::convert::{From, TryFrom};
 use std::default::Default;
-use std::ops::{Bound, RangeBounds};
-use std::path::Path;
+use std::ops::{Bound, Deref, RangeBounds};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use uuid::Uuid;
 
+pub mod datetime;
 pub mod error;
 pub mod ical;
+pub mod merged;
+pub mod tz;
 
 pub use error::*;
 
@@ -104,6 +106,16 @@ impl<Tz: TimeZone> Occurrence<Tz> {
         matches!(self, Onetime(_))
     }
 
+    pub fn is_instant(&self) -> bool {
+        use Occurrence::*;
+        matches!(self, Instant(_))
+    }
+
+    /// Instants and timespans without duration, i.e. begin equals end
+    pub fn is_zero_length(&self) -> bool {
+        !self.is_allday() && self.duration().is_zero()
+    }
+
     pub fn as_date(&self) -> NaiveDate {
         use Occurrence::*;
         match self {
@@ -152,6 +164,38 @@ impl<Tz: TimeZone> Occurrence<Tz> {
         }
     }
 
+    /// End of the occurrence for display purposes: occurrences shorter than
+    /// `min_duration` are extended to last at least `min_duration`
+    pub fn display_end(&self, min_duration: Duration) -> chrono::DateTime<Tz> {
+        if !self.is_allday() && self.duration() < min_duration {
+            self.begin() + min_duration
+        } else {
+            self.end()
+        }
+    }
+
+    /// The days the occurrence covers. The end of a timed occurrence is
+    /// exclusive, so an event ending at midnight does not cover the following
+    /// day, while zero-length occurrences always cover the day they begin on.
+    pub fn days(&self) -> impl Iterator<Item = NaiveDate> {
+        use Occurrence::*;
+
+        let first = self.as_date();
+        let last = match self {
+            Allday(date, Some(edate)) if edate.clone() > date.clone() => {
+                // DTEND of all-day events is exclusive as well
+                edate.naive_utc().pred_opt().unwrap_or(first)
+            }
+            Allday(_, _) | Instant(_) => first,
+            Onetime(_) if self.is_zero_length() => first,
+            Onetime(timespan) => (timespan.end() - Duration::nanoseconds(1))
+                .date_naive()
+                .max(first),
+        };
+
+        first.iter_days().take_while(move |day| *day <= last)
+    }
+
     pub fn with_tz<Tz2: TimeZone>(self, tz: &Tz2) -> Occurrence<Tz2> {
         use Occurrence::*;
         match self {
@@ -174,9 +218,12 @@ impl<Tz: TimeZone> Occurrence<Tz> {
     }
 }
 
+#[derive(Clone)]
 pub struct EventFilter {
     pub begin: Bound<NaiveDateTime>,
     pub end: Bound<NaiveDateTime>,
+    /// Ids or names of the calendars to include, all if `None`
+    pub calendars: Option<BTreeSet<String>>,
 }
 
 impl Default for EventFilter {
@@ -184,6 +231,7 @@ impl Default for EventFilter {
         EventFilter {
             begin: Bound::Unbounded,
             end: Bound::Unbounded,
+            calendars: None,
         }
     }
 }
@@ -205,12 +253,201 @@ impl EventFilter {
 
         self
     }
+
+    pub fn calendars<I: IntoIterator<Item = S>, S: Into<String>>(mut self, calendars: I) -> Self {
+        self.calendars = Some(calendars.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn includes_calendar(&self, calendar: &dyn Calendarlike) -> bool {
+        self.calendars.as_ref().is_none_or(|calendars| {
+            calendars.contains(calendar.id()) || calendars.contains(calendar.name())
+        })
+    }
+}
+
+/// Provider independent description of an event to be created
+pub struct NewEvent<Tz: TimeZone> {
+    pub begin: DateTime<Tz>,
+    pub end: Option<DateTime<Tz>>,
+    pub summary: Option<String>,
+    pub location: Option<String>,
+    /// Recurrence rule as in RRULE, e.g. `FREQ=WEEKLY;BYDAY=MO`
+    pub rrule: Option<String>,
+}
+
+impl<Tz: TimeZone> NewEvent<Tz> {
+    pub fn new(begin: DateTime<Tz>) -> Self {
+        NewEvent {
+            begin,
+            end: None,
+            summary: None,
+            location: None,
+            rrule: None,
+        }
+    }
+}
+
+/// An event together with the calendar it was loaded from, so that its
+/// source can be shown without looking it up again
+#[derive(Clone, Copy)]
+pub struct CalendarEvent<'a> {
+    pub calendar: &'a (dyn Calendarlike + 'a),
+    pub event: &'a (dyn Eventlike + 'a),
+}
+
+impl<'a> Deref for CalendarEvent<'a> {
+    type Target = dyn Eventlike + 'a;
+
+    fn deref(&self) -> &Self::Target {
+        self.event
+    }
+}
+
+/// A task together with the calendar it was loaded from
+#[derive(Clone, Copy)]
+pub struct CalendarTodo<'a> {
+    pub calendar: &'a (dyn Calendarlike + 'a),
+    pub todo: &'a (dyn Todolike + 'a),
+}
+
+impl<'a> Deref for CalendarTodo<'a> {
+    type Target = dyn Todolike + 'a;
+
+    fn deref(&self) -> &Self::Target {
+        self.todo
+    }
+}
+
+/// The point of an event a relative alarm trigger refers to, or the fixed
+/// time of an absolute one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlarmRelation {
+    Start,
+    End,
+    /// TRIGGER;VALUE=DATE-TIME, the same for all instances of a series
+    At(DateTime<Utc>),
+}
+
+/// A reminder (VALARM) attached to an event
+#[derive(Clone, Debug)]
+pub struct Alarm {
+    /// Offset from the start or end of the event, negative if before. Zero
+    /// for absolute triggers.
+    pub offset: Duration,
+    pub related: AlarmRelation,
+    /// E.g. DISPLAY, AUDIO or EMAIL
+    pub action: String,
+    pub description: Option<String>,
+    /// How often the alarm fires again after the first time (REPEAT)
+    pub repeat: u32,
+    /// Time between two repetitions (DURATION)
+    pub interval: Duration,
+}
+
+impl Alarm {
+    /// Repetitions of an alarm beyond this are ignored, REPEAT may be
+    /// anything up to 2^32 - 1
+    pub const MAX_REPEAT: u32 = 100;
+
+    /// When the alarm fires for `occurrence` the first time, `None` if that
+    /// is out of the range of representable times
+    pub fn trigger_time<Tz: TimeZone>(&self, occurrence: &Occurrence<Tz>) -> Option<DateTime<Tz>> {
+        match self.related {
+            AlarmRelation::Start => occurrence.begin().checked_add_signed(self.offset),
+            AlarmRelation::End => occurrence.end().checked_add_signed(self.offset),
+            AlarmRelation::At(time) => Some(time.with_timezone(&occurrence.begin().timezone())),
+        }
+    }
+
+    /// When the alarm fires for `occurrence`, the first time and up to
+    /// `MAX_REPEAT` repetitions. Stops early at the end of the range of
+    /// representable times.
+    pub fn trigger_times<Tz: TimeZone>(
+        &self,
+        occurrence: &Occurrence<Tz>,
+    ) -> impl Iterator<Item = DateTime<Tz>> {
+        let first = self.trigger_time(occurrence);
+        let interval = self.interval;
+        (0..=self.repeat.min(Self::MAX_REPEAT)).map_while(move |i| {
+            let offset = interval.checked_mul(i32::try_from(i).ok()?)?;
+            first.clone()?.checked_add_signed(offset)
+        })
+    }
+}
+
+/// A modification of an existing event
+#[derive(Clone, Debug)]
+pub enum EventChange {
+    /// Moves begin and end by the given duration
+    Shift(Duration),
+    /// Moves only the end by the given duration
+    Resize(Duration),
+    /// Replaces all categories of the event
+    SetCategories(Vec<String>),
+    /// Pinned events are always listed, whatever day is shown
+    SetPinned(bool),
+    AddAlarm(Alarm),
+    /// Removes the alarms triggering at the given offset from the start
+    RemoveAlarm(Duration),
+    /// Moves all times of the event given in `from` to `to`, keeping the
+    /// local times unless `keep_instant` is set
+    ChangeTimezone {
+        from: Tz,
+        to: Tz,
+        keep_instant: bool,
+    },
+}
+
+/// The event an operation applies to: the one with `uid` including all its
+/// instances or, with `recurrence_id`, only the override replacing the
+/// instance that originally began at that time
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventId {
+    pub uid: String,
+    pub recurrence_id: Option<DateTime<Utc>>,
+}
+
+impl EventId {
+    /// All instances of the event with `uid`
+    pub fn series(uid: &str) -> Self {
+        EventId {
+            uid: uid.to_owned(),
+            recurrence_id: None,
+        }
+    }
+
+    /// `event` alone if it is an override, its whole series otherwise
+    pub fn of(event: &(impl Eventlike + ?Sized)) -> Self {
+        EventId {
+            uid: event.uid().to_owned(),
+            recurrence_id: event.recurrence_id(),
+        }
+    }
+}
+
+/// Result of importing iCalendar data into a calendar
+#[derive(Default, Debug)]
+pub struct ImportSummary {
+    pub events: usize,
+    pub overrides: usize,
+    pub files: usize,
+}
+
+/// Several event files of one calendar sharing the same UID
+pub struct UidConflict {
+    pub uid: String,
+    pub paths: Vec<PathBuf>,
 }
 
 pub trait Eventlike {
     fn title(&self) -> &str;
     fn set_title(&mut self, title: &str);
     fn uuid(&self) -> Uuid;
+    fn uid(&self) -> &str;
+    /// Original begin of the instance this event replaces, `None` unless it
+    /// is an override of a recurring event
+    fn recurrence_id(&self) -> Option<DateTime<Utc>>;
     fn summary(&self) -> &str;
     fn set_summary(&mut self, summary: &str);
     fn occurrence(&self) -> &Occurrence<Tz>;
@@ -220,33 +457,136 @@ pub trait Eventlike {
     fn begin(&self) -> DateTime<Tz>;
     fn end(&self) -> DateTime<Tz>;
     fn duration(&self) -> Duration;
+    /// File the event is stored in
+    fn path(&self) -> &Path;
+    /// Serialized iCalendar representation of the event's component
+    fn raw_ical(&self) -> String;
+    fn categories(&self) -> Vec<String>;
+    fn location(&self) -> Option<String>;
+    /// The URL property, e.g. a link to the meeting
+    fn url(&self) -> Option<String>;
+    /// Latitude and longitude of the GEO property
+    fn geo(&self) -> Option<(f64, f64)>;
+    /// Names or, if they have none, addresses of the attendees
+    fn attendees(&self) -> Vec<String>;
+    /// The DESCRIPTION with escaped newlines, commas etc. resolved
+    fn description(&self) -> Option<String>;
+    /// Alarms with a trigger relative to the event
+    fn alarms(&self) -> Vec<Alarm>;
+    fn is_pinned(&self) -> bool;
+    /// Whether the event blocks its time, i.e. its TRANSP is not TRANSPARENT
+    fn is_opaque(&self) -> bool;
+}
+
+/// Progress of a task as given by its STATUS
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TodoStatus {
+    NeedsAction,
+    InProcess,
+    Completed,
+    Cancelled,
+}
+
+pub trait Todolike {
+    fn uid(&self) -> &str;
+    fn summary(&self) -> &str;
+    /// When the task has to be done, `None` if it has no deadline
+    fn due(&self) -> Option<DateTime<Tz>>;
+    /// Whether the deadline is a point in time rather than a whole day
+    fn has_due_time(&self) -> bool;
+    fn status(&self) -> TodoStatus;
+    fn percent_complete(&self) -> u8;
+    /// From 1 (highest) to 9 (lowest), `None` if undefined
+    fn priority(&self) -> Option<u8>;
+    /// The task as iCalendar data, e.g. to restore it
+    fn raw_ical(&self) -> String;
+    /// File the task is stored in
+    fn path(&self) -> &Path;
+
+    fn is_done(&self) -> bool {
+        matches!(self.status(), TodoStatus::Completed | TodoStatus::Cancelled)
+    }
 }
 
 pub trait Calendarlike {
+    fn id(&self) -> &str;
     fn name(&self) -> &str;
+    fn rename(&mut self, name: &str) -> Result<()>;
+    fn color(&self) -> Option<&str>;
+    fn set_color(&mut self, color: &str) -> Result<()>;
     fn path(&self) -> &Path;
     fn tz(&self) -> &Tz;
     fn set_tz(&mut self, tz: &Tz);
-    fn event_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &(dyn Eventlike + 'a)> + 'a>;
+    /// False while the calendar's directory is missing, e.g. on an
+    /// unmounted drive. The events loaded before stay visible but can not be
+    /// changed until `reload` finds the directory again.
+    fn is_available(&self) -> bool;
+    fn uid_conflicts(&self) -> Vec<UidConflict>;
+    /// Keeps the event with the newest SEQUENCE and deletes all other files
+    /// sharing `uid`. Returns the paths of the removed files.
+    fn resolve_uid_conflict(&mut self, uid: &str) -> Result<Vec<PathBuf>>;
+    /// Re-reads event files that changed on disk since they were loaded.
+    /// Returns whether anything changed.
+    fn reload(&mut self) -> Result<bool>;
+    /// When the calendar last changed on disk, e.g. by a sync
+    fn last_modified(&self) -> Option<SystemTime>;
+    /// Moves the files of non-recurring events that ended before `before`
+    /// into an archive, where they are not loaded. Returns their number.
+    fn archive(&mut self, before: DateTime<Utc>) -> Result<usize>;
+    /// Loads the archived events as well and returns their number
+    fn load_archive(&mut self) -> Result<usize>;
+    /// Keeps other instances from reloading the calendar while many files
+    /// change. Returns the marker files to remove to resume watching.
+    fn suspend_watching(&self) -> Result<Vec<PathBuf>>;
+    fn event_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a (dyn Eventlike + 'a)> + 'a>;
     fn filter_events<'a>(
         &'a self,
         filter: EventFilter,
-    ) -> Box<dyn Iterator<Item = &(dyn Eventlike + 'a)> + 'a>;
-    fn new_event(&mut self);
+    ) -> Box<dyn Iterator<Item = &'a (dyn Eventlike + 'a)> + 'a>;
+    /// Number of events matching `filter` without materializing them
+    fn count_events(&self, filter: EventFilter) -> usize;
+    /// The first event beginning strictly after `dt`
+    fn next_event_after<'a>(&'a self, dt: &DateTime<Tz>) -> Option<&'a (dyn Eventlike + 'a)>;
+    /// Adds a new event and returns its UID
+    fn new_event(&mut self, event: NewEvent<Tz>) -> Result<String>;
+    /// Deletes `event`. Removing an override excludes its instance from the
+    /// series rather than bringing back the original one.
+    fn remove_event(&mut self, event: &EventId) -> Result<()>;
+    /// Applies `change` to `event` and writes it back
+    fn modify_event(&mut self, event: &EventId, change: &EventChange) -> Result<()>;
+    /// Imports all events of the iCalendar data `ics`, writing one file per
+    /// UID. Existing events with the same UID are replaced.
+    fn import_ics(&mut self, ics: &str) -> Result<ImportSummary>;
+    /// All events of the calendar as a single iCalendar document
+    fn export_ics(&self) -> String;
+    fn todo_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a (dyn Todolike + 'a)> + 'a>;
+    /// Marks the task with `uid` as completed or as needing action again
+    fn set_todo_done(&mut self, uid: &str, done: bool) -> Result<()>;
 }
 
 pub trait Collectionlike {
     fn name(&self) -> &str;
     fn path(&self) -> &Path;
-    fn calendar_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &(dyn Calendarlike + 'a)> + 'a>;
-    fn event_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &(dyn Eventlike + 'a)> + 'a>;
+    fn calendar_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a (dyn Calendarlike + 'a)> + 'a>;
+    fn calendar_iter_mut<'a>(
+        &'a mut self,
+    ) -> Box<dyn Iterator<Item = &'a mut (dyn Calendarlike + 'a)> + 'a>;
+    fn event_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a (dyn Eventlike + 'a)> + 'a>;
     fn new_calendar(&mut self);
+    /// Loads the calendars of a collection whose directory was missing so
+    /// far once it is back. Returns whether any were loaded.
+    fn load_missing(&mut self) -> bool;
+    /// Removes the calendar with `id` from the collection and hands it over
+    fn take_calendar(&mut self, id: &str) -> Option<Box<dyn Calendarlike>>;
 }
 
 pub fn load_collection(provider: &str, path: &Path) -> Result<impl Collectionlike> {
     match provider {
         "ical" => ical::Collection::from_dir(path),
-        _ => Err(Error::new(ErrorKind::CalendarParse, "No collection found")),
+        _ => Err(Error::new(
+            ErrorKind::Unsupported,
+            &format!("Unknown provider '{}'", provider),
+        )),
     }
 }
 
@@ -257,6 +597,9 @@ pub fn load_collection_with_calendars(
 ) -> Result<impl Collectionlike> {
     match provider {
         "ical" => ical::Collection::calendars_from_dir(path, calendar_specs),
-        _ => Err(Error::new(ErrorKind::CalendarParse, "No collection found")),
+        _ => Err(Error::new(
+            ErrorKind::Unsupported,
+            &format!("Unknown provider '{}'", provider),
+        )),
     }
 }
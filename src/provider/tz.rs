@@ -0,0 +1,96 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Offset, TimeZone};
+use chrono_tz::Tz;
+
+use super::{Error, ErrorKind, Result};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const TIME_FORMATS: &[&str] = &["%H:%M", "%H:%M:%S"];
+
+pub fn parse_tz(name: &str) -> Result<Tz> {
+    name.parse::<Tz>()
+        .map_err(|err| Error::new(ErrorKind::TimeParse, &err))
+}
+
+/// The timezone of the system as named by `TZ` or the `/etc/localtime`
+/// link, `None` if it cannot be told
+pub fn local_tz() -> Option<Tz> {
+    if let Ok(name) = std::env::var("TZ") {
+        // POSIX allows a leading colon
+        return parse_tz(name.trim_start_matches(':')).ok();
+    }
+    let target = std::fs::read_link("/etc/localtime").ok()?;
+    let target = target.to_str()?;
+    let (_, name) = target.split_once("zoneinfo/")?;
+    parse_tz(name).ok()
+}
+
+fn parse_time(input: &str) -> Option<NaiveTime> {
+    TIME_FORMATS
+        .iter()
+        .find_map(|fmt| NaiveTime::parse_from_str(input, fmt).ok())
+}
+
+/// Parses a datetime of the form `[YYYY-MM-DD] [HH:MM[:SS]] [TZID]`.
+///
+/// Missing parts are taken from `reference`: the date as seen in
+/// `reference`'s timezone, midnight as time and `reference`'s timezone.
+pub fn parse_datetime(input: &str, reference: &DateTime<Tz>) -> Result<DateTime<Tz>> {
+    let mut tokens: Vec<&str> = input.split_whitespace().collect();
+
+    let tz = match tokens.last().map(|token| parse_tz(token)) {
+        Some(Ok(tz)) => {
+            tokens.pop();
+            tz
+        }
+        _ => reference.timezone(),
+    };
+
+    let mut date = reference.date_naive();
+    let mut time = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+
+    for token in tokens {
+        if let Ok(d) = NaiveDate::parse_from_str(token, DATE_FORMAT) {
+            date = d;
+        } else if let Some(t) = parse_time(token) {
+            time = t;
+        } else {
+            return Err(Error::new(
+                ErrorKind::TimeParse,
+                &format!("Unknown date, time or timezone '{}'", token),
+            ));
+        }
+    }
+
+    tz.from_local_datetime(&date.and_time(time))
+        .earliest()
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::TimeParse,
+                &format!("{} {} does not exist in {}", date, time, tz.name()),
+            )
+        })
+}
+
+/// The change of the UTC offset of `tz` during `date`, e.g. one hour when
+/// clocks go forward for daylight saving time. `None` if the offset does not
+/// change on that day.
+pub fn offset_change_on<T: TimeZone>(tz: &T, date: NaiveDate) -> Option<Duration> {
+    // Midnight may be skipped if the transition happens right at it
+    let offset_at = |date: NaiveDate| {
+        let start_of_day = tz
+            .from_local_datetime(&date.and_time(NaiveTime::MIN))
+            .earliest()
+            .or_else(|| {
+                tz.from_local_datetime(&date.and_hms_opt(1, 0, 0)?)
+                    .earliest()
+            })?;
+        Some(start_of_day.offset().fix().local_minus_utc())
+    };
+
+    let change = offset_at(date.succ_opt()?)? - offset_at(date)?;
+    if change == 0 {
+        None
+    } else {
+        Some(Duration::seconds(change as i64))
+    }
+}
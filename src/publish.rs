@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use crate::config::PublishSpec;
+use crate::provider::ical::import::file_stem_for_uid;
+use crate::provider::Eventlike;
+use crate::state::{Migration, StateFile};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+fn publish_error(msg: String) -> Box<dyn std::error::Error> {
+    Box::new(io::Error::other(msg))
+}
+
+/// ETags of published resources by URL so that we only ever overwrite the
+/// version we uploaded ourselves
+struct EtagStore {
+    file: StateFile,
+    etags: BTreeMap<String, String>,
+}
+
+/// Version 1 only added the version header
+const ETAG_MIGRATIONS: &[Migration] = &[Ok];
+
+impl EtagStore {
+    fn load() -> Result<Self> {
+        let file = StateFile::new("published.toml", ETAG_MIGRATIONS)?;
+        let etags = file.load()?;
+        Ok(EtagStore { file, etags })
+    }
+
+    fn save(&self) -> Result<()> {
+        self.file.save(&self.etags)
+    }
+}
+
+struct Response {
+    status: u32,
+    etag: Option<String>,
+}
+
+fn parse_response(headers: &str) -> Option<Response> {
+    // Only the last header block is relevant if there were redirects
+    let block = headers
+        .rsplit("\r\n\r\n")
+        .find(|block| block.starts_with("HTTP/"))?;
+    let mut lines = block.lines();
+
+    let status = lines.next()?.split_whitespace().nth(1)?.parse().ok()?;
+    let etag = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("etag"))
+        .map(|(_, value)| value.trim().to_owned());
+
+    Some(Response { status, etag })
+}
+
+/// The curl config line with the user and password of `spec`, if any. The
+/// password is resolved here so that a failing lookup stops publishing
+/// before any request is made.
+fn curl_credentials(spec: &PublishSpec) -> Result<Option<String>> {
+    let username = match &spec.username {
+        Some(username) => username,
+        None => return Ok(None),
+    };
+    let password = match &spec.password {
+        Some(secret) => secret.resolve()?,
+        None => String::new(),
+    };
+    let credentials = format!("{}:{}", username, password);
+    Ok(Some(format!(
+        "user = \"{}\"",
+        credentials.replace('\\', "\\\\").replace('"', "\\\"")
+    )))
+}
+
+fn curl(credentials: Option<&str>, args: &[&str]) -> Result<Response> {
+    let mut child = Command::new("curl")
+        .args(["-sS", "-o", "/dev/null", "-D", "-", "-K", "-"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| publish_error(format!("could not run curl: {}", err)))?;
+
+    // Pass credentials via stdin so that they do not show up in the process list
+    if let (Some(credentials), Some(mut stdin)) = (credentials, child.stdin.take()) {
+        writeln!(stdin, "{}", credentials)?;
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(publish_error(format!(
+            "curl failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    parse_response(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| publish_error("could not parse server response".to_owned()))
+}
+
+fn resource_url(spec: &PublishSpec, event: &dyn Eventlike) -> String {
+    format!(
+        "{}/{}.ics",
+        spec.url.trim_end_matches('/'),
+        file_stem_for_uid(event.uid())
+    )
+}
+
+/// Uploads `event` to the configured WebDAV collection.
+///
+/// The first upload only succeeds if the resource does not exist yet, later
+/// uploads only if the resource still has the ETag we got the last time.
+/// Returns the URL of the published resource.
+pub fn publish(spec: &PublishSpec, event: &dyn Eventlike) -> Result<String> {
+    let url = resource_url(spec, event);
+    let mut store = EtagStore::load()?;
+    let credentials = curl_credentials(spec)?;
+
+    let precondition = match store.etags.get(&url) {
+        Some(etag) => format!("If-Match: {}", etag),
+        None => "If-None-Match: *".to_owned(),
+    };
+    let data = format!("@{}", event.path().display());
+
+    let response = curl(
+        credentials.as_deref(),
+        &[
+            "-X",
+            "PUT",
+            "-H",
+            "Content-Type: text/calendar; charset=utf-8",
+            "-H",
+            &precondition,
+            "-H",
+            "Expect:",
+            "--data-binary",
+            &data,
+            &url,
+        ],
+    )?;
+
+    match response.status {
+        200..=299 => {}
+        412 if store.etags.contains_key(&url) => {
+            return Err(publish_error(format!(
+                "{} was modified on the server since it was last published",
+                url
+            )))
+        }
+        412 => {
+            return Err(publish_error(format!(
+                "{} already exists on the server",
+                url
+            )))
+        }
+        status => {
+            return Err(publish_error(format!(
+                "server responded with status {} for {}",
+                status, url
+            )))
+        }
+    }
+
+    // Servers may omit the ETag if they altered the uploaded data
+    let etag = match response.etag {
+        Some(etag) => Some(etag),
+        None => curl(credentials.as_deref(), &["-I", &url])?.etag,
+    };
+
+    match etag {
+        Some(etag) => {
+            store.etags.insert(url.clone(), etag);
+        }
+        None => {
+            log::warn!("No ETag for {}, it can not be published again", url);
+            store.etags.remove(&url);
+        }
+    }
+    store.save()?;
+
+    Ok(url)
+}
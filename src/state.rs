@@ -0,0 +1,144 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Upgrades the data of a state file by one version
+pub type Migration = fn(toml::Value) -> Result<toml::Value>;
+
+fn state_error(msg: String) -> Box<dyn std::error::Error> {
+    Box::new(io::Error::other(msg))
+}
+
+/// Where jackal keeps state of its own, e.g. notes on events. This is the
+/// XDG state directory where there is one and the data directory otherwise.
+pub fn state_dir() -> Result<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_dir)
+        .map(|dir| dir.join("jackal"))
+        .ok_or_else(|| state_error("could not determine state directory".to_owned()))
+}
+
+/// On-disk layout of state files, the version has to precede the data table
+#[derive(Serialize)]
+struct Document {
+    version: i64,
+    data: toml::Value,
+}
+
+/// A file in the state directory holding data together with the version of
+/// its format. Files of older versions are upgraded by `migrations` when
+/// they are loaded, `migrations[i]` turning version `i` into `i + 1`.
+/// Version 0 are the unversioned files of earlier releases, which were kept
+/// in the data directory.
+pub struct StateFile {
+    path: PathBuf,
+    legacy_path: Option<PathBuf>,
+    migrations: &'static [Migration],
+}
+
+impl StateFile {
+    pub fn new(name: &str, migrations: &'static [Migration]) -> Result<Self> {
+        Ok(StateFile {
+            path: state_dir()?.join(name),
+            legacy_path: dirs::data_dir().map(|dir| dir.join("jackal").join(name)),
+            migrations,
+        })
+    }
+
+    fn current_version(&self) -> i64 {
+        self.migrations.len() as i64
+    }
+
+    /// The version and data of `content`
+    fn parse(content: &str) -> Result<(i64, toml::Value)> {
+        let document: toml::value::Table = toml::from_str(content)?;
+
+        let version = document.get("version").and_then(toml::Value::as_integer);
+        match (version, document.get("data")) {
+            (Some(version), Some(data)) if data.is_table() && document.len() == 2 => {
+                Ok((version, data.clone()))
+            }
+            _ => Ok((0, toml::Value::Table(document))),
+        }
+    }
+
+    /// Loads the data, upgrading it to the current version if necessary. A
+    /// missing file is no error but yields the default.
+    pub fn load<T: DeserializeOwned + Default>(&self) -> Result<T> {
+        let (content, path) = match fs::read_to_string(&self.path) {
+            Ok(content) => (content, &self.path),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                match self
+                    .legacy_path
+                    .as_ref()
+                    .map(|p| (fs::read_to_string(p), p))
+                {
+                    Some((Ok(content), path)) => (content, path),
+                    Some((Err(err), _)) if err.kind() != io::ErrorKind::NotFound => {
+                        return Err(Box::new(err))
+                    }
+                    _ => return Ok(T::default()),
+                }
+            }
+            Err(err) => return Err(Box::new(err)),
+        };
+
+        let (version, mut data) = Self::parse(&content)
+            .map_err(|e| state_error(format!("could not read {}: {}", path.display(), e)))?;
+        if version > self.current_version() || version < 0 {
+            // Better keep it than lose what the newer version stored
+            return Err(state_error(format!(
+                "{} was written by a newer version of jackal",
+                path.display()
+            )));
+        }
+
+        if version < self.current_version() {
+            for migration in &self.migrations[version as usize..] {
+                data = migration(data)?;
+            }
+            // Keep the old file in case the upgrade loses anything
+            let backup = self.path.with_extension(format!("v{}.bak", version));
+            if let Some(dir) = backup.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::copy(path, &backup)?;
+            self.write(data.clone())?;
+            log::info!(
+                "Upgraded {} to version {}, the old file is kept as {}",
+                path.display(),
+                self.current_version(),
+                backup.display()
+            );
+        }
+
+        Ok(data.try_into()?)
+    }
+
+    pub fn save<T: Serialize>(&self, data: &T) -> Result<()> {
+        self.write(toml::Value::try_from(data)?)
+    }
+
+    /// Replaces the file atomically, so that it is either the old or the new
+    /// version if jackal is interrupted
+    fn write(&self, data: toml::Value) -> Result<()> {
+        let content = toml::to_string(&Document {
+            version: self.current_version(),
+            data,
+        })?;
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temporary = self.path.with_extension("tmp");
+        let mut file = fs::File::create(&temporary)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temporary, &self.path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use crate::agenda::Agenda;
+use crate::config::SyncSpec;
+use crate::events::Event;
+use crate::state;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const SOCKET_EXTENSION: &str = "sock";
+
+fn sync_error(msg: String) -> Box<dyn std::error::Error> {
+    Box::new(io::Error::other(msg))
+}
+
+/// Where running instances listen for changes made by `jk sync`, the XDG
+/// runtime directory where there is one
+fn socket_dir() -> Result<PathBuf> {
+    match dirs::runtime_dir() {
+        Some(dir) => Ok(dir.join("jackal")),
+        None => Ok(state::state_dir()?.join("instances")),
+    }
+}
+
+/// The socket of a running instance, removed when the instance exits
+pub struct SyncListener {
+    path: PathBuf,
+}
+
+impl Drop for SyncListener {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            log::warn!("Could not remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Listens for `jk sync` telling that the calendars changed and passes that
+/// on as `Event::ExternalModification`
+pub fn listen(tx: mpsc::Sender<Event>) -> Result<SyncListener> {
+    let dir = socket_dir()?;
+    fs::create_dir_all(&dir)?;
+    let path = dir
+        .join(std::process::id().to_string())
+        .with_extension(SOCKET_EXTENSION);
+    // Left behind by an instance with the same pid that crashed
+    if path.exists() {
+        fs::remove_file(&path)?;
+    }
+
+    let listener = UnixListener::bind(&path)?;
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            // The connection itself is the message
+            if stream.is_ok() && tx.send(Event::ExternalModification).is_err() {
+                return;
+            }
+        }
+    });
+
+    Ok(SyncListener { path })
+}
+
+/// Tells all running instances that the calendars changed, returns their
+/// number. Sockets of instances that are gone are removed.
+pub fn notify_instances() -> Result<usize> {
+    let dir = socket_dir()?;
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(Box::new(err)),
+    };
+
+    let mut notified = 0;
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(SOCKET_EXTENSION) {
+            continue;
+        }
+        match UnixStream::connect(&path) {
+            Ok(mut stream) => match stream.write_all(b"modified\n") {
+                Ok(()) => notified += 1,
+                Err(err) => log::warn!("Could not notify {}: {}", path.display(), err),
+            },
+            Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => {
+                log::info!("Removing stale socket {}", path.display());
+                fs::remove_file(&path)?;
+            }
+            Err(err) => log::warn!("Could not notify {}: {}", path.display(), err),
+        }
+    }
+    Ok(notified)
+}
+
+/// Runs the sync command once. Other instances do not watch the calendars
+/// while it runs and are notified afterwards, so that they do not pick up
+/// half of the changes.
+pub fn sync(agenda: &Agenda, spec: &SyncSpec) -> Result<()> {
+    {
+        let _suspension = agenda.suspend_watching()?;
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(&spec.command)
+            .status()
+            .map_err(|err| sync_error(format!("could not run '{}': {}", spec.command, err)))?;
+        if !status.success() {
+            return Err(sync_error(format!("'{}' failed: {}", spec.command, status)));
+        }
+    }
+
+    let notified = notify_instances()?;
+    log::info!("Synced, notified {} running instance(s)", notified);
+    Ok(())
+}
+
+/// Syncs every `interval_minutes` until killed. Failed runs are logged and
+/// retried at the next interval.
+pub fn run_daemon(agenda: &Agenda, spec: &SyncSpec) -> Result<()> {
+    let interval = Duration::from_secs(spec.interval_minutes.max(1) as u64 * 60);
+    loop {
+        if let Err(e) = sync(agenda, spec) {
+            log::error!("{}", e);
+        }
+        thread::sleep(interval);
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const SLOW_THRESHOLD: Duration = Duration::from_millis(50);
+
+static REPORT_SLOW: AtomicBool = AtomicBool::new(false);
+static SLOW_SPANS: Mutex<Vec<(&'static str, String, Duration)>> = Mutex::new(Vec::new());
+
+/// Measures the time between its creation and drop and logs it under the
+/// `timing` target. Spans exceeding `SLOW_THRESHOLD` are additionally
+/// collected for `report` if enabled via `enable_report`.
+pub struct Span {
+    name: &'static str,
+    detail: String,
+    start: Instant,
+}
+
+pub fn span<D: Display>(name: &'static str, detail: D) -> Span {
+    Span {
+        name,
+        detail: detail.to_string(),
+        start: Instant::now(),
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+
+        log::debug!(
+            target: "timing",
+            "span={} detail='{}' elapsed_ms={}",
+            self.name,
+            self.detail,
+            elapsed.as_millis()
+        );
+
+        if elapsed >= SLOW_THRESHOLD && REPORT_SLOW.load(Ordering::Relaxed) {
+            if let Ok(mut spans) = SLOW_SPANS.lock() {
+                spans.push((self.name, std::mem::take(&mut self.detail), elapsed));
+            }
+        }
+    }
+}
+
+pub fn enable_report() {
+    REPORT_SLOW.store(true, Ordering::Relaxed);
+}
+
+/// Prints all collected slow spans to stderr
+pub fn report() {
+    if !REPORT_SLOW.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let spans = match SLOW_SPANS.lock() {
+        Ok(spans) => spans,
+        Err(_) => return,
+    };
+
+    eprintln!(
+        "{} operation(s) took longer than {} ms",
+        spans.len(),
+        SLOW_THRESHOLD.as_millis()
+    );
+    for (name, detail, elapsed) in spans.iter() {
+        eprintln!("{:>8} ms  {} {}", elapsed.as_millis(), name, detail);
+    }
+}
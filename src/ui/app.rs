@@ -1,10 +1,16 @@
+use chrono::{Local, NaiveDate};
 use std::pin::Pin;
 
 use crate::agenda::Agenda;
 use crate::config::Config;
 use crate::events::{Dispatcher, Event};
+use crate::timing;
 
-use super::{CalendarWindow, Context, EventWindow, EventWindowBehaviour, Mode, MonthPane};
+use super::{
+    insert_preview, quick_add_preview, CalendarWindow, ConsoleWindow, Context, CountdownWindow,
+    EventWindow, EventWindowBehaviour, InsertParser, MenuBehaviour, MenuWindow, Mode,
+    PickerBehaviour, PickerWindow, QuickAddBehaviour, SearchBehaviour,
+};
 
 use unsegen::base::{Cursor, GraphemeCluster, Terminal};
 use unsegen::input::{
@@ -21,8 +27,27 @@ pub struct App<'a> {
 
 impl<'a> App<'a> {
     pub fn new(config: &'a Config, agenda: Agenda) -> App<'a> {
-        let context = Context::new(agenda);
-        App { config, context }
+        App {
+            config,
+            context: Context::from_config(agenda, config),
+        }
+    }
+
+    /// Starts with the cursor on `date` instead of today
+    pub fn go_to_date(&mut self, date: NaiveDate) {
+        self.context.select_date(date);
+    }
+
+    /// Starts with the cursor on the next occurrence of the event with `uid`
+    pub fn go_to_event(&mut self, uid: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let begin = self
+            .context
+            .agenda()
+            .next_occurrence(uid, self.context.now())
+            .map(|event| event.occurrence().clone().with_tz(&Local {}).begin())
+            .ok_or_else(|| format!("No event with UID {}", uid))?;
+        self.context.jump_to_event(uid, begin);
+        Ok(())
     }
 
     fn bottom_bar<'w>(&'w self) -> impl Widget + 'w {
@@ -34,10 +59,37 @@ impl<'a> App<'a> {
         let mut layout = HLayout::new()
             .separator(GraphemeCluster::try_from(' ').unwrap())
             .widget(spacer);
-        if let mode @ (Mode::Command | Mode::Insert) = self.context.mode {
+        if let mode
+        @ (Mode::Command | Mode::Insert | Mode::QuickAdd | Mode::Picker | Mode::Search) =
+            self.context.mode
+        {
             layout = layout.widget(self.context.input_sink(mode).as_widget());
         }
 
+        let preview = match self.context.mode {
+            Mode::Insert => insert_preview(&self.context),
+            Mode::QuickAdd => quick_add_preview(&self.context),
+            _ => None,
+        };
+
+        if let Some(preview) = preview {
+            layout = layout.widget(preview);
+        } else if let (Mode::Normal | Mode::Insert | Mode::QuickAdd | Mode::Confirm, Some(msg)) =
+            (self.context.mode, &self.context.last_error_message)
+        {
+            layout = layout.widget(msg.as_str());
+        } else if let (Mode::Normal, Some(days)) = (self.context.mode, self.context.selected_days())
+        {
+            layout = layout.widget(format!(
+                "Range {} to {} ({} days)",
+                days.start().format("%Y-%m-%d"),
+                days.end().format("%Y-%m-%d"),
+                (*days.end() - *days.start()).num_days() + 1
+            ));
+        } else if self.context.mode == Mode::Normal {
+            layout = layout.widget(self.context.calendar_ages.as_str());
+        }
+
         layout
     }
 
@@ -45,15 +97,26 @@ impl<'a> App<'a> {
     where
         'a: 'w,
     {
-        let mut layout = VLayout::new()
-            .widget(
-                HLayout::new()
-                    .widget(CalendarWindow::new(&self.context))
-                    .widget(EventWindow::new(&self.context)),
-            )
-            .widget(self.bottom_bar());
+        let mut layout = VLayout::new();
+        if self.context.mode == Mode::Picker {
+            layout = layout.widget(PickerWindow::new(&self.context));
+        } else {
+            layout = layout.widget(MainPanes(&self.context));
+        }
 
-        layout
+        if self.context.mode == Mode::Menu {
+            layout = layout.widget(MenuWindow::new(&self.context));
+        }
+
+        if self.context.show_countdown {
+            layout = layout.widget(CountdownWindow::new(&self.context));
+        }
+
+        if self.context.show_console {
+            layout = layout.widget(ConsoleWindow::new(&self.context));
+        }
+
+        layout.widget(self.bottom_bar())
     }
 
     pub fn run(
@@ -67,7 +130,8 @@ impl<'a> App<'a> {
             // Handle events
             if let Ok(event) = dispatcher.next() {
                 match event {
-                    Event::Update => self.context.update(),
+                    Event::Update | Event::ExternalModification => self.context.update(),
+                    Event::Resize => {}
                     Event::Input(input) => {
                         let num_events_of_current_day = self
                             .context
@@ -76,7 +140,12 @@ impl<'a> App<'a> {
                             .count();
 
                         if input.matches(Key::Esc) {
+                            if self.context.mode == Mode::Confirm {
+                                self.context.confirm(false);
+                            }
                             self.context.mode = Mode::Normal;
+                            self.context.selection_anchor = None;
+                            self.context.day_range_anchor = None;
                         } else {
                             match self.context.mode {
                                 Mode::Normal => {
@@ -85,9 +154,70 @@ impl<'a> App<'a> {
                                         .chain((Key::Char(':'), || {
                                             self.context.mode = Mode::Command
                                         }))
+                                        .chain((Key::Char('/'), || {
+                                            self.context.mode = Mode::Search
+                                        }))
+                                        .chain((Key::Char('n'), || self.context.search_next(true)))
+                                        .chain((Key::Char('N'), || self.context.search_next(false)))
                                         .chain((Key::Char('i'), || {
                                             self.context.mode = Mode::Insert
                                         }))
+                                        .chain((Key::Char('a'), || {
+                                            self.context.mode = Mode::QuickAdd
+                                        }))
+                                        .chain((Key::Ctrl('p'), || {
+                                            self.context.picker_index = 0;
+                                            self.context.picker_days = None;
+                                            self.context.mode = Mode::Picker
+                                        }))
+                                        .chain((Key::Char('m'), || self.context.open_menu()))
+                                        .chain((Key::Char('v'), || self.context.toggle_selection()))
+                                        .chain((Key::Char('V'), || self.context.toggle_day_range()))
+                                        .chain((Key::Char('u'), || self.context.undo()))
+                                        .chain((Key::Char('p'), || self.context.toggle_pin()))
+                                        .chain((Key::Char('d'), || self.context.request_delete()))
+                                        .chain((Key::Char('x'), || self.context.request_delete()))
+                                        .chain((Key::Char('z'), || {
+                                            self.context.cycle_density(self.config)
+                                        }))
+                                        .chain((Key::Char('}'), || {
+                                            self.context.change_lookahead(1, self.config)
+                                        }))
+                                        .chain((Key::Char('{'), || {
+                                            self.context.change_lookahead(-1, self.config)
+                                        }))
+                                        .chain((Key::Char('!'), || {
+                                            self.context.show_console = !self.context.show_console
+                                        }))
+                                        .chain((Key::Char('+'), || {
+                                            self.context
+                                                .shift_selection(chrono::Duration::minutes(15))
+                                        }))
+                                        .chain((Key::Char('-'), || {
+                                            self.context
+                                                .shift_selection(chrono::Duration::minutes(-15))
+                                        }))
+                                        .chain((Key::Char('J'), || {
+                                            self.context.shift_selection(chrono::Duration::hours(1))
+                                        }))
+                                        .chain((Key::Char('K'), || {
+                                            self.context
+                                                .shift_selection(chrono::Duration::hours(-1))
+                                        }))
+                                        .chain((Key::Char('>'), || {
+                                            let step = self.context.resize_step;
+                                            self.context.resize_selection(step)
+                                        }))
+                                        .chain((Key::Char('<'), || {
+                                            let step = self.context.resize_step;
+                                            self.context.resize_selection(-step)
+                                        }))
+                                        .chain((Key::Char('L'), || {
+                                            self.context.shift_selection(chrono::Duration::days(1))
+                                        }))
+                                        .chain((Key::Char('H'), || {
+                                            self.context.shift_selection(chrono::Duration::days(-1))
+                                        }))
                                         .chain(
                                             NavigateBehavior::new(&mut CursorBehaviour(
                                                 &mut self.context,
@@ -107,7 +237,47 @@ impl<'a> App<'a> {
                                         )
                                         .finish();
                                 }
-                                Mode::Insert => {}
+                                Mode::Confirm => {
+                                    // Anything but y cancels
+                                    self.context.confirm(input.matches(Key::Char('y')));
+                                }
+                                Mode::Menu => {
+                                    input.chain(MenuBehaviour(&mut self.context)).finish();
+                                }
+                                mode @ Mode::Insert => {
+                                    input
+                                        .chain(
+                                            EditBehavior::new(self.context.input_sink_mut(mode))
+                                                .delete_forwards_on(Key::Delete)
+                                                .delete_backwards_on(Key::Backspace)
+                                                .left_on(Key::Left)
+                                                .right_on(Key::Right),
+                                        )
+                                        .chain(
+                                            ScrollBehavior::new(self.context.input_sink_mut(mode))
+                                                .backwards_on(Key::Up)
+                                                .forwards_on(Key::Down),
+                                        )
+                                        .chain(InsertParser::new(&mut self.context, self.config))
+                                        .finish();
+                                }
+                                mode @ Mode::QuickAdd => {
+                                    input
+                                        .chain(
+                                            EditBehavior::new(self.context.input_sink_mut(mode))
+                                                .delete_forwards_on(Key::Delete)
+                                                .delete_backwards_on(Key::Backspace)
+                                                .left_on(Key::Left)
+                                                .right_on(Key::Right),
+                                        )
+                                        .chain(
+                                            ScrollBehavior::new(self.context.input_sink_mut(mode))
+                                                .backwards_on(Key::Up)
+                                                .forwards_on(Key::Down),
+                                        )
+                                        .chain(QuickAddBehaviour(&mut self.context))
+                                        .finish();
+                                }
                                 mode @ Mode::Command => {
                                     input
                                         .chain(
@@ -125,13 +295,61 @@ impl<'a> App<'a> {
                                         .chain(CommandParser::new(&mut self.context, &self.config))
                                         .finish();
                                 }
+                                mode @ Mode::Search => {
+                                    input
+                                        .chain(
+                                            EditBehavior::new(self.context.input_sink_mut(mode))
+                                                .delete_forwards_on(Key::Delete)
+                                                .delete_backwards_on(Key::Backspace)
+                                                .left_on(Key::Left)
+                                                .right_on(Key::Right),
+                                        )
+                                        .chain(
+                                            ScrollBehavior::new(self.context.input_sink_mut(mode))
+                                                .backwards_on(Key::Up)
+                                                .forwards_on(Key::Down),
+                                        )
+                                        .chain(SearchBehaviour(&mut self.context))
+                                        .finish();
+                                }
+                                mode @ Mode::Picker => {
+                                    let query =
+                                        self.context.input_sink(mode).active_line().to_owned();
+                                    input
+                                        .chain(PickerBehaviour(&mut self.context))
+                                        .chain(
+                                            EditBehavior::new(self.context.input_sink_mut(mode))
+                                                .delete_forwards_on(Key::Delete)
+                                                .delete_backwards_on(Key::Backspace)
+                                                .left_on(Key::Left)
+                                                .right_on(Key::Right),
+                                        )
+                                        .finish();
+                                    // Other matches, so start again at the best one
+                                    if self.context.input_sink(mode).active_line() != query {
+                                        self.context.picker_index = 0;
+                                    }
+                                }
                             }
                         }
                     }
                 }
             }
 
+            if let Some(uid) = self.context.pending_edit.take() {
+                let _pause = dispatcher.pause_input();
+                let result = term.on_main_screen(|| crate::cli::edit(self.context.agenda(), &uid));
+                self.context.last_error_message = match result {
+                    Ok(Ok(())) => None,
+                    Ok(Err(e)) => Some(e.to_string()),
+                    Err(e) => Some(e.to_string()),
+                };
+                self.context.update();
+            }
+
             // Draw
+            let _span = timing::span("draw", "");
+            self.context.eventlist_rows = EventWindow::new(&self.context).content_rows();
             let mut root = term.create_root_window();
 
             let mut layout = self.as_widget().draw(root, RenderingHints::new());
@@ -143,6 +361,34 @@ impl<'a> App<'a> {
     }
 }
 
+/// Calendar and event list side by side or, if the terminal is too narrow
+/// for that, on top of each other
+struct MainPanes<'a>(&'a Context);
+
+impl Widget for MainPanes<'_> {
+    fn space_demand(&self) -> Demand2D {
+        Demand2D {
+            width: ColDemand::at_least(1),
+            height: RowDemand::at_least(1),
+        }
+    }
+
+    fn draw(&self, window: unsegen::base::Window, hints: RenderingHints) {
+        let side_by_side = HLayout::new()
+            .widget(CalendarWindow::new(self.0))
+            .widget(EventWindow::new(self.0));
+
+        if side_by_side.space_demand().width.min <= window.get_width() {
+            side_by_side.draw(window, hints);
+        } else {
+            VLayout::new()
+                .widget(CalendarWindow::new(self.0))
+                .widget(EventWindow::new(self.0))
+                .draw(window, hints);
+        }
+    }
+}
+
 struct CursorBehaviour<'a>(&'a mut Context);
 
 impl Navigatable for CursorBehaviour<'_> {
@@ -1,5 +1,6 @@
 use crate::provider::ical::days_of_month;
-use chrono::{Datelike, Local, Month, NaiveDate};
+use crate::provider::tz;
+use chrono::{Datelike, Local, Month, NaiveDate, Weekday};
 use num_traits::FromPrimitive;
 use std::fmt::Display;
 use std::fmt::Write;
@@ -13,6 +14,7 @@ pub struct DayCell<'a> {
     day_num: u8,
     selected: bool,
     is_today: bool,
+    continued: bool,
     theme: &'a Theme,
 }
 
@@ -25,6 +27,7 @@ impl<'a> DayCell<'a> {
             day_num,
             selected: false,
             is_today: false,
+            continued: false,
             theme,
         }
     }
@@ -46,20 +49,35 @@ impl<'a> DayCell<'a> {
         self.set_today(is_today);
         self
     }
+
+    fn set_continued(&mut self, continued: bool) {
+        self.continued = continued;
+    }
+
+    /// Connects the cell to the one before, for events lasting several days
+    fn continued(mut self, continued: bool) -> Self {
+        self.set_continued(continued);
+        self
+    }
 }
 
 impl Display for DayCell<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let blank = match self.theme.continued_day_char {
+            Some(c) if self.continued => c,
+            _ => ' ',
+        };
+
         let arg_today = if self.is_today {
-            self.theme.today_day_char.unwrap_or(' ')
+            self.theme.today_day_char.unwrap_or(blank)
         } else {
-            ' '
+            blank
         };
 
         let arg_focus = if self.selected {
-            self.theme.focus_day_char.unwrap_or(' ')
+            self.theme.focus_day_char.unwrap_or(blank)
         } else {
-            ' '
+            blank
         };
 
         write!(f, "{}{}{:>2}", arg_today, arg_focus, self.day_num)
@@ -80,8 +98,6 @@ impl<'a> MonthPane<'a> {
     const ROWS: usize = 6;
     const HEADER_ROWS: usize = 2;
 
-    const HEADER: &'static [&'static str] = &["Mon", "Tue", "Wen", "Thu", "Fri", "Sat", "Sun"];
-
     const WIDTH: usize = Self::COLUMNS * DayCell::CELL_WIDTH;
     const HEIGHT: usize = (Self::ROWS + Self::HEADER_ROWS) * DayCell::CELL_HEIGHT;
 
@@ -125,13 +141,21 @@ impl Widget for MonthPane<'_> {
             );
 
         // print Header first
-        writeln!(&mut cursor, "{} {}", &self.month.name(), self.year).unwrap();
-
-        for &head in Self::HEADER {
+        let locale = &self.context.locale;
+        writeln!(
+            &mut cursor,
+            "{} {}",
+            locale.month_name(self.month),
+            self.year
+        )
+        .unwrap();
+
+        let monday = NaiveDate::from_isoywd_opt(self.year, 1, Weekday::Mon).unwrap();
+        for day in monday.iter_days().take(Self::COLUMNS) {
             write!(
                 &mut cursor,
                 "{:>width$}",
-                &head,
+                locale.weekday_abbr(day.weekday()),
                 width = DayCell::CELL_WIDTH
             )
             .unwrap();
@@ -150,15 +174,66 @@ impl Widget for MonthPane<'_> {
         let is_selected_month = (self.context.cursor().month() == self.month.number_from_month())
             && (self.context.cursor().year() == self.year);
 
+        let summary = self.context.agenda().month_summary(self.month, self.year);
+
         for (idx, cell) in (1..=self.num_days).map(|idx| (idx, DayCell::new(idx, &theme))) {
             let is_today = is_current_month && (idx as u32 == self.context.now().day());
             let is_selected = is_selected_month && (idx as u32 == self.context.cursor().day());
+            let date =
+                NaiveDate::from_ymd_opt(self.year, self.month.number_from_month(), idx as u32);
+            let in_range = date
+                .zip(self.context.selected_days())
+                .is_some_and(|(date, days)| days.contains(&date));
+
+            let has_dst_change = date
+                .and_then(|date| tz::offset_change_on(&Local, date))
+                .is_some();
+
+            let day = summary.get(idx as usize - 1);
+            let has_events = day.is_some_and(|day| day.events > 0);
+            let is_continued = day.is_some_and(|day| day.continued);
+            let is_overbooked = day
+                .zip(self.context.overbooked_after)
+                .is_some_and(|(day, limit)| day.busy > limit);
+            let category_color = day
+                .and_then(|day| self.context.category_spec(&day.categories))
+                .and_then(|spec| spec.color.as_deref())
+                .and_then(|color| self.context.color(color));
+
+            let is_marked = date.is_some_and(|date| self.context.day_marks(date).is_some());
+
+            let saved_style =
+                if is_today || is_selected || in_range || has_dst_change || has_events || is_marked
+                {
+                    Some(cursor.get_style_modifier())
+                } else {
+                    None
+                };
+
+            if has_events {
+                cursor.apply_style_modifier(theme.busy_day_style);
+            }
 
-            let saved_style = if is_today || is_selected {
-                Some(cursor.get_style_modifier())
-            } else {
-                None
-            };
+            if let Some(color) = category_color {
+                cursor.apply_style_modifier(StyleModifier::new().fg_color(color));
+            }
+
+            if is_marked {
+                cursor.apply_style_modifier(theme.marked_day_style);
+            }
+
+            // The warning wins over the category's color
+            if is_overbooked {
+                cursor.apply_style_modifier(theme.overbooked_day_style);
+            }
+
+            if has_dst_change {
+                cursor.apply_style_modifier(theme.dst_change_style);
+            }
+
+            if in_range {
+                cursor.apply_style_modifier(theme.range_day_style);
+            }
 
             if is_today {
                 cursor
@@ -170,7 +245,14 @@ impl Widget for MonthPane<'_> {
                     .apply_style_modifier(theme.focus_day_style.format(theme.focus_day_text_style));
             }
 
-            write!(&mut cursor, "{}", cell.select(is_selected).today(is_today)).unwrap();
+            write!(
+                &mut cursor,
+                "{}",
+                cell.select(is_selected)
+                    .today(is_today)
+                    .continued(is_continued)
+            )
+            .unwrap();
 
             if let Some(style) = saved_style {
                 cursor.set_style_modifier(style);
@@ -313,18 +395,19 @@ impl Widget for CalendarWindow<'_> {
 
     fn draw(&self, mut window: Window, hints: RenderingHints) {
         // Calculate number of fitting month panes and prepare
-        // subwindows accordingly
-        let num_fitting_months = window.get_height() / MonthPane::HEIGHT;
+        // subwindows accordingly. Show at least the cursor's month even if
+        // it has to be cut off.
+        let num_fitting_months =
+            (window.get_height().raw_value() / MonthPane::HEIGHT as i32).max(1);
 
-        let offset: MonthIndex = MonthIndex::from(self.context.cursor.clone())
-            - (num_fitting_months.raw_value() / 2) as u32;
+        let offset: MonthIndex =
+            MonthIndex::from(self.context.cursor) - (num_fitting_months / 2) as u32;
 
-        let (subwindow_x, subwindow_y) = (
-            (window.get_width().raw_value() - MonthPane::WIDTH as i32) / 2,
-            0,
-        );
+        let width = window.get_width().raw_value();
+        let (subwindow_x, subwindow_y) = (((width - MonthPane::WIDTH as i32) / 2).max(0), 0);
         let pane = window.create_subwindow(
-            ColIndex::new(subwindow_x)..ColIndex::new(subwindow_x + MonthPane::WIDTH as i32),
+            ColIndex::new(subwindow_x)
+                ..ColIndex::new((subwindow_x + MonthPane::WIDTH as i32).min(width)),
             RowIndex::new(subwindow_y)..RowIndex::new(window.get_height().raw_value()),
         );
 
@@ -333,7 +416,7 @@ impl Widget for CalendarWindow<'_> {
         //
         let mut layout = VLayout::new();
 
-        for i in 0..num_fitting_months.raw_value() {
+        for i in 0..num_fitting_months {
             layout = layout.widget(MonthPane::from_month_index(
                 offset + i as u32,
                 &self.context,
@@ -1,4 +1,8 @@
 use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
 use std::result::Result;
 use std::str::FromStr;
 use unsegen::input::*;
@@ -17,7 +21,11 @@ use nom::{
 };
 
 use super::context::{Context, Mode};
+use crate::agenda::EventOperation;
 use crate::config::Config;
+use crate::provider::ical::calendar::IcalDuration;
+use crate::provider::ical::itip::{self, PartStat};
+use crate::provider::{Alarm, AlarmRelation, CalendarEvent, EventChange, EventId};
 
 pub struct CommandParser<'a> {
     context: &'a mut Context,
@@ -31,10 +39,16 @@ where
     &'a T: IntoIterator<Item = &'s (&'s str, Act)>,
 {
     move |input| {
-        if let Some((name, act)) = c.into_iter().find(|(name, _)| name == &input) {
-            Ok(("", (name, act)))
+        // Prefer the longest matching action name so that actions sharing a
+        // common prefix can not shadow each other
+        if let Some((name, act)) = c
+            .into_iter()
+            .filter(|(name, _)| input.starts_with(name))
+            .max_by_key(|(name, _)| name.len())
+        {
+            Ok((&input[name.len()..], (name, act)))
         } else {
-            Err(Err::Failure(ParseError::from_error_kind(
+            Err(Err::Error(ParseError::from_error_kind(
                 input,
                 ErrorKind::Tag,
             )))
@@ -103,7 +117,8 @@ impl Behavior for CommandParser<'_> {
                         .to_owned();
                     if let Err(e) = self.run_command(&cmd) {
                         self.report_error(e);
-                    } else {
+                    } else if self.context.mode == Mode::Command {
+                        // Unless the command switched to another mode
                         self.context.mode = Mode::Normal;
                     }
                     None
@@ -124,7 +139,374 @@ pub enum Action {
     Repeatable(fn(&mut Context, u32) -> ActionResult),
 }
 
+fn calendar_command(c: &mut Context, arg: String) -> ActionResult {
+    let fail = |msg: String| ParseError::from_error_kind(msg, ErrorKind::Fail);
+
+    let (subcommand, rest) = arg.split_once(' ').unwrap_or((&arg, ""));
+    let (id, value) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+    let value = value.trim();
+
+    if let "hide" | "show" = subcommand {
+        if id.is_empty() {
+            return Err(fail(format!("usage: calendar {} <id>", subcommand)));
+        }
+        return c
+            .set_calendar_hidden(id, subcommand == "hide")
+            .map_err(|e| fail(e.to_string()));
+    }
+
+    if id.is_empty() || value.is_empty() {
+        return Err(fail(format!("usage: calendar {} <id> <value>", subcommand)));
+    }
+
+    let calendar = c
+        .agenda_mut()
+        .calendar_by_id_mut(id)
+        .ok_or_else(|| fail(format!("no calendar with id '{}'", id)))?;
+
+    match subcommand {
+        "rename" => calendar.rename(value),
+        "set-color" => calendar.set_color(value),
+        _ => return Err(ParseError::from_error_kind(arg, ErrorKind::Tag)),
+    }
+    .map_err(|e| fail(e.to_string()))
+}
+
+fn publish_command(c: &mut Context) -> ActionResult {
+    let fail = |msg: String| ParseError::from_error_kind(msg, ErrorKind::Fail);
+
+    let spec = c
+        .publish_target
+        .as_ref()
+        .ok_or_else(|| fail("no [publish] section in config".to_owned()))?;
+    let event = c
+        .selected_event()
+        .ok_or_else(|| fail("no event selected".to_owned()))?;
+
+    crate::publish::publish(spec, event.event)
+        .map(|_| ())
+        .map_err(|e| fail(e.to_string()))
+}
+
+fn note_command(c: &mut Context, arg: String) -> ActionResult {
+    let fail = |msg: String| ParseError::from_error_kind(msg, ErrorKind::Fail);
+
+    let uid = c
+        .selected_event()
+        .map(|event| event.uid().to_owned())
+        .ok_or_else(|| fail("no event selected".to_owned()))?;
+
+    c.annotations
+        .set(&uid, &arg)
+        .map_err(|e| fail(e.to_string()))
+}
+
+fn apply_to_selection(
+    c: &mut Context,
+    description: &str,
+    operation: impl Fn(&CalendarEvent) -> EventOperation,
+) -> ActionResult {
+    let num_events = c
+        .apply_to_selection(description, operation)
+        .map_err(|e| ParseError::from_error_kind(e.to_string(), ErrorKind::Fail))?;
+    c.last_error_message = Some(format!("{} {} event(s)", description, num_events));
+    Ok(())
+}
+
+fn move_command(c: &mut Context, arg: String) -> ActionResult {
+    let id = arg.trim();
+    if c.agenda().calendar_by_id(id).is_none() {
+        return Err(ParseError::from_error_kind(
+            format!("no calendar with id '{}'", id),
+            ErrorKind::Fail,
+        ));
+    }
+    apply_to_selection(c, &format!("moved to {}:", id), |_| {
+        EventOperation::MoveTo(id.to_owned())
+    })
+}
+
+fn shift_command(c: &mut Context, arg: String) -> ActionResult {
+    let duration: Duration = arg
+        .trim()
+        .parse::<IcalDuration>()
+        .map_err(|e| ParseError::from_error_kind(e.to_string(), ErrorKind::Fail))?
+        .into();
+    apply_to_selection(c, "shifted", |_| {
+        EventOperation::Modify(EventChange::Shift(duration))
+    })
+}
+
+fn alarm_command(c: &mut Context, arg: String) -> ActionResult {
+    let fail = |msg: String| ParseError::from_error_kind(msg, ErrorKind::Fail);
+
+    let (subcommand, trigger) = arg.split_once(' ').unwrap_or((&arg, ""));
+    let offset: Duration = trigger
+        .trim()
+        .parse::<IcalDuration>()
+        .map_err(|_| fail(format!("usage: alarm {} <duration, e.g. -P1D>", subcommand)))?
+        .into();
+
+    match subcommand {
+        "add" => apply_to_selection(c, "added alarm to", |_| {
+            EventOperation::Modify(EventChange::AddAlarm(Alarm {
+                offset,
+                related: AlarmRelation::Start,
+                action: "DISPLAY".to_owned(),
+                description: None,
+                repeat: 0,
+                interval: Duration::zero(),
+            }))
+        }),
+        "remove" => apply_to_selection(c, "removed alarm from", |_| {
+            EventOperation::Modify(EventChange::RemoveAlarm(offset))
+        }),
+        _ => Err(ParseError::from_error_kind(arg, ErrorKind::Tag)),
+    }
+}
+
+/// Answers the invitation to the selected event like `jk invite` and saves
+/// the reply to the organizer in a temporary file
+fn rsvp_command(c: &mut Context, arg: String) -> ActionResult {
+    let fail = |msg: String| ParseError::from_error_kind(msg, ErrorKind::Fail);
+
+    let answer: PartStat = arg.trim().parse().map_err(|e: crate::provider::Error| {
+        fail(format!("usage: rsvp accept|decline|tentative ({})", e))
+    })?;
+    let event = c
+        .selected_event()
+        .ok_or_else(|| fail("no event selected".to_owned()))?;
+    let message = itip::Message::parse(&event.raw_ical()).map_err(|e| fail(e.to_string()))?;
+    let address = message
+        .find_attendee(&c.addresses)
+        .ok_or_else(|| {
+            fail("you are not among the attendees, set 'addresses' in the config".to_owned())
+        })?
+        .to_owned();
+
+    let calendar = event.calendar.id().to_owned();
+    let uid = event.uid().to_owned();
+    let summary = event.summary().to_owned();
+    let file_name = event
+        .path()
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    // A new file only we can read, others could guess its name
+    let reply_path = env::temp_dir().join(format!(
+        "jackal-reply-{}-{}.ics",
+        file_name,
+        uuid::Uuid::new_v4().simple()
+    ));
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&reply_path)
+        .and_then(|mut file| file.write_all(message.reply(&address, answer).as_bytes()))
+        .map_err(|e| fail(e.to_string()))?;
+
+    // Declined events are not kept, as with jk invite
+    let result = match answer {
+        PartStat::Declined => c.agenda_mut().apply(
+            "declined",
+            &[(calendar, EventId::series(&uid), EventOperation::Remove)],
+        ),
+        _ => c
+            .agenda_mut()
+            .import_ics(&calendar, &message.to_event(&address, answer))
+            .map(|_| ()),
+    };
+    result.map_err(|e| fail(e.to_string()))?;
+
+    c.clamp_eventlist_index();
+    c.last_error_message = Some(format!(
+        "{} '{}', reply saved to {}",
+        answer,
+        summary,
+        reply_path.display()
+    ));
+    Ok(())
+}
+
+fn category_command(c: &mut Context, arg: String) -> ActionResult {
+    let categories: Vec<String> = arg
+        .split(',')
+        .map(str::trim)
+        .filter(|category| !category.is_empty())
+        .map(str::to_owned)
+        .collect();
+    apply_to_selection(c, "recategorized", |_| {
+        EventOperation::Modify(EventChange::SetCategories(categories.clone()))
+    })
+}
+
+/// Toggles whether the n-th task of the day is done
+fn done_command(c: &mut Context, arg: String) -> ActionResult {
+    let fail = |msg: String| ParseError::from_error_kind(msg, ErrorKind::Fail);
+
+    let n = arg
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|n| *n > 0)
+        .ok_or_else(|| fail("usage: done <number of the task>".to_owned()))?;
+    let (calendar, uid, done) = c
+        .day_todos()
+        .get(n - 1)
+        .map(|todo| {
+            (
+                todo.calendar.id().to_owned(),
+                todo.uid().to_owned(),
+                !todo.is_done(),
+            )
+        })
+        .ok_or_else(|| fail(format!("no task {}", n)))?;
+
+    c.agenda_mut()
+        .set_todo_done(&calendar, &uid, done)
+        .map_err(|e| fail(e.to_string()))
+}
+
+/// Acts on the days selected with V in the month pane
+fn range_command(c: &mut Context, arg: String) -> ActionResult {
+    let fail = |msg: String| ParseError::from_error_kind(msg, ErrorKind::Fail);
+
+    let days = c
+        .selected_days()
+        .ok_or_else(|| fail("no days selected, select them with V".to_owned()))?;
+    let (subcommand, value) = arg.split_once(' ').unwrap_or((&arg, ""));
+    let value = value.trim();
+
+    match subcommand {
+        "list" => {
+            c.picker_days = Some(days);
+            c.picker_index = 0;
+            c.mode = Mode::Picker;
+        }
+        "export" => {
+            if value.is_empty() {
+                return Err(fail("usage: range export <file>".to_owned()));
+            }
+            let (ics, num_events) = c
+                .agenda()
+                .export_days(&days)
+                .map_err(|e| fail(e.to_string()))?;
+            fs::write(value, ics).map_err(|e| fail(format!("{}: {}", value, e)))?;
+            c.last_error_message = Some(format!("exported {} event(s) to {}", num_events, value));
+        }
+        "busy" => {
+            let summaries = c.agenda().day_summaries(&days);
+            let busy = summaries
+                .iter()
+                .fold(Duration::zero(), |busy, day| busy + day.busy);
+            let num_free = summaries.iter().filter(|day| day.events == 0).count();
+            let num_events = c.agenda().events_covering(&days).count();
+            c.last_error_message = Some(format!(
+                "{}h {:02}m busy, {} of {} day(s) free, {} event(s)",
+                busy.num_hours(),
+                busy.num_minutes() % 60,
+                num_free,
+                summaries.len(),
+                num_events
+            ));
+        }
+        "allday" => {
+            // A leading calendar id picks the calendar, the first one is
+            // used otherwise
+            let (calendar, summary) = match value.split_once(' ') {
+                Some((id, summary)) if c.agenda().calendar_by_id(id).is_some() => {
+                    (Some(id), summary.trim())
+                }
+                _ => (None, value),
+            };
+            if summary.is_empty() {
+                return Err(fail(
+                    "usage: range allday [<calendar>] <summary>".to_owned(),
+                ));
+            }
+            let agenda = c.agenda_mut();
+            match calendar {
+                Some(id) => agenda.new_allday_event_in(id, summary, &days),
+                None => agenda.new_allday_event(summary, &days),
+            }
+            .map_err(|e| fail(e.to_string()))?;
+            c.last_error_message = Some(format!("added all-day event '{}'", summary));
+        }
+        _ => return Err(ParseError::from_error_kind(arg, ErrorKind::Tag)),
+    }
+
+    c.day_range_anchor = None;
+    Ok(())
+}
+
 const COMMANDS: &[(&'static str, Action)] = &[
+    ("calendar", Action::Arg(calendar_command)),
+    ("publish", Action::NoArg(publish_command)),
+    (
+        "delete",
+        Action::NoArg(|c| apply_to_selection(c, "deleted", |_| EventOperation::Remove)),
+    ),
+    ("move", Action::Arg(move_command)),
+    (
+        "log",
+        Action::Arg(|c, filter| {
+            c.console_filter = filter.trim().to_owned();
+            c.show_console = true;
+            Ok(())
+        }),
+    ),
+    (
+        "console",
+        Action::NoArg(|c| {
+            c.console_filter.clear();
+            c.show_console = !c.show_console;
+            Ok(())
+        }),
+    ),
+    ("shift", Action::Arg(shift_command)),
+    ("category", Action::Arg(category_command)),
+    ("alarm", Action::Arg(alarm_command)),
+    ("note", Action::Arg(note_command)),
+    (
+        "path",
+        Action::NoArg(|c| {
+            let path = c
+                .selected_event()
+                .map(|event| event.path().display().to_string())
+                .ok_or_else(|| {
+                    ParseError::from_error_kind("no event selected".to_owned(), ErrorKind::Fail)
+                })?;
+            c.last_error_message = Some(path);
+            Ok(())
+        }),
+    ),
+    ("done", Action::Arg(done_command)),
+    ("range", Action::Arg(range_command)),
+    ("rsvp", Action::Arg(rsvp_command)),
+    (
+        "pin",
+        Action::NoArg(|c| {
+            apply_to_selection(c, "pinned", |_| {
+                EventOperation::Modify(EventChange::SetPinned(true))
+            })
+        }),
+    ),
+    (
+        "unpin",
+        Action::NoArg(|c| {
+            apply_to_selection(c, "unpinned", |_| {
+                EventOperation::Modify(EventChange::SetPinned(false))
+            })
+        }),
+    ),
+    (
+        "undo",
+        Action::NoArg(|c| {
+            c.undo();
+            Ok(())
+        }),
+    ),
     (
         "gy",
         Action::Repeatable(|c, p| {
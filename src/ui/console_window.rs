@@ -0,0 +1,67 @@
+use log::Level;
+use std::fmt::Write;
+use unsegen::base::*;
+use unsegen::widget::*;
+
+use crate::console;
+use crate::ui::Context;
+
+/// Recent warnings and errors, newest at the bottom
+pub struct ConsoleWindow<'a> {
+    context: &'a Context,
+}
+
+impl<'a> ConsoleWindow<'a> {
+    const HEIGHT: usize = 8;
+
+    pub fn new(context: &'a Context) -> Self {
+        ConsoleWindow { context }
+    }
+}
+
+impl Widget for ConsoleWindow<'_> {
+    fn space_demand(&self) -> Demand2D {
+        Demand2D {
+            width: ColDemand::at_least(10),
+            height: RowDemand::exact(Self::HEIGHT),
+        }
+    }
+
+    fn draw(&self, mut window: unsegen::base::Window, _hints: RenderingHints) {
+        let theme = self.context.theme();
+        let filter = &self.context.console_filter;
+        let entries = console::entries(filter);
+        let height = window.get_height().raw_value().max(1) as usize;
+
+        let mut cursor = Cursor::new(&mut window);
+
+        let saved_style = cursor.get_style_modifier();
+        cursor.apply_style_modifier(theme.month_header_style);
+        if filter.is_empty() {
+            write!(&mut cursor, "Log ({})", entries.len()).unwrap();
+        } else {
+            write!(&mut cursor, "Log matching '{}' ({})", filter, entries.len()).unwrap();
+        }
+        cursor.fill_and_wrap_line();
+        cursor.set_style_modifier(saved_style);
+
+        let num_shown = entries.len().min(height - 1);
+        for entry in &entries[entries.len() - num_shown..] {
+            let saved_style = cursor.get_style_modifier();
+            cursor.apply_style_modifier(match entry.level {
+                Level::Error => theme.console_error_style,
+                _ => theme.console_warn_style,
+            });
+            write!(
+                &mut cursor,
+                "{} {:5} {}",
+                entry.time.format("%H:%M:%S"),
+                entry.level,
+                entry.message
+            )
+            .unwrap();
+            cursor.fill_and_wrap_line();
+            cursor.set_style_modifier(saved_style);
+        }
+    }
+}
@@ -1,17 +1,171 @@
 use chrono::prelude::*;
+use chrono::Duration;
 use num_traits::FromPrimitive;
-use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::RangeInclusive;
 
-use crate::agenda::Agenda;
+use crate::agenda::{Agenda, EventOperation, SubscriptionId};
+use crate::annotations::Annotations;
+use crate::config::{
+    AvailabilitySpec, CategorySpec, Config, Density, MarkedDaySpec, PublishSpec, ThemeName,
+    TitleOverflow,
+};
+use crate::locale::Locale;
+use crate::provider::{self, CalendarEvent, CalendarTodo, EventChange, EventId, NewEvent};
+use crate::state::{Migration, StateFile};
+
+use super::format_relative;
 
 use unsegen::base::style::*;
 use unsegen::widget::builtin::PromptLine;
 
+/// Most days after the selected one that the event list shows
+const MAX_LOOKAHEAD_DAYS: u32 = 31;
+/// Version 1 only added the version header
+const HIDDEN_CALENDARS_MIGRATIONS: &[Migration] = &[Ok];
+
+/// Calendars hidden with `:calendar hide`, kept for the next start of the
+/// TUI. Other commands and the API show all calendars.
+#[derive(Default, Serialize, Deserialize)]
+struct HiddenCalendars {
+    #[serde(default)]
+    ids: BTreeSet<String>,
+}
+
+fn hidden_calendars_file() -> Result<StateFile, Box<dyn std::error::Error>> {
+    StateFile::new("hidden_calendars.toml", HIDDEN_CALENDARS_MIGRATIONS)
+}
+
+/// Parses a `#RRGGBB[AA]` color, ignoring the alpha channel
+pub fn parse_color(color: &str) -> Option<Color> {
+    let hex = color.strip_prefix('#')?;
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(Color::Rgb {
+        r: channel(0)?,
+        g: channel(2)?,
+        b: channel(4)?,
+    })
+}
+
+/// The 16 basic colors as most terminals show them by default
+const BASIC_COLORS: &[(Color, (u8, u8, u8))] = &[
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::White, (229, 229, 229)),
+    (Color::LightBlack, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::LightWhite, (255, 255, 255)),
+];
+
+/// The colors the terminal can show
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorSupport {
+    Monochrome,
+    Basic,
+    Ansi256,
+    Truecolor,
+}
+
+impl ColorSupport {
+    /// Tells the colors of the terminal from COLORTERM and TERM. NO_COLOR
+    /// turns colors off, see https://no-color.org.
+    pub fn detect() -> Self {
+        let var = |name: &str| std::env::var(name).unwrap_or_default();
+        if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            return ColorSupport::Monochrome;
+        }
+
+        let colorterm = var("COLORTERM");
+        let term = var("TERM");
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            ColorSupport::Truecolor
+        } else if term.contains("256color") {
+            ColorSupport::Ansi256
+        } else if term == "dumb" {
+            ColorSupport::Monochrome
+        } else {
+            ColorSupport::Basic
+        }
+    }
+
+    /// The closest color to `color` that the terminal can show, `None` if it
+    /// shows no colors at all
+    pub fn adapt(self, color: Color) -> Option<Color> {
+        let (r, g, b) = match (self, color) {
+            (ColorSupport::Monochrome, _) => return None,
+            (ColorSupport::Truecolor, _) => return Some(color),
+            (_, Color::Rgb { r, g, b }) => (r, g, b),
+            (_, color) => return Some(color),
+        };
+
+        Some(match self {
+            ColorSupport::Ansi256 => Color::Ansi(ansi256_index(r, g, b)),
+            _ => BASIC_COLORS
+                .iter()
+                .min_by_key(|(_, rgb)| rgb_distance(*rgb, (r, g, b)))
+                .map(|(color, _)| *color)
+                .unwrap_or(Color::Default),
+        })
+    }
+}
+
+/// Squared distance of two colors
+fn rgb_distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> i32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+/// Index of the closest color in the 6x6x6 cube or the gray ramp of 256
+/// color terminals
+fn ansi256_index(r: u8, g: u8, b: u8) -> u8 {
+    // Levels of the cube are 0, 95, 135, 175, 215 and 255
+    let level = |v: u8| match v {
+        0..=47 => 0,
+        48..=114 => 1,
+        v => (v - 35) / 40,
+    };
+    let value = |level: u8| if level == 0 { 0 } else { 55 + 40 * level };
+    let (lr, lg, lb) = (level(r), level(g), level(b));
+    let cube = (value(lr), value(lg), value(lb));
+
+    // The ramp has 24 grays from 8 to 238
+    let average = ((r as u16 + g as u16 + b as u16) / 3) as u8;
+    let step = (average.saturating_sub(3) / 10).min(23);
+    let gray = 8 + 10 * step;
+
+    if rgb_distance((gray, gray, gray), (r, g, b)) < rgb_distance(cube, (r, g, b)) {
+        232 + step
+    } else {
+        16 + 36 * lr + 6 * lg + lb
+    }
+}
+
 #[derive(Clone, Copy, Debug, Ord, Eq, PartialEq, PartialOrd)]
 pub enum Mode {
     Normal,
     Insert,
+    /// Entering a new event in natural language
+    QuickAdd,
     Command,
+    /// Fuzzy search for an event to jump to
+    Picker,
+    /// Waiting for y/n before deleting the selected events
+    Confirm,
+    /// Choosing an action for the selected event, see `menu_entries`
+    Menu,
+    /// Full-text search for events, see `Agenda::search`
+    Search,
 }
 
 #[derive(Clone, Debug)]
@@ -24,8 +178,27 @@ pub struct Theme {
     pub today_day_style: StyleModifier,
     pub today_day_text_style: TextFormatModifier,
     pub today_day_char: Option<char>,
+    /// Fills the gap before days an earlier event lasts into, so that
+    /// events spanning several days show as a line in the month pane
+    pub continued_day_char: Option<char>,
     pub month_header_style: StyleModifier,
     pub month_header_text_style: TextFormatModifier,
+    pub ongoing_header_style: StyleModifier,
+    pub pinned_header_style: StyleModifier,
+    pub dst_change_style: StyleModifier,
+    pub busy_day_style: StyleModifier,
+    pub overbooked_day_style: StyleModifier,
+    pub range_day_style: StyleModifier,
+    pub marked_day_style: StyleModifier,
+    pub availability_style: StyleModifier,
+    pub hidden_calendar_style: StyleModifier,
+    pub note_style: StyleModifier,
+    pub description_style: StyleModifier,
+    pub deadline_style: StyleModifier,
+    pub day_separator_style: StyleModifier,
+    pub weekend_separator_style: StyleModifier,
+    pub console_warn_style: StyleModifier,
+    pub console_error_style: StyleModifier,
 }
 
 impl Default for Theme {
@@ -39,8 +212,71 @@ impl Default for Theme {
             today_day_style: StyleModifier::default().invert(true),
             today_day_text_style: TextFormatModifier::default().italic(true),
             today_day_char: Some('*'),
+            continued_day_char: Some('─'),
             month_header_style: StyleModifier::default().fg_color(Color::Yellow),
             month_header_text_style: TextFormatModifier::default(),
+            ongoing_header_style: StyleModifier::default()
+                .fg_color(Color::Green)
+                .format(TextFormatModifier::default().bold(true)),
+            pinned_header_style: StyleModifier::default()
+                .fg_color(Color::Yellow)
+                .format(TextFormatModifier::default().bold(true)),
+            dst_change_style: StyleModifier::default()
+                .format(TextFormatModifier::default().underline(true)),
+            busy_day_style: StyleModifier::default()
+                .format(TextFormatModifier::default().bold(true)),
+            overbooked_day_style: StyleModifier::default().fg_color(Color::Red),
+            range_day_style: StyleModifier::default().bg_color(Color::Cyan),
+            marked_day_style: StyleModifier::default().fg_color(Color::LightGreen),
+            availability_style: StyleModifier::default().fg_color(Color::LightBlack),
+            hidden_calendar_style: StyleModifier::default().fg_color(Color::LightBlack),
+            note_style: StyleModifier::default()
+                .fg_color(Color::LightBlack)
+                .format(TextFormatModifier::default().italic(true)),
+            description_style: StyleModifier::default().fg_color(Color::LightBlack),
+            deadline_style: StyleModifier::default()
+                .fg_color(Color::Red)
+                .format(TextFormatModifier::default().bold(true)),
+            day_separator_style: StyleModifier::default()
+                .format(TextFormatModifier::default().bold(true)),
+            weekend_separator_style: StyleModifier::default()
+                .fg_color(Color::Magenta)
+                .format(TextFormatModifier::default().bold(true)),
+            console_warn_style: StyleModifier::default().fg_color(Color::Yellow),
+            console_error_style: StyleModifier::default().fg_color(Color::Red),
+        }
+    }
+}
+
+impl Theme {
+    /// Tells things apart by bold, italic, underlined and inverted text only
+    pub fn monochrome() -> Self {
+        let bold = StyleModifier::default().format(TextFormatModifier::default().bold(true));
+        let italic = StyleModifier::default().format(TextFormatModifier::default().italic(true));
+        let underline =
+            StyleModifier::default().format(TextFormatModifier::default().underline(true));
+        Theme {
+            focus_day_style: StyleModifier::default().invert(true),
+            focus_day_char: Some('>'),
+            today_day_style: StyleModifier::default(),
+            month_header_style: bold,
+            ongoing_header_style: bold,
+            pinned_header_style: bold,
+            dst_change_style: underline,
+            busy_day_style: bold,
+            overbooked_day_style: underline,
+            range_day_style: underline,
+            marked_day_style: italic,
+            availability_style: italic,
+            hidden_calendar_style: italic,
+            note_style: italic,
+            description_style: StyleModifier::default(),
+            deadline_style: bold,
+            day_separator_style: bold,
+            weekend_separator_style: underline,
+            console_warn_style: bold,
+            console_error_style: bold.invert(true),
+            ..Theme::default()
         }
     }
 }
@@ -48,30 +284,178 @@ impl Default for Theme {
 pub struct Context {
     pub mode: Mode,
     pub theme: Theme,
+    pub colors: ColorSupport,
     pub cursor: DateTime<Local>,
     pub eventlist_index: usize,
+    /// Event list index where visual selection started, with the day it refers to
+    pub selection_anchor: Option<(NaiveDate, usize)>,
+    /// Day where selecting a range of days in the month pane started
+    pub day_range_anchor: Option<NaiveDate>,
+    /// Highlighted entry of the event picker
+    pub picker_index: usize,
+    /// Highlighted entry of the action menu
+    pub menu_index: usize,
+    /// UID of the event to open in the editor once the terminal is free
+    pub pending_edit: Option<String>,
+    /// Days the picker lists the events of instead of upcoming ones
+    pub picker_days: Option<RangeInclusive<NaiveDate>>,
+    /// Query repeated by n and N
+    pub last_search: Option<String>,
     pub last_error_message: Option<String>,
+    /// Event waiting to be confirmed in `Mode::Confirm`, which confirms
+    /// deleting the selection otherwise
+    pub pending_event: Option<NewEvent<chrono_tz::Tz>>,
+    pub min_display_duration: Duration,
+    pub resize_step: Duration,
+    pub title_overflow: TitleOverflow,
+    pub density: Density,
+    /// Days after the selected one that the event list shows as well
+    pub lookahead_days: u32,
+    /// Rows the event list needs in the current frame, see
+    /// `EventWindow::content_rows`
+    pub eventlist_rows: usize,
+    /// When each calendar last changed, e.g. "work 5m ago · home 2d ago",
+    /// computed once per tick instead of on every redraw
+    pub calendar_ages: String,
+    pub locale: Locale,
+    /// Show times relative to now next to absolute ones
+    pub relative_times: bool,
+    pub publish_target: Option<PublishSpec>,
+    /// The user's own addresses, to answer invitations with
+    pub addresses: Vec<String>,
+    /// Template of links to the position of an event, see `Config::map_url`
+    pub map_url: String,
+    /// Show tomorrow's first event from this time of day on
+    pub tomorrow_from: Option<NaiveTime>,
+    pub availability: Vec<AvailabilitySpec>,
+    /// Days with more time covered by events are flagged in the month pane
+    pub overbooked_after: Option<Duration>,
+    pub categories: BTreeMap<String, CategorySpec>,
+    /// Calendars whose all-day events mark their days as public holidays
+    pub holiday_calendars: Vec<String>,
+    pub marked_days: Vec<MarkedDaySpec>,
+    pub annotations: Annotations,
+    pub show_console: bool,
+    pub show_countdown: bool,
+    /// Only log messages containing this are shown in the console
+    pub console_filter: String,
     input_sinks: BTreeMap<Mode, PromptLine>,
     agenda: Agenda,
+    eventlist_subscription: SubscriptionId,
     now: DateTime<Local>,
 }
 
 impl Context {
-    pub fn new(calendar: Agenda) -> Self {
+    pub fn new(mut calendar: Agenda) -> Self {
+        let today = Local::now().date_naive().and_time(NaiveTime::MIN);
+        let eventlist_subscription = calendar.subscribe(today..today + Duration::days(1));
+
         Context {
             mode: Mode::Normal,
             theme: Theme::default(),
+            colors: ColorSupport::detect(),
             cursor: Local::now(),
             last_error_message: None,
+            pending_event: None,
+            min_display_duration: Duration::zero(),
+            resize_step: Duration::minutes(15),
+            title_overflow: TitleOverflow::default(),
+            density: Density::default(),
+            lookahead_days: 0,
+            eventlist_rows: 0,
+            calendar_ages: String::new(),
+            locale: Locale::from_env(),
+            relative_times: false,
+            publish_target: None,
+            addresses: Vec::new(),
+            map_url: String::new(),
+            tomorrow_from: None,
+            availability: Vec::new(),
+            overbooked_after: None,
+            holiday_calendars: Vec::new(),
+            marked_days: Vec::new(),
+            categories: BTreeMap::new(),
+            annotations: Annotations::default(),
+            show_console: false,
+            show_countdown: false,
+            console_filter: String::new(),
             input_sinks: BTreeMap::from([
                 (Mode::Insert, PromptLine::with_prompt(">".to_owned())),
+                (Mode::QuickAdd, PromptLine::with_prompt("+".to_owned())),
                 (Mode::Command, PromptLine::with_prompt(":".to_owned())),
+                (Mode::Picker, PromptLine::with_prompt("Go to:".to_owned())),
+                (Mode::Search, PromptLine::with_prompt("/".to_owned())),
             ]),
             eventlist_index: 0,
+            selection_anchor: None,
+            day_range_anchor: None,
+            picker_index: 0,
+            menu_index: 0,
+            pending_edit: None,
+            picker_days: None,
+            last_search: None,
             agenda: calendar,
+            eventlist_subscription,
             now: Local::now(),
         }
     }
+    /// A context with the display settings of `config`
+    pub fn from_config(agenda: Agenda, config: &Config) -> Self {
+        let mut context = Context::new(agenda);
+        context.min_display_duration = Duration::minutes(config.min_display_minutes as i64);
+        context.resize_step = Duration::minutes(config.resize_minutes as i64);
+        context.publish_target = config.publish.clone();
+        context.addresses = config.addresses.clone();
+        context.map_url = config.map_url.clone();
+        context.tomorrow_from = config.tomorrow.as_ref().map(|spec| spec.from);
+        context.availability = config.availability.clone();
+        context.overbooked_after = config
+            .overbooked_hours
+            .map(|hours| Duration::minutes((hours * 60.0).round() as i64));
+        context.categories = config.categories.clone();
+        context.holiday_calendars = config.holiday_calendars.clone();
+        context.marked_days = config.marked_days.clone();
+        context.show_countdown = config.countdown;
+        if config.theme == ThemeName::Monochrome {
+            context.colors = ColorSupport::Monochrome;
+        }
+        if context.colors == ColorSupport::Monochrome {
+            context.theme = Theme::monochrome();
+        }
+        context.title_overflow = config.title_overflow;
+        context.density = config.density;
+        context.lookahead_days = config.lookahead_days.min(MAX_LOOKAHEAD_DAYS);
+        context.locale = config.locale();
+        context.relative_times = config.formatting.relative_times;
+        match Annotations::load() {
+            Ok(annotations) => context.annotations = annotations,
+            Err(err) => log::warn!("Could not load annotations: {}", err),
+        }
+        match hidden_calendars_file().and_then(|file| file.load::<HiddenCalendars>()) {
+            Ok(hidden) => {
+                for id in hidden.ids {
+                    // The calendar may have been removed from the config
+                    if let Err(err) = context.agenda.set_hidden(&id, true) {
+                        log::info!("Not hiding calendar: {}", err);
+                    }
+                }
+            }
+            Err(err) => log::warn!("Could not load the hidden calendars: {}", err),
+        }
+
+        context.update_calendar_ages();
+
+        let num_conflicts = context.agenda().uid_conflicts().len();
+        if num_conflicts > 0 {
+            context.last_error_message = Some(format!(
+                "{} duplicate UID(s) found, run 'jk doctor' for details",
+                num_conflicts
+            ));
+        }
+
+        context
+    }
+
     pub fn with_today(mut self) -> Self {
         self.select_today();
         self
@@ -81,6 +465,18 @@ impl Context {
         self.cursor = Local::now();
     }
 
+    /// Moves the cursor to `date`, keeping the time of day
+    pub fn select_date(&mut self, date: NaiveDate) {
+        if let Some(cursor) = Local
+            .from_local_datetime(&date.and_time(self.cursor.time()))
+            .earliest()
+        {
+            self.cursor = cursor;
+            self.selection_anchor = None;
+            self.eventlist_index = 0;
+        }
+    }
+
     pub fn selected_day(&self) -> u32 {
         self.cursor.day()
     }
@@ -97,6 +493,12 @@ impl Context {
         &self.theme
     }
 
+    /// The color of a calendar or category given as `#RRGGBB`, as close as
+    /// the terminal can show it
+    pub fn color(&self, spec: &str) -> Option<Color> {
+        self.colors.adapt(parse_color(spec)?)
+    }
+
     pub fn input_sink(&self, mode: Mode) -> &PromptLine {
         self.input_sinks.get(&mode).unwrap()
     }
@@ -108,6 +510,412 @@ impl Context {
         &self.agenda
     }
 
+    pub fn agenda_mut(&mut self) -> &mut Agenda {
+        &mut self.agenda
+    }
+
+    /// Display settings of the first of `categories` that has any
+    pub fn category_spec<S: AsRef<str>>(&self, categories: &[S]) -> Option<&CategorySpec> {
+        categories.iter().find_map(|category| {
+            self.categories
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(category.as_ref()))
+                .map(|(_, spec)| spec)
+        })
+    }
+
+    /// Labels of the public holidays and marked days on `date`, `None` if
+    /// the day is not marked. Marked days without a label add none.
+    pub fn day_marks(&self, date: NaiveDate) -> Option<Vec<String>> {
+        let holidays = self
+            .agenda
+            .events_of_day(&date)
+            .filter(|event| {
+                event.occurrence().is_allday()
+                    && self
+                        .holiday_calendars
+                        .iter()
+                        .any(|id| id == event.calendar.id())
+            })
+            .map(|event| Some(format!("Public holiday: {}", event.summary())));
+        let marked = self
+            .marked_days
+            .iter()
+            .filter(|spec| spec.applies_to(date))
+            .map(|spec| spec.label.clone());
+
+        let marks: Vec<Option<String>> = holidays.chain(marked).collect();
+        if marks.is_empty() {
+            None
+        } else {
+            Some(marks.into_iter().flatten().collect())
+        }
+    }
+
+    /// The events of the cursor's day in the same order as in the event list
+    pub fn day_events(&self) -> Vec<CalendarEvent<'_>> {
+        let mut events: Vec<_> = self
+            .agenda
+            .events_of_day(&self.cursor.date_naive())
+            .collect();
+        events.sort_by_key(|ev| ev.occurrence().clone().with_tz(&Local {}).begin());
+        events
+    }
+
+    /// The tasks of the cursor's day in the same order as in the event list
+    pub fn day_todos(&self) -> Vec<CalendarTodo<'_>> {
+        self.agenda
+            .todos_of_day(self.cursor.date_naive(), &self.now)
+    }
+
+    /// The event highlighted in the event list
+    pub fn selected_event(&self) -> Option<CalendarEvent<'_>> {
+        self.day_events().into_iter().nth(self.eventlist_index)
+    }
+
+    /// Event list indices covered by the visual selection, or just the
+    /// highlighted event if there is none
+    pub fn selection_range(&self) -> std::ops::RangeInclusive<usize> {
+        match self.selection_anchor {
+            Some((date, anchor)) if date == self.cursor.date_naive() => {
+                anchor.min(self.eventlist_index)..=anchor.max(self.eventlist_index)
+            }
+            _ => self.eventlist_index..=self.eventlist_index,
+        }
+    }
+
+    pub fn selected_events(&self) -> Vec<CalendarEvent<'_>> {
+        let range = self.selection_range();
+        self.day_events()
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| range.contains(i))
+            .map(|(_, ev)| ev)
+            .collect()
+    }
+
+    /// Switches to the next density of the event list and stores it in the
+    /// config file so that it is kept across restarts
+    pub fn cycle_density(&mut self, config: &Config) {
+        self.density = self.density.next();
+        let name = self.density.name();
+
+        self.last_error_message = Some(
+            match config.store_setting("density", toml::Value::String(name.to_owned())) {
+                Ok(()) => format!("Density: {}", name),
+                Err(err) => format!("Density: {} (could not save it: {})", name, err),
+            },
+        );
+    }
+
+    /// Shows `delta` more or fewer days in the event list and stores the
+    /// number in the config file so that it is kept across restarts
+    pub fn change_lookahead(&mut self, delta: i32, config: &Config) {
+        let days = (self.lookahead_days as i32 + delta).clamp(0, MAX_LOOKAHEAD_DAYS as i32) as u32;
+        if days == self.lookahead_days {
+            return;
+        }
+        self.lookahead_days = days;
+
+        self.last_error_message = Some(
+            match config.store_setting("lookahead_days", toml::Value::Integer(days as i64)) {
+                Ok(()) => format!("Lookahead: {} day(s)", days),
+                Err(err) => format!("Lookahead: {} day(s) (could not save it: {})", days, err),
+            },
+        );
+    }
+
+    /// Hides or shows the events of `calendar_id` and keeps that for the
+    /// next start
+    pub fn set_calendar_hidden(&mut self, calendar_id: &str, hidden: bool) -> provider::Result<()> {
+        self.agenda.set_hidden(calendar_id, hidden)?;
+
+        let ids = self
+            .agenda
+            .calendar_iter()
+            .map(|calendar| calendar.id())
+            .filter(|id| self.agenda.is_hidden(id))
+            .map(str::to_owned)
+            .collect();
+        if let Err(err) =
+            hidden_calendars_file().and_then(|file| file.save(&HiddenCalendars { ids }))
+        {
+            self.last_error_message = Some(format!("Could not save the hidden calendars: {}", err));
+        }
+        Ok(())
+    }
+
+    /// Starts a visual selection at the highlighted event or ends the current one
+    pub fn toggle_selection(&mut self) {
+        self.selection_anchor = match self.selection_anchor {
+            Some(_) => None,
+            None => Some((self.cursor.date_naive(), self.eventlist_index)),
+        };
+    }
+
+    /// Starts selecting a range of days at the cursor's day or ends the
+    /// current selection
+    pub fn toggle_day_range(&mut self) {
+        self.day_range_anchor = match self.day_range_anchor {
+            Some(_) => None,
+            None => Some(self.cursor.date_naive()),
+        };
+    }
+
+    /// The days from where the range selection started to the cursor's day
+    pub fn selected_days(&self) -> Option<RangeInclusive<NaiveDate>> {
+        let anchor = self.day_range_anchor?;
+        let day = self.cursor.date_naive();
+        Some(anchor.min(day)..=anchor.max(day))
+    }
+
+    /// Applies the operation returned by `operation` to every selected event
+    /// as a single undoable batch and returns the number of events
+    pub fn apply_to_selection(
+        &mut self,
+        description: &str,
+        operation: impl Fn(&CalendarEvent) -> EventOperation,
+    ) -> provider::Result<usize> {
+        let operations: Vec<_> = self
+            .selected_events()
+            .iter()
+            .map(|ev| {
+                (
+                    ev.calendar.id().to_owned(),
+                    EventId::of(ev.event),
+                    operation(ev),
+                )
+            })
+            .collect();
+
+        let result = self.agenda.apply(description, &operations);
+        self.selection_anchor = None;
+        self.clamp_eventlist_index();
+        result.map(|_| operations.len())
+    }
+
+    /// Moves the selected events by `by` and keeps the cursor on the
+    /// highlighted one
+    pub fn shift_selection(&mut self, by: Duration) {
+        let uid = self.selected_event().map(|ev| ev.uid().to_owned());
+
+        if let Err(err) = self.apply_to_selection("shifted", |_| {
+            EventOperation::Modify(EventChange::Shift(by))
+        }) {
+            self.last_error_message = Some(err.to_string());
+            return;
+        }
+
+        self.cursor += by;
+        if let Some(index) = uid.and_then(|uid| {
+            self.day_events()
+                .iter()
+                .position(|ev| ev.uid() == uid.as_str())
+        }) {
+            self.eventlist_index = index;
+        }
+    }
+
+    /// Moves the end of the selected events by `by`
+    pub fn resize_selection(&mut self, by: Duration) {
+        if let Err(err) = self.apply_to_selection("resized", |_| {
+            EventOperation::Modify(EventChange::Resize(by))
+        }) {
+            self.last_error_message = Some(err.to_string());
+        }
+    }
+
+    /// Pins the selected events or, if the highlighted one is pinned
+    /// already, unpins them
+    pub fn toggle_pin(&mut self) {
+        let pin = !self.selected_event().is_some_and(|event| event.is_pinned());
+        let description = if pin { "pinned" } else { "unpinned" };
+
+        self.last_error_message = Some(
+            match self.apply_to_selection(description, |_| {
+                EventOperation::Modify(EventChange::SetPinned(pin))
+            }) {
+                Ok(num_events) => format!("{} {} event(s)", description, num_events),
+                Err(err) => err.to_string(),
+            },
+        );
+    }
+
+    /// Moves the cursor to the event with `uid` beginning at `begin` and
+    /// highlights it in the event list
+    pub fn jump_to_event(&mut self, uid: &str, begin: DateTime<Local>) {
+        self.cursor = begin;
+        self.selection_anchor = None;
+        self.eventlist_index = self
+            .day_events()
+            .iter()
+            .position(|event| event.uid() == uid)
+            .unwrap_or(0);
+    }
+
+    /// Searches for `query` starting at the cursor
+    pub fn search(&mut self, query: &str) {
+        self.last_search = Some(query.to_owned());
+        self.find_match(true, true);
+    }
+
+    /// Jumps to the next (or previous) match of the last search, wrapping
+    /// around at the end
+    pub fn search_next(&mut self, forwards: bool) {
+        if self.last_search.is_none() {
+            self.last_error_message = Some("No previous search".to_owned());
+            return;
+        }
+        self.find_match(forwards, false);
+    }
+
+    fn find_match(&mut self, forwards: bool, include_cursor: bool) {
+        let query = self.last_search.clone().unwrap_or_default();
+        let cursor = self.cursor;
+        let matches: Vec<_> = self
+            .agenda
+            .search(&query)
+            .iter()
+            .map(|event| {
+                (
+                    event.uid().to_owned(),
+                    event.occurrence().clone().with_tz(&Local {}).begin(),
+                )
+            })
+            .collect();
+
+        // Matches beginning at the same time are told apart by the highlighted one
+        let selected_uid = self.selected_event().map(|event| event.uid().to_owned());
+        let current = matches
+            .iter()
+            .position(|(uid, begin)| *begin == cursor && Some(uid) == selected_uid.as_ref())
+            .filter(|_| !include_cursor);
+
+        let after_cursor =
+            |begin: &DateTime<Local>| *begin > cursor || (include_cursor && *begin == cursor);
+        let found = if let Some(current) = current {
+            Some(if forwards {
+                (current + 1) % matches.len()
+            } else {
+                (current + matches.len() - 1) % matches.len()
+            })
+        } else if forwards {
+            matches
+                .iter()
+                .position(|(_, begin)| after_cursor(begin))
+                .or_else(|| (!matches.is_empty()).then_some(0))
+        } else {
+            matches
+                .iter()
+                .rposition(|(_, begin)| *begin < cursor)
+                .or_else(|| matches.len().checked_sub(1))
+        };
+
+        match found {
+            Some(index) => {
+                let (uid, begin) = &matches[index];
+                self.jump_to_event(uid, *begin);
+                self.last_error_message =
+                    Some(format!("/{} ({}/{})", query, index + 1, matches.len()));
+            }
+            None => self.last_error_message = Some(format!("No match for '{}'", query)),
+        }
+    }
+
+    /// Adds `event` and returns to normal mode. Events beginning in the past
+    /// or overlapping others are only added after `confirm`.
+    pub fn add_event(&mut self, event: NewEvent<chrono_tz::Tz>) -> provider::Result<()> {
+        let now = self.now.with_timezone(&event.begin.timezone());
+        let warnings = self.agenda.check_new_event(&event, &now);
+        if !warnings.is_empty() {
+            self.last_error_message = Some(format!(
+                "'{}' {}, add anyway? (y/n)",
+                event.summary.as_deref().unwrap_or_default(),
+                warnings
+            ));
+            self.pending_event = Some(event);
+            self.mode = Mode::Confirm;
+            return Ok(());
+        }
+
+        self.agenda.new_event(event)?;
+        self.last_error_message = None;
+        self.mode = Mode::Normal;
+        Ok(())
+    }
+
+    /// Answers the question asked by `add_event` or `request_delete`
+    pub fn confirm(&mut self, confirmed: bool) {
+        let event = match self.pending_event.take() {
+            Some(event) => event,
+            None => return self.confirm_delete(confirmed),
+        };
+
+        self.mode = Mode::Normal;
+        self.last_error_message = if !confirmed {
+            Some("Not added".to_owned())
+        } else if let Err(err) = self.agenda.new_event(event) {
+            Some(err.to_string())
+        } else {
+            None
+        };
+    }
+
+    /// Shows the actions applicable to the selected event
+    pub fn open_menu(&mut self) {
+        if self.selected_event().is_none() {
+            self.last_error_message = Some("No event selected".to_owned());
+            return;
+        }
+        self.menu_index = 0;
+        self.mode = Mode::Menu;
+    }
+
+    /// Asks whether the selected events should be deleted, which is done by
+    /// `confirm`
+    pub fn request_delete(&mut self) {
+        let (question, any_selected) = match self.selected_events().as_slice() {
+            [] => ("No event selected".to_owned(), false),
+            [event] => (format!("Delete '{}'? (y/n)", event.summary()), true),
+            events => (format!("Delete {} events? (y/n)", events.len()), true),
+        };
+        self.last_error_message = Some(question);
+        if any_selected {
+            self.mode = Mode::Confirm;
+        }
+    }
+
+    /// Deletes the selected events if `confirmed` and returns to normal mode
+    fn confirm_delete(&mut self, confirmed: bool) {
+        self.mode = Mode::Normal;
+        if !confirmed {
+            self.last_error_message = Some("Not deleted".to_owned());
+            return;
+        }
+
+        self.last_error_message = Some(
+            match self.apply_to_selection("deleted", |_| EventOperation::Remove) {
+                Ok(num_events) => format!("deleted {} event(s)", num_events),
+                Err(err) => err.to_string(),
+            },
+        );
+    }
+
+    pub fn undo(&mut self) {
+        self.last_error_message = match self.agenda.undo() {
+            Ok(Some(description)) => Some(format!("Undid {}", description)),
+            Ok(None) => Some("Nothing to undo".to_owned()),
+            Err(err) => Some(format!("Undo failed: {}", err)),
+        };
+        self.clamp_eventlist_index();
+    }
+
+    /// Clamps the event list index after events were added or removed
+    pub fn clamp_eventlist_index(&mut self) {
+        let num_events = self.agenda.events_of_day(&self.cursor.date_naive()).count();
+        self.eventlist_index = self.eventlist_index.min(num_events.saturating_sub(1));
+    }
+
     pub fn now(&self) -> &DateTime<Local> {
         &self.now
     }
@@ -122,6 +930,49 @@ impl Context {
 
     pub fn update(&mut self) {
         self.now = Local::now();
+
+        let day = self.cursor.date_naive().and_time(NaiveTime::MIN);
+        self.agenda
+            .set_subscription_range(self.eventlist_subscription, day..day + Duration::days(1));
+
+        let changes = self.agenda.refresh();
+        for (_, change) in &changes {
+            log::info!(
+                "Event '{}' in '{}' changed on disk: {:?}",
+                change.uid,
+                change.calendar,
+                change.kind
+            );
+        }
+
+        if changes
+            .iter()
+            .any(|(id, _)| *id == self.eventlist_subscription)
+        {
+            self.clamp_eventlist_index();
+        }
+
+        self.update_calendar_ages();
+    }
+
+    fn update_calendar_ages(&mut self) {
+        let now = self.now;
+        self.calendar_ages = self
+            .agenda
+            .calendar_iter()
+            .filter_map(|calendar| {
+                if !calendar.is_available() {
+                    return Some(format!("{} unavailable", calendar.id()));
+                }
+                let modified = DateTime::<Local>::from(calendar.last_modified()?);
+                Some(format!(
+                    "{} {}",
+                    calendar.id(),
+                    format_relative(modified.min(now) - now)
+                ))
+            })
+            .collect::<Vec<_>>()
+            .join(" \u{b7} ");
     }
 
     pub fn current_day(&self) -> u32 {
@@ -136,3 +987,60 @@ impl Context {
         self.now().year()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gray_ramp_boundaries() {
+        assert_eq!(ansi256_index(0, 0, 0), 16);
+        // Closer to the darkest gray (8) than to black
+        assert_eq!(ansi256_index(5, 5, 5), 232);
+        assert_eq!(ansi256_index(12, 12, 12), 232);
+        assert_eq!(ansi256_index(13, 13, 13), 233);
+        assert_eq!(ansi256_index(238, 238, 238), 255);
+        // Closer to white (255) than to the lightest gray (238)
+        assert_eq!(ansi256_index(246, 246, 246), 255);
+        assert_eq!(ansi256_index(247, 247, 247), 231);
+        assert_eq!(ansi256_index(255, 255, 255), 231);
+    }
+
+    #[test]
+    fn cube_boundaries() {
+        // Grays that are in the cube are taken from it
+        assert_eq!(ansi256_index(95, 95, 95), 59);
+        // Levels switch halfway between them, blue keeps the grays away
+        assert_eq!(ansi256_index(47, 0, 255), 21);
+        assert_eq!(ansi256_index(48, 0, 255), 57);
+        assert_eq!(ansi256_index(114, 0, 255), 57);
+        assert_eq!(ansi256_index(115, 0, 255), 93);
+        assert_eq!(ansi256_index(154, 0, 255), 93);
+        assert_eq!(ansi256_index(155, 0, 255), 129);
+        assert_eq!(ansi256_index(255, 128, 0), 208);
+        assert_eq!(ansi256_index(0, 0, 255), 21);
+    }
+
+    #[test]
+    fn adapt_to_support() {
+        let orange = Color::Rgb {
+            r: 255,
+            g: 128,
+            b: 0,
+        };
+        assert_eq!(ColorSupport::Monochrome.adapt(orange), None);
+        assert_eq!(ColorSupport::Truecolor.adapt(orange), Some(orange));
+        assert_eq!(ColorSupport::Ansi256.adapt(orange), Some(Color::Ansi(208)));
+        assert_eq!(ColorSupport::Basic.adapt(orange), Some(Color::Yellow));
+        assert_eq!(
+            ColorSupport::Basic.adapt(Color::Rgb {
+                r: 120,
+                g: 130,
+                b: 125
+            }),
+            Some(Color::LightBlack)
+        );
+        // Named colors are left to the terminal
+        assert_eq!(ColorSupport::Basic.adapt(Color::Blue), Some(Color::Blue));
+    }
+}
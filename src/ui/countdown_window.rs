@@ -0,0 +1,43 @@
+use chrono::Local;
+use std::fmt::Write;
+use unsegen::base::*;
+use unsegen::widget::*;
+
+use crate::agenda::Countdown;
+use crate::ui::Context;
+
+/// A single line counting down to the end of the current or the start of
+/// the next event
+pub struct CountdownWindow<'a> {
+    context: &'a Context,
+}
+
+impl<'a> CountdownWindow<'a> {
+    pub fn new(context: &'a Context) -> Self {
+        CountdownWindow { context }
+    }
+}
+
+impl Widget for CountdownWindow<'_> {
+    fn space_demand(&self) -> Demand2D {
+        Demand2D {
+            width: ColDemand::at_least(10),
+            height: RowDemand::exact(1),
+        }
+    }
+
+    fn draw(&self, mut window: unsegen::base::Window, _hints: RenderingHints) {
+        // Use the actual time, the context's clock only advances on updates
+        let countdown = self.context.agenda().countdown(&Local::now());
+        let mut cursor = Cursor::new(&mut window);
+
+        match countdown {
+            Some(countdown @ Countdown::Ends(..)) => {
+                cursor.apply_style_modifier(self.context.theme().ongoing_header_style);
+                write!(&mut cursor, " {}", countdown).unwrap();
+            }
+            Some(countdown) => write!(&mut cursor, " {}", countdown).unwrap(),
+            None => write!(&mut cursor, " No upcoming events").unwrap(),
+        }
+    }
+}
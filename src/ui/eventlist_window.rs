@@ -1,14 +1,69 @@
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Weekday};
 use std::fmt::{Display, Write};
 use unsegen::base::*;
 use unsegen::input::Scrollable;
 use unsegen::widget::*;
 
-use crate::provider::{Eventlike, Occurrence};
+use crate::config::{AvailabilitySpec, CategorySpec, Density, TitleOverflow};
+use crate::locale::FormatLocalized;
+use crate::provider::{tz, CalendarEvent, Calendarlike};
 use crate::ui::Context;
 
+/// Shown in front of deadlines, i.e. events without duration
+const DEADLINE_ICON: &str = "\u{2691}";
+
+/// Check boxes in front of tasks
+const TODO_OPEN: &str = "\u{2610}";
+const TODO_DONE: &str = "\u{2611}";
+
+/// Longer descriptions of the selected event are cut off
+const MAX_DESCRIPTION_LINES: usize = 10;
+
+struct EventEntry<'a> {
+    event: CalendarEvent<'a>,
+    min_duration: Duration,
+    category: Option<&'a CategorySpec>,
+    /// Days left until the event if it is a deadline
+    days_left: Option<i64>,
+    /// Show the time relative to this point in time as well
+    relative_to: Option<DateTime<Local>>,
+}
+
+impl EventEntry<'_> {
+    /// The time the event is displayed with in front of its summary
+    fn time_label(&self) -> String {
+        let occurrence = self.event.occurrence().clone().with_tz(&Local {});
+        let begin = occurrence.begin();
+        let end = occurrence.display_end(self.min_duration);
+
+        let mut label = if occurrence.is_allday() {
+            "Allday".to_owned()
+        } else if begin == end {
+            format!("{}", begin.time().format("%H:%M"))
+        } else {
+            format!(
+                "{} - {}",
+                begin.time().format("%H:%M"),
+                end.time().format("%H:%M")
+            )
+        };
+
+        if let (Some(now), false) = (self.relative_to, occurrence.is_allday()) {
+            let relative = if begin <= now && now < end {
+                "now".to_owned()
+            } else {
+                format_relative(begin - now)
+            };
+            label += &format!(" ({})", relative);
+        }
+
+        label
+    }
+}
+
 enum Entry<'a> {
-    Event(&'a dyn Eventlike),
+    Event(EventEntry<'a>),
+    Availability(&'a AvailabilitySpec, DateTime<Local>),
     Time(DateTime<Local>),
     Cursor(DateTime<Local>),
 }
@@ -16,35 +71,340 @@ enum Entry<'a> {
 impl Entry<'_> {
     pub fn datetime(&self) -> DateTime<Local> {
         match self {
-            &Entry::Event(evt) => evt.occurrence().clone().with_tz(&Local {}).begin(),
-            &Entry::Cursor(dt) | &Entry::Time(dt) => dt,
+            Entry::Event(entry) => entry.event.occurrence().clone().with_tz(&Local {}).begin(),
+            &Entry::Availability(_, dt) | &Entry::Cursor(dt) | &Entry::Time(dt) => dt,
+        }
+    }
+
+    /// Column at which the text after the time starts, used to indent
+    /// wrapped lines
+    fn text_column(&self) -> usize {
+        match self {
+            Entry::Event(entry) => cell_width(&entry.time_label()) + 2,
+            _ => 0,
         }
     }
 }
 
 impl Display for Entry<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match *self {
-            Self::Event(event) => {
-                let time = match event.occurrence() {
-                    Occurrence::Allday(a, b) => "Allday".to_owned(),
-                    Occurrence::Onetime(timespan) => format!(
-                        "{} - {}",
-                        timespan.begin().time().format("%H:%M"),
-                        timespan.end().time().format("%H:%M")
-                    ),
-                    Occurrence::Instant(dt) => {
-                        format!("{}", dt.time().format("%H:%M"))
-                    }
-                };
-                write!(f, "{}: {}", time, event.summary())
+        match self {
+            Self::Event(entry) => {
+                write!(f, "{}: ", entry.time_label())?;
+                if entry.days_left.is_some() {
+                    write!(f, "{} ", DEADLINE_ICON)?;
+                }
+                if let Some(icon) = entry.category.and_then(|spec| spec.icon.as_ref()) {
+                    write!(f, "{} ", icon)?;
+                }
+                write!(f, "{}", entry.event.summary())?;
+                if let Some(days) = entry.days_left {
+                    write!(f, " ({})", format_days_left(days))?;
+                }
+                Ok(())
             }
+            Self::Availability(block, _) => write!(
+                f,
+                " {} - {}: {}",
+                block.begin.format("%H:%M"),
+                block.end.format("%H:%M"),
+                block.name
+            ),
             Self::Time(dt) => write!(f, " -> {}", dt.time().format("%H:%M")),
             Self::Cursor(dt) => write!(f, " * {}", dt.time().format("%H:%M")),
         }
     }
 }
 
+/// Marks the source calendar of an event or task in front of it
+fn write_calendar_marker(cursor: &mut Cursor, context: &Context, calendar: &dyn Calendarlike) {
+    let saved_style = cursor.get_style_modifier();
+    if let Some(color) = calendar.color().and_then(|color| context.color(color)) {
+        cursor.apply_style_modifier(StyleModifier::new().fg_color(color));
+    }
+    write!(cursor, "\u{258c}").unwrap();
+    cursor.set_style_modifier(saved_style);
+}
+
+/// A line like "▌work ▌home ▌holidays (hidden)" of all calendars in their
+/// color, hidden ones dimmed
+fn write_calendar_legend(cursor: &mut Cursor, context: &Context, width: usize) {
+    let agenda = context.agenda();
+    let mut used = 0;
+    for calendar in agenda.calendar_iter() {
+        let name = match calendar.name() {
+            "" => calendar.id(),
+            name => name,
+        };
+        let hidden = agenda.is_hidden(calendar.id());
+        let label = if hidden {
+            format!("{} (hidden) ", name)
+        } else {
+            format!("{} ", name)
+        };
+
+        // Keep room for the ellipsis
+        let needed = 1 + cell_width(&label);
+        if used + needed > width.saturating_sub(1) {
+            write!(cursor, "\u{2026}").unwrap();
+            break;
+        }
+        used += needed;
+
+        write_calendar_marker(cursor, context, calendar);
+        let saved_style = cursor.get_style_modifier();
+        if hidden {
+            cursor.apply_style_modifier(context.theme.hidden_calendar_style);
+        }
+        write!(cursor, "{}", label).unwrap();
+        cursor.set_style_modifier(saved_style);
+    }
+    cursor.wrap_line();
+}
+
+fn cell_width(text: &str) -> usize {
+    text_width(text).raw_value() as usize
+}
+
+fn char_width(c: char) -> usize {
+    cell_width(c.encode_utf8(&mut [0; 4]))
+}
+
+/// Cuts `text` to at most `width` cells, ending with an ellipsis if anything
+/// was cut off
+fn truncate(text: &str, width: usize) -> String {
+    if cell_width(text) <= width {
+        return text.to_owned();
+    }
+
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        if used + char_width(c) + 1 > width {
+            break;
+        }
+        truncated.push(c);
+        used += char_width(c);
+    }
+    if width > 0 {
+        truncated.push('\u{2026}');
+    }
+    truncated
+}
+
+/// Breaks `text` into lines of at most `width` cells, at spaces where
+/// possible. All but the first line are indented by `indent` cells.
+fn wrap(text: &str, width: usize, indent: usize) -> Vec<String> {
+    let indent = if indent < width / 2 { indent } else { 0 };
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut used = 0;
+    let mut line_is_empty = true;
+
+    let mut break_line = |line: &mut String, used: &mut usize, line_is_empty: &mut bool| {
+        lines.push(std::mem::replace(line, " ".repeat(indent)));
+        *used = indent;
+        *line_is_empty = true;
+    };
+
+    for word in text.split_inclusive(' ') {
+        if !line_is_empty && used + cell_width(word.trim_end()) > width {
+            break_line(&mut line, &mut used, &mut line_is_empty);
+        }
+
+        for c in word.chars() {
+            if used + char_width(c) > width {
+                if c == ' ' {
+                    continue;
+                }
+                // Words longer than a line are split anywhere
+                if !line_is_empty {
+                    break_line(&mut line, &mut used, &mut line_is_empty);
+                }
+            }
+            line.push(c);
+            used += char_width(c);
+            line_is_empty = false;
+        }
+    }
+    lines.push(line);
+
+    lines
+        .into_iter()
+        .map(|line| line.trim_end().to_owned())
+        .collect()
+}
+
+/// E.g. "in 2h" or "3d ago", rounded down to the largest unit
+pub fn format_relative(offset: Duration) -> String {
+    let abs = offset.abs();
+    let amount = if abs.num_days() > 0 {
+        format!("{}d", abs.num_days())
+    } else if abs.num_hours() > 0 {
+        format!("{}h", abs.num_hours())
+    } else {
+        format!("{}m", abs.num_minutes())
+    };
+
+    if offset < Duration::zero() {
+        format!("{} ago", amount)
+    } else {
+        format!("in {}", amount)
+    }
+}
+
+fn format_days_left(days: i64) -> String {
+    match days {
+        0 => "today".to_owned(),
+        1 => "tomorrow".to_owned(),
+        -1 => "yesterday".to_owned(),
+        days if days > 1 => format!("in {} days", days),
+        days => format!("{} days ago", -days),
+    }
+}
+
+fn format_remaining(remaining: Duration) -> String {
+    if remaining.num_hours() > 0 {
+        format!(
+            "{}h {:02}m left",
+            remaining.num_hours(),
+            remaining.num_minutes() % 60
+        )
+    } else {
+        format!("{}m left", remaining.num_minutes().max(1))
+    }
+}
+
+fn format_offset(offset: Duration) -> String {
+    if offset.num_minutes() % 60 == 0 {
+        format!("{}h", offset.num_hours())
+    } else {
+        format!("{}m", offset.num_minutes())
+    }
+}
+
+/// Replaces URLs longer than `max_len` cells by their host, e.g.
+/// "example.com/…"
+fn shorten_urls(text: &str, max_len: usize) -> String {
+    text.split(' ')
+        .map(|word| {
+            // Keep punctuation following the URL
+            let url = word.trim_end_matches(&[',', '.', ';', ':', ')'][..]);
+            let host = url
+                .strip_prefix("https://")
+                .or_else(|| url.strip_prefix("http://"));
+            match host {
+                Some(host) if cell_width(url) > max_len => {
+                    let host = host.split('/').next().unwrap_or(host);
+                    format!("{}/\u{2026}{}", host, &word[url.len()..])
+                }
+                _ => word.to_owned(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Lays out an event description for `width` cells: paragraphs are wrapped,
+/// list items get a bullet and a hanging indent and long URLs are shortened.
+fn format_description(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for paragraph in text.lines().map(str::trim_end) {
+        if paragraph.trim().is_empty() {
+            // Collapse runs of blank lines
+            if lines.last().is_some_and(|line| !line.is_empty()) {
+                lines.push(String::new());
+            }
+            continue;
+        }
+
+        let item = ["- ", "* ", "\u{2022} "]
+            .iter()
+            .find_map(|bullet| paragraph.trim_start().strip_prefix(bullet));
+        match item {
+            Some(item) => lines.extend(wrap(
+                &format!("\u{2022} {}", shorten_urls(item.trim(), width / 2)),
+                width,
+                2,
+            )),
+            None => lines.extend(wrap(&shorten_urls(paragraph, width / 2), width, 0)),
+        }
+    }
+
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+/// The number of events on `date` and when the first one that is not all-day
+/// begins, e.g. "3 events, first at 07:30: Standup"
+fn tomorrow_summary(context: &Context, date: NaiveDate) -> String {
+    let occurrences: Vec<_> = context
+        .agenda()
+        .events_of_day(&date)
+        .map(|event| (event.occurrence().clone().with_tz(&Local {}), event))
+        .collect();
+    let first = occurrences
+        .iter()
+        .filter(|(occurrence, _)| !occurrence.is_allday())
+        .map(|(occurrence, event)| (occurrence.begin(), event))
+        // Events continuing from today do not count as starting tomorrow
+        .filter(|(begin, _)| begin.date_naive() == date)
+        .min_by_key(|(begin, _)| *begin);
+
+    let count = match occurrences.len() {
+        0 => return "Nothing planned".to_owned(),
+        1 => "1 event".to_owned(),
+        n => format!("{} events", n),
+    };
+    match first {
+        Some((begin, event)) => format!(
+            "{}, first at {}: {}",
+            count,
+            begin.format("%H:%M"),
+            event.summary()
+        ),
+        None => format!("{}, all day", count),
+    }
+}
+
+/// A line like "── Saturday, 17 October · W42 ──────" spanning the whole
+/// width, styled differently on weekends
+fn write_day_separator(cursor: &mut Cursor, context: &Context, date: NaiveDate, width: usize) {
+    let mut label = format!(
+        "\u{2500}\u{2500} {} \u{00b7} W{:02} ",
+        date.format_in(&context.locale, "%A, %-d %B"),
+        date.iso_week().week()
+    );
+    let marks = context.day_marks(date);
+    for mark in marks.iter().flatten() {
+        label.push_str(&format!("\u{00b7} {} ", mark));
+    }
+    let fill = width.saturating_sub(cell_width(&label));
+
+    let style = match date.weekday() {
+        Weekday::Sat | Weekday::Sun => context.theme.weekend_separator_style,
+        _ => context.theme.day_separator_style,
+    };
+
+    let saved_style = cursor.get_style_modifier();
+    cursor.apply_style_modifier(style);
+    if marks.is_some() {
+        cursor.apply_style_modifier(context.theme.marked_day_style);
+    }
+    write!(
+        cursor,
+        "{}{}",
+        truncate(&label, width),
+        "\u{2500}".repeat(fill)
+    )
+    .unwrap();
+    cursor.set_style_modifier(saved_style);
+    cursor.wrap_line();
+}
+
 pub struct EventWindow<'a> {
     context: &'a Context,
 }
@@ -53,22 +413,231 @@ impl<'a> EventWindow<'a> {
     pub fn new(context: &'a Context) -> Self {
         EventWindow { context }
     }
+
+    /// The days after the selected one that are listed as well
+    fn lookahead(&self) -> impl Iterator<Item = NaiveDate> {
+        let date = self.context.cursor().date_naive();
+        date.iter_days()
+            .skip(1)
+            .take(self.context.lookahead_days as usize)
+    }
+
+    /// Rows needed to show the events of all listed days without wrapping
+    /// or details, each day with its separator and the cursor line. Counted
+    /// once per frame into `Context::eventlist_rows` as layouts ask for the
+    /// space demand repeatedly.
+    pub fn content_rows(&self) -> usize {
+        let agenda = self.context.agenda();
+        let legend = usize::from(agenda.calendar_iter().nth(1).is_some());
+        let pinned = match agenda.pinned_events().len() {
+            0 => 0,
+            n => n + 2,
+        };
+        let selected_day = agenda
+            .events_of_day(&self.context.cursor().date_naive())
+            .count()
+            + 2;
+        let lookahead: usize = self
+            .lookahead()
+            .map(|date| agenda.events_of_day(&date).count().max(1) + 1)
+            .sum();
+        legend + pinned + selected_day + lookahead
+    }
+
+    /// Lists the events of a day after the selected one by their begin
+    fn write_lookahead_day(&self, cursor: &mut Cursor, date: NaiveDate, width: usize) {
+        write_day_separator(cursor, self.context, date, width);
+
+        let mut events: Vec<_> = self
+            .context
+            .agenda()
+            .events_of_day(&date)
+            .map(|event| (event.occurrence().clone().with_tz(&Local {}), event))
+            .collect();
+        events.sort_by_key(|(occurrence, _)| occurrence.begin());
+
+        if events.is_empty() {
+            let saved_style = cursor.get_style_modifier();
+            cursor.apply_style_modifier(self.context.theme.availability_style);
+            writeln!(cursor, "Nothing planned").unwrap();
+            cursor.set_style_modifier(saved_style);
+        }
+        for (occurrence, event) in events {
+            let time = if occurrence.is_allday() {
+                "all day".to_owned()
+            } else {
+                occurrence.begin().format("%H:%M").to_string()
+            };
+            write_calendar_marker(cursor, self.context, event.calendar);
+            let line = format!("{} {}", time, event.summary());
+            writeln!(cursor, "{}", truncate(&line, width.saturating_sub(1))).unwrap();
+        }
+    }
 }
 
 impl Widget for EventWindow<'_> {
     fn space_demand(&self) -> Demand2D {
         Demand2D {
             width: ColDemand::at_least(10),
-            height: RowDemand::at_least(10),
+            height: RowDemand::at_least(self.context.eventlist_rows.max(10)),
         }
     }
 
     fn draw(&self, mut window: unsegen::base::Window, hints: RenderingHints) {
+        let width = window.get_width().raw_value().max(0) as usize;
+        let mut cursor = Cursor::new(&mut window);
+
+        // Which calendars are shown only matters if there is a choice
+        if self.context.agenda().calendar_iter().nth(1).is_some() {
+            write_calendar_legend(&mut cursor, self.context, width);
+        }
+
+        let pinned = self.context.agenda().pinned_events();
+        if !pinned.is_empty() {
+            let saved_style = cursor.get_style_modifier();
+            cursor.apply_style_modifier(self.context.theme.pinned_header_style);
+            writeln!(&mut cursor, "Pinned").unwrap();
+            cursor.set_style_modifier(saved_style);
+
+            for event in pinned {
+                let begin = event.occurrence().clone().with_tz(&Local {}).begin();
+                write_calendar_marker(&mut cursor, self.context, event.calendar);
+                let line = format!(
+                    "{} {}",
+                    begin.format_in(&self.context.locale, "%a %d %b %H:%M"),
+                    event.summary()
+                );
+                writeln!(&mut cursor, "{}", truncate(&line, width.saturating_sub(1))).unwrap();
+            }
+            writeln!(&mut cursor).unwrap();
+        }
+
+        // List ongoing events first if today is shown
+        if self.context.now().date_naive() == self.context.cursor().date_naive() {
+            let now = self.context.now();
+            let ongoing = self.context.agenda().ongoing_at(now);
+
+            if !ongoing.is_empty() {
+                let saved_style = cursor.get_style_modifier();
+                cursor.apply_style_modifier(self.context.theme.ongoing_header_style);
+                writeln!(&mut cursor, "Happening now").unwrap();
+                cursor.set_style_modifier(saved_style);
+
+                for event in ongoing {
+                    let remaining = event.end().with_timezone(&Local) - *now;
+                    write_calendar_marker(&mut cursor, self.context, event.calendar);
+                    writeln!(
+                        &mut cursor,
+                        "{} ({})",
+                        event.summary(),
+                        format_remaining(remaining)
+                    )
+                    .unwrap();
+                }
+                writeln!(&mut cursor).unwrap();
+            }
+        }
+
+        if let Some(from) = self.context.tomorrow_from {
+            let now = self.context.now();
+            if now.date_naive() == self.context.cursor().date_naive() && now.time() >= from {
+                let saved_style = cursor.get_style_modifier();
+                cursor.apply_style_modifier(self.context.theme.ongoing_header_style);
+                writeln!(&mut cursor, "Tomorrow").unwrap();
+                cursor.set_style_modifier(saved_style);
+
+                let line = tomorrow_summary(self.context, now.date_naive().succ_opt().unwrap());
+                writeln!(&mut cursor, "{}", truncate(&line, width)).unwrap();
+                writeln!(&mut cursor).unwrap();
+            }
+        }
+
+        let todos = self.context.day_todos();
+        if !todos.is_empty() {
+            let saved_style = cursor.get_style_modifier();
+            cursor.apply_style_modifier(self.context.theme.ongoing_header_style);
+            writeln!(&mut cursor, "Tasks").unwrap();
+            cursor.set_style_modifier(saved_style);
+
+            for (i, todo) in todos.iter().enumerate() {
+                let check = if todo.is_done() { TODO_DONE } else { TODO_OPEN };
+                let mut line = format!("{} {} {}", i + 1, check, todo.summary());
+                if let Some(due) = todo.due().filter(|_| todo.has_due_time()) {
+                    let due = due.with_timezone(&Local);
+                    if due.date_naive() == self.context.cursor().date_naive() {
+                        line.push_str(&due.format(" (%H:%M)").to_string());
+                    } else {
+                        line.push_str(&due.format_in(&self.context.locale, " (%a %d %b %H:%M)"));
+                    }
+                }
+                let percent = todo.percent_complete();
+                if !todo.is_done() && percent > 0 {
+                    line.push_str(&format!(" {}%", percent));
+                }
+                write_calendar_marker(&mut cursor, self.context, todo.calendar);
+                writeln!(&mut cursor, "{}", truncate(&line, width.saturating_sub(1))).unwrap();
+            }
+            writeln!(&mut cursor).unwrap();
+        }
+
+        write_day_separator(
+            &mut cursor,
+            self.context,
+            self.context.cursor().date_naive(),
+            width,
+        );
+
+        if let Some(change) = tz::offset_change_on(&Local, self.context.cursor().date_naive()) {
+            let saved_style = cursor.get_style_modifier();
+            cursor.apply_style_modifier(self.context.theme.dst_change_style);
+            writeln!(
+                &mut cursor,
+                "Clocks go {} {}",
+                if change > Duration::zero() {
+                    "forward"
+                } else {
+                    "back"
+                },
+                format_offset(change.abs())
+            )
+            .unwrap();
+            cursor.set_style_modifier(saved_style);
+        }
+
+        let date = self.context.cursor().date_naive();
+
+        // Availability blocks go first so that they precede events at the
+        // same time
         let mut events = self
             .context
-            .agenda()
-            .events_of_day(&self.context.cursor().date_naive())
-            .map(|ev| Entry::Event(ev))
+            .availability
+            .iter()
+            .filter(|block| block.applies_to(date))
+            .filter_map(|block| {
+                Local
+                    .from_local_datetime(&date.and_time(block.begin))
+                    .earliest()
+                    .map(|begin| Entry::Availability(block, begin))
+            })
+            .chain(self.context.agenda().events_of_day(&date).map(|ev| {
+                let category = self.context.category_spec(&ev.categories());
+                let days_left = if ev.occurrence().is_instant() {
+                    Some((date - self.context.now().date_naive()).num_days())
+                } else {
+                    None
+                };
+                Entry::Event(EventEntry {
+                    event: ev,
+                    min_duration: self.context.min_display_duration,
+                    category,
+                    days_left,
+                    relative_to: if self.context.relative_times {
+                        Some(*self.context.now())
+                    } else {
+                        None
+                    },
+                })
+            }))
             .chain([Entry::Cursor(self.context.cursor().clone())])
             .collect::<Vec<Entry>>();
 
@@ -77,33 +646,146 @@ impl Widget for EventWindow<'_> {
             events.push(Entry::Time(self.context.now().clone()))
         }
 
-        events.sort_unstable_by_key(|entry| entry.datetime());
-
-        let mut cursor = Cursor::new(&mut window);
+        // Keep the order stable so that it matches `Context::selected_event`
+        events.sort_by_key(|entry| entry.datetime());
 
         // Only count the real events (no cursor/clock)
+        let selection = self.context.selection_range();
         let mut idx: usize = 0;
         for ev in events {
-            match ev {
-                ev @ Entry::Event(_) => {
+            match &ev {
+                Entry::Event(EventEntry {
+                    event,
+                    category,
+                    days_left,
+                    ..
+                }) => {
+                    // One column is taken by the calendar marker
+                    let text_width = width.saturating_sub(1);
+                    let text = ev.to_string();
+                    let density = self.context.density;
+                    let lines = match (density, self.context.title_overflow) {
+                        (Density::Compact, _) | (_, TitleOverflow::Truncate) => {
+                            vec![truncate(&text, text_width)]
+                        }
+                        (_, TitleOverflow::Wrap) => wrap(&text, text_width, ev.text_column()),
+                    };
+
                     let saved_style = cursor.get_style_modifier();
 
-                    if idx == self.context.eventlist_index {
-                        cursor.apply_style_modifier(StyleModifier::new().invert(true));
+                    if days_left.is_some() {
+                        cursor.apply_style_modifier(self.context.theme.deadline_style);
+                    }
+
+                    if let Some(color) = category
+                        .and_then(|spec| spec.color.as_deref())
+                        .and_then(|color| self.context.color(color))
+                    {
+                        cursor.apply_style_modifier(StyleModifier::new().fg_color(color));
                     }
 
-                    if let Err(err) = write!(&mut cursor, "{}", ev) {
-                        log::warn!("Error while writing event: {}", err);
+                    if selection.contains(&idx) {
+                        cursor.apply_style_modifier(StyleModifier::new().invert(true));
                     }
+                    let entry_style = cursor.get_style_modifier();
+
+                    // Pad every line so that highlighting covers the whole entry
+                    for (i, line) in lines.iter().enumerate() {
+                        cursor.set_style_modifier(saved_style);
+                        if i == 0 {
+                            write_calendar_marker(&mut cursor, self.context, event.calendar);
+                        } else {
+                            write!(&mut cursor, " ").unwrap();
+                        }
 
-                    cursor.fill_and_wrap_line();
+                        cursor.set_style_modifier(entry_style);
+                        let padding = text_width.saturating_sub(cell_width(line));
+                        write!(&mut cursor, "{}{}", line, " ".repeat(padding)).unwrap();
+                        cursor.wrap_line();
+                    }
 
                     cursor.set_style_modifier(saved_style);
+
+                    if density == Density::Detailed {
+                        let mut details = Vec::new();
+                        if let Some(location) = event.location() {
+                            details.push(format!("@ {}", location));
+                        }
+                        if let Some((lat, lon)) = event.geo() {
+                            details.push(format!("\u{2316} {:.5}, {:.5}", lat, lon));
+                        }
+                        details.push(match event.calendar.name() {
+                            "" => event.calendar.id().to_owned(),
+                            name => name.to_owned(),
+                        });
+                        let attendees = event.attendees();
+                        if !attendees.is_empty() {
+                            details.push(format!("with {}", attendees.join(", ")));
+                        }
+                        // To inspect the raw file if the event looks odd
+                        if selection.contains(&idx) {
+                            details.push(event.path().display().to_string());
+                        }
+
+                        let indent = " ".repeat(ev.text_column() + 1);
+                        cursor.apply_style_modifier(self.context.theme.description_style);
+                        for detail in details {
+                            let line = format!("{}{}", indent, detail.replace('\n', " "));
+                            write!(&mut cursor, "{}", truncate(&line, width)).unwrap();
+                            cursor.wrap_line();
+                        }
+                        cursor.set_style_modifier(saved_style);
+                    }
+
+                    let compact = density == Density::Compact;
+                    if let Some(note) = self
+                        .context
+                        .annotations
+                        .get(event.uid())
+                        .filter(|_| !compact)
+                    {
+                        let indent = " ".repeat(ev.text_column() + 1);
+                        let line = format!("{}\u{270e} {}", indent, note.replace('\n', " "));
+                        cursor.apply_style_modifier(self.context.theme.note_style);
+                        write!(&mut cursor, "{}", truncate(&line, width)).unwrap();
+                        cursor.wrap_line();
+                        cursor.set_style_modifier(saved_style);
+                    }
+
+                    if idx == self.context.eventlist_index && !compact {
+                        if let Some(description) = event.description() {
+                            let indent = ev.text_column() + 1;
+                            let mut lines =
+                                format_description(&description, width.saturating_sub(indent));
+                            if lines.len() > MAX_DESCRIPTION_LINES {
+                                lines.truncate(MAX_DESCRIPTION_LINES);
+                                lines.push("\u{2026}".to_owned());
+                            }
+
+                            cursor.apply_style_modifier(self.context.theme.description_style);
+                            for line in lines {
+                                write!(&mut cursor, "{}{}", " ".repeat(indent), line).unwrap();
+                                cursor.wrap_line();
+                            }
+                            cursor.set_style_modifier(saved_style);
+                        }
+                    }
+
                     idx += 1;
                 }
+                Entry::Availability(_, _) => {
+                    let saved_style = cursor.get_style_modifier();
+                    cursor.apply_style_modifier(self.context.theme.availability_style);
+                    writeln!(&mut cursor, "{}", ev).unwrap();
+                    cursor.set_style_modifier(saved_style);
+                }
                 entry => writeln!(&mut cursor, "{}", entry).unwrap(),
             }
         }
+
+        for date in self.lookahead() {
+            self.write_lookahead_day(&mut cursor, date, width);
+        }
     }
 }
 
@@ -128,3 +810,48 @@ impl Scrollable for EventWindowBehaviour<'_> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_to_width() {
+        assert_eq!(truncate("Planning", 8), "Planning");
+        assert_eq!(truncate("Planning", 5), "Plan\u{2026}");
+        assert_eq!(truncate("Planning", 1), "\u{2026}");
+        assert_eq!(truncate("Planning", 0), "");
+    }
+
+    #[test]
+    fn truncate_wide_characters() {
+        // Each character takes two cells
+        assert_eq!(truncate("日本語", 6), "日本語");
+        assert_eq!(truncate("日本語", 5), "日本\u{2026}");
+        assert_eq!(truncate("日本語", 4), "日\u{2026}");
+        assert_eq!(truncate("日本語", 2), "\u{2026}");
+    }
+
+    #[test]
+    fn wrap_at_spaces() {
+        assert_eq!(wrap("aaa bbb ccc", 7, 2), ["aaa bbb", "  ccc"]);
+        // Indents of half the width or more are dropped
+        assert_eq!(wrap("aaa bbb", 4, 2), ["aaa", "bbb"]);
+        assert_eq!(wrap("", 10, 0), [""]);
+    }
+
+    #[test]
+    fn wrap_wide_characters() {
+        assert_eq!(wrap("日本語 テスト", 6, 0), ["日本語", "テスト"]);
+        // Words longer than a line are split between characters
+        assert_eq!(wrap("日本語テスト", 5, 0), ["日本", "語テ", "スト"]);
+    }
+
+    #[test]
+    fn wrap_narrow_widths() {
+        assert_eq!(wrap("ab c", 1, 0), ["a", "b", "c"]);
+        // Characters wider than a line still get one of their own
+        assert_eq!(wrap("日本", 1, 0), ["日", "本"]);
+        assert_eq!(wrap("ab c", 0, 0), ["a", "b", "c"]);
+    }
+}
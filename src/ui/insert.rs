@@ -1,90 +1,205 @@
-use std::str::FromStr;
 use unsegen::input::*;
 
 use nom::{
     branch::alt,
-    bytes::complete::take_until1,
-    character::{
-        complete::{char, space1},
-        is_space,
-    },
+    bytes::complete::{is_not, take_until},
+    character::complete::{char, space0},
     combinator::all_consuming,
     error::*,
-    multi::{many1, separated_list1},
+    multi::many1,
     sequence::{delimited, separated_pair, terminated},
     IResult,
 };
 
-use chrono::Duration;
+use chrono::{DateTime, Local};
+use chrono_tz::Tz;
 
 use super::command::ActionResult;
 use super::context::Context;
 use super::match_action;
 use crate::config::Config;
-use crate::provider::ical::calendar::IcalDateTime;
-use crate::provider::ical::EventBuilder;
+use crate::provider::ical::calendar::IcalDuration;
+use crate::provider::{datetime, tz, NewEvent};
 
-type InsertAction = fn(&mut EventBuilder, &str) -> ActionResult;
+fn provider_error(err: crate::provider::Error) -> Error<String> {
+    ParseError::from_error_kind(err.to_string(), ErrorKind::Fail)
+}
+
+/// The end of an event entered in insert mode, resolved once the begin is
+/// known whatever the order of the keys
+enum End {
+    At(String),
+    After(chrono::Duration),
+}
+
+struct Draft {
+    event: NewEvent<Tz>,
+    end: Option<End>,
+}
+
+type InsertAction = fn(&mut Draft, &str) -> ActionResult;
 const INSERT_ACTIONS: &'static [(&'static str, InsertAction)] = &[
-    ("description", |b, v| {
-        b.set_description(v.to_owned());
+    ("description", |d, v| {
+        d.event.summary = Some(v.to_owned());
         Ok(())
     }),
-    ("begin", |b, v| {
-        // let start = IcalDateTime::from_str(v)
-        //     .or_else(|_| Err(ParseError::from_error_kind(v.into(), ErrorKind::Tag)))?;
-        // b.set_start(start);
+    ("begin", |d, v| {
+        d.event.begin = tz::parse_datetime(v, &d.event.begin).map_err(provider_error)?;
         Ok(())
     }),
-    ("duration", |b, v| {
-        // let duration = Duration::from_str(v)
-        //     .or_else(|_| Err(ParseError::from_error_kind(v.into(), ErrorKind::Tag)))?;
-        // b.set_duration(duration);
+    ("duration", |d, v| {
+        let duration = v.parse::<IcalDuration>().map_err(provider_error)?;
+        d.end = Some(End::After(duration.into()));
         Ok(())
     }),
-    ("end", |b, v| {
-        // let end = IcalDateTime::from_str(v)
-        //     .or_else(|_| Err(ParseError::from_error_kind(v.into(), ErrorKind::Tag)))?;
-        // b.set_end(end);
+    ("end", |d, v| {
+        d.end = Some(End::At(v.to_owned()));
         Ok(())
     }),
-    ("location", |b, v| {
-        b.set_location(v.to_owned());
+    ("location", |d, v| {
+        d.event.location = Some(v.to_owned());
         Ok(())
     }),
 ];
 
+fn parse_key_value(key_value: &str) -> IResult<&str, ((&str, &InsertAction), &str)> {
+    terminated(
+        separated_pair(
+            match_action(INSERT_ACTIONS),
+            char(':'),
+            alt((
+                delimited(char('"'), take_until("\""), char('"')),
+                is_not(" "),
+            )),
+        ),
+        space0,
+    )(key_value)
+}
+
+/// Parses a line of `key:value` pairs into a new event beginning at
+/// `reference` unless specified otherwise.
+fn parse_new_event(line: &str, reference: DateTime<Tz>) -> Result<NewEvent<Tz>, Error<String>> {
+    let (_, found_key_values) = all_consuming(many1(parse_key_value))(line.trim())
+        .map_err(|_| ParseError::from_error_kind(line.into(), ErrorKind::Many1))?;
+
+    let mut draft = Draft {
+        event: NewEvent::new(reference),
+        end: None,
+    };
+    for ((_, action), value) in found_key_values {
+        action(&mut draft, value)?;
+    }
+
+    let mut event = draft.event;
+    event.end = match draft.end {
+        Some(End::At(end)) => Some(tz::parse_datetime(&end, &event.begin).map_err(provider_error)?),
+        Some(End::After(duration)) => {
+            Some(event.begin.checked_add_signed(duration).ok_or_else(|| {
+                ParseError::from_error_kind(
+                    "the event would last too long".to_owned(),
+                    ErrorKind::Fail,
+                )
+            })?)
+        }
+        None => None,
+    };
+    Ok(event)
+}
+
+fn reference_datetime(context: &Context) -> Option<DateTime<Tz>> {
+    let calendar_tz = *context.agenda().calendar_iter().next()?.tz();
+    Some(context.cursor().with_timezone(&calendar_tz))
+}
+
+fn describe(dt: &DateTime<Tz>) -> String {
+    let local = dt.with_timezone(&Local);
+    if local.naive_local() == dt.naive_local() {
+        format!("{}", dt.format("%Y-%m-%d %H:%M %Z"))
+    } else {
+        format!(
+            "{} ({} local)",
+            dt.format("%Y-%m-%d %H:%M %Z"),
+            local.format("%H:%M")
+        )
+    }
+}
+
+/// Describes the event currently entered in insert mode, converting its
+/// begin and end into local time if they are specified in another timezone
+pub fn insert_preview(context: &Context) -> Option<String> {
+    let line = context.input_sink(super::Mode::Insert).active_line();
+    let event = parse_new_event(line, reference_datetime(context)?).ok()?;
+
+    Some(match &event.end {
+        Some(end) => format!("{} - {}", describe(&event.begin), describe(end)),
+        None => describe(&event.begin),
+    })
+}
+
+fn parse_quick_add(context: &Context, line: &str) -> Result<NewEvent<Tz>, Error<String>> {
+    let reference = reference_datetime(context).ok_or_else(|| {
+        ParseError::from_error_kind("no calendar available".to_owned(), ErrorKind::Fail)
+    })?;
+    datetime::parse_event(line, context.now().date_naive(), &reference).map_err(provider_error)
+}
+
+/// Describes the event currently entered in natural language the way it is
+/// understood
+pub fn quick_add_preview(context: &Context) -> Option<String> {
+    let line = context.input_sink(super::Mode::QuickAdd).active_line();
+    let event = parse_quick_add(context, line).ok()?;
+
+    let mut preview = format!("{}: {}", event.summary.as_deref()?, describe(&event.begin));
+    if let Some(end) = &event.end {
+        preview.push_str(&format!(" - {}", describe(end)));
+    }
+    if let Some(rrule) = &event.rrule {
+        preview.push_str(&format!(" ({})", rrule));
+    }
+    Some(preview)
+}
+
+/// Adds the event entered in natural language after + on enter
+pub struct QuickAddBehaviour<'a>(pub &'a mut Context);
+
+impl Behavior for QuickAddBehaviour<'_> {
+    fn input(self, input: Input) -> Option<Input> {
+        if let Event::Key(Key::Char('\n')) = input.event {
+            let line = self
+                .0
+                .input_sink_mut(super::Mode::QuickAdd)
+                .finish_line()
+                .to_owned();
+
+            let result = parse_quick_add(self.0, &line)
+                .and_then(|event| self.0.add_event(event).map_err(provider_error));
+            if let Err(e) = result {
+                self.0.last_error_message = Some(format!("{}", e));
+            }
+            None
+        } else {
+            Some(input)
+        }
+    }
+}
+
 pub struct InsertParser<'a> {
     context: &'a mut Context,
     config: &'a Config,
-    builder: EventBuilder,
 }
 
 impl<'a> InsertParser<'a> {
-    pub fn new(context: &'a mut Context, config: &'a Config, builder: EventBuilder) -> Self {
-        InsertParser {
-            context,
-            config,
-            builder,
-        }
+    pub fn new(context: &'a mut Context, config: &'a Config) -> Self {
+        InsertParser { context, config }
     }
 
-    fn parse_key_value(key_value: &str) -> IResult<&str, ((&str, &InsertAction), &str)> {
-        separated_pair(
-            match_action(INSERT_ACTIONS),
-            char(':'),
-            alt((
-                terminated(take_until1(" "), space1),
-                delimited(char('"'), take_until1("\""), char('"')),
-            )),
-        )(key_value)
-    }
-
-    fn parse_line(&mut self, line: &str) -> Result<(), Error<String>> {
-        let (rest, found_key_values) = many1(Self::parse_key_value)(line)
-            .or_else(|_| Err(ParseError::from_error_kind(line.into(), ErrorKind::Many1)))?;
+    fn insert_line(&mut self, line: &str) -> Result<(), Error<String>> {
+        let reference = reference_datetime(self.context).ok_or_else(|| {
+            ParseError::from_error_kind("no calendar available".to_owned(), ErrorKind::Fail)
+        })?;
+        let event = parse_new_event(line, reference)?;
 
-        Ok(())
+        self.context.add_event(event).map_err(provider_error)
     }
 }
 
@@ -99,12 +214,8 @@ impl Behavior for InsertParser<'_> {
                         .finish_line()
                         .to_owned();
 
-                    let res = self.parse_line(&line);
-                    if let Err(e) = res {
+                    if let Err(e) = self.insert_line(&line) {
                         self.context.last_error_message = Some(format!("{}", e));
-                    } else {
-                        let event = self.builder.finish();
-                        // actually write & save event
                     }
 
                     None
@@ -0,0 +1,292 @@
+use std::fmt::Write;
+use std::io::Write as _;
+use std::process::{Command, Stdio};
+use unsegen::base::*;
+use unsegen::input::*;
+use unsegen::widget::*;
+
+use super::context::{Context, Mode};
+use crate::link::{self, open_url};
+use crate::provider::ical::itip;
+use crate::provider::{CalendarEvent, NewEvent};
+
+/// Programs that take text to copy to the clipboard on stdin, tried in order
+const CLIPBOARD_COMMANDS: &[&[&str]] = &[
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+    &["pbcopy"],
+];
+
+/// Something that can be done with the selected event, see `menu_entries`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuEntry {
+    Edit,
+    Delete,
+    Duplicate,
+    Move,
+    CopyIcs,
+    OpenUrl,
+    OpenMap,
+    Rsvp,
+    AddAlarm,
+}
+
+impl MenuEntry {
+    pub fn key(self) -> char {
+        match self {
+            MenuEntry::Edit => 'e',
+            MenuEntry::Delete => 'd',
+            MenuEntry::Duplicate => 'c',
+            MenuEntry::Move => 'm',
+            MenuEntry::CopyIcs => 'y',
+            MenuEntry::OpenUrl => 'o',
+            MenuEntry::OpenMap => 'g',
+            MenuEntry::Rsvp => 'r',
+            MenuEntry::AddAlarm => 'a',
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MenuEntry::Edit => "Edit in $EDITOR",
+            MenuEntry::Delete => "Delete",
+            MenuEntry::Duplicate => "Duplicate",
+            MenuEntry::Move => "Move to calendar...",
+            MenuEntry::CopyIcs => "Copy as iCalendar",
+            MenuEntry::OpenUrl => "Open URL",
+            MenuEntry::OpenMap => "Open location in map",
+            MenuEntry::Rsvp => "Answer invitation...",
+            MenuEntry::AddAlarm => "Add alarm...",
+        }
+    }
+}
+
+/// The URL of `event` or, if it has none, the first link in its location or
+/// description
+fn event_url(event: &CalendarEvent) -> Option<String> {
+    event.url().or_else(|| {
+        let text = vec![event.location(), event.description()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+        text.split_whitespace()
+            .find(|word| word.starts_with("https://") || word.starts_with("http://"))
+            .map(|url| {
+                url.trim_end_matches(&[',', '.', ';', ')', '>'][..])
+                    .to_owned()
+            })
+    })
+}
+
+fn is_invited(context: &Context, event: &CalendarEvent) -> bool {
+    itip::Message::parse(&event.raw_ical())
+        .is_ok_and(|message| message.find_attendee(&context.addresses).is_some())
+}
+
+/// The actions applicable to the selected event, none if no event is selected
+pub fn menu_entries(context: &Context) -> Vec<MenuEntry> {
+    let event = match context.selected_event() {
+        Some(event) => event,
+        None => return Vec::new(),
+    };
+
+    let mut entries = vec![MenuEntry::Edit, MenuEntry::Delete];
+    // All-day events cannot be created from a NewEvent
+    if !event.occurrence().is_allday() {
+        entries.push(MenuEntry::Duplicate);
+    }
+    if context.agenda().calendar_iter().nth(1).is_some() {
+        entries.push(MenuEntry::Move);
+    }
+    entries.push(MenuEntry::CopyIcs);
+    if event_url(&event).is_some() {
+        entries.push(MenuEntry::OpenUrl);
+    }
+    if event.geo().is_some() {
+        entries.push(MenuEntry::OpenMap);
+    }
+    if is_invited(context, &event) {
+        entries.push(MenuEntry::Rsvp);
+    }
+    entries.push(MenuEntry::AddAlarm);
+    entries
+}
+
+fn copy_to_clipboard(text: &str) -> Result<&'static str, String> {
+    for command in CLIPBOARD_COMMANDS {
+        let child = Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(_) => continue,
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(text.as_bytes())
+                .map_err(|e| format!("{} failed: {}", command[0], e))?;
+        }
+        return match child.wait() {
+            Ok(status) if status.success() => Ok(command[0]),
+            Ok(status) => Err(format!("{} failed ({})", command[0], status)),
+            Err(e) => Err(format!("{} failed: {}", command[0], e)),
+        };
+    }
+    Err("no clipboard program found (wl-copy, xclip, xsel or pbcopy)".to_owned())
+}
+
+/// Lists the actions applicable to the selected event
+pub struct MenuWindow<'a> {
+    context: &'a Context,
+}
+
+impl<'a> MenuWindow<'a> {
+    pub fn new(context: &'a Context) -> Self {
+        MenuWindow { context }
+    }
+}
+
+impl Widget for MenuWindow<'_> {
+    fn space_demand(&self) -> Demand2D {
+        Demand2D {
+            width: ColDemand::at_least(10),
+            height: RowDemand::exact(menu_entries(self.context).len() + 1),
+        }
+    }
+
+    fn draw(&self, mut window: unsegen::base::Window, _hints: RenderingHints) {
+        let theme = self.context.theme();
+        let summary = self
+            .context
+            .selected_event()
+            .map(|event| event.summary().to_owned())
+            .unwrap_or_default();
+
+        let mut cursor = Cursor::new(&mut window);
+
+        let saved_style = cursor.get_style_modifier();
+        cursor.apply_style_modifier(theme.month_header_style);
+        write!(&mut cursor, "Actions for '{}'", summary).unwrap();
+        cursor.fill_and_wrap_line();
+        cursor.set_style_modifier(saved_style);
+
+        for (i, entry) in menu_entries(self.context).into_iter().enumerate() {
+            let saved_style = cursor.get_style_modifier();
+            if i == self.context.menu_index {
+                cursor.apply_style_modifier(StyleModifier::new().invert(true));
+            }
+            write!(&mut cursor, " {}  {}", entry.key(), entry.label()).unwrap();
+            cursor.fill_and_wrap_line();
+            cursor.set_style_modifier(saved_style);
+        }
+    }
+}
+
+pub struct MenuBehaviour<'a>(pub &'a mut Context);
+
+impl MenuBehaviour<'_> {
+    fn select(&mut self, forwards: bool) {
+        let num_entries = menu_entries(self.0).len();
+        let index = &mut self.0.menu_index;
+
+        if forwards && *index + 1 < num_entries {
+            *index += 1;
+        } else if !forwards && *index > 0 {
+            *index -= 1;
+        }
+    }
+
+    /// Continues in command mode with `command` entered, for actions that
+    /// need an argument
+    fn prompt(&mut self, command: &str) {
+        self.0.mode = Mode::Command;
+        let prompt = self.0.input_sink_mut(Mode::Command);
+        prompt.line.set(command);
+        prompt.line.move_cursor_to_end_of_line();
+    }
+
+    fn run(&mut self, entry: MenuEntry) {
+        self.0.mode = Mode::Normal;
+        let event = match self.0.selected_event() {
+            Some(event) => event,
+            None => {
+                self.0.last_error_message = Some("No event selected".to_owned());
+                return;
+            }
+        };
+
+        let message = match entry {
+            MenuEntry::Edit => {
+                self.0.pending_edit = Some(event.uid().to_owned());
+                return;
+            }
+            MenuEntry::Delete => return self.0.request_delete(),
+            MenuEntry::Duplicate => {
+                let calendar = event.calendar.id().to_owned();
+                let mut copy = NewEvent::new(event.begin());
+                copy.end = Some(event.end());
+                copy.summary = Some(event.summary().to_owned());
+                copy.location = event.location();
+                match self.0.agenda_mut().new_event_in(&calendar, copy) {
+                    Ok(_) => "Duplicated the event".to_owned(),
+                    Err(e) => e.to_string(),
+                }
+            }
+            MenuEntry::Move => return self.prompt("move "),
+            MenuEntry::CopyIcs => match copy_to_clipboard(&event.raw_ical()) {
+                Ok(program) => format!("Copied the event with {}", program),
+                Err(e) => e,
+            },
+            MenuEntry::OpenUrl => match event_url(&event) {
+                Some(url) => match open_url(&url) {
+                    Ok(()) => format!("Opened {}", url),
+                    Err(e) => e,
+                },
+                None => "The event has no URL".to_owned(),
+            },
+            MenuEntry::OpenMap => match event.geo() {
+                Some(geo) => {
+                    let url = link::map_link(&self.0.map_url, geo);
+                    match open_url(&url) {
+                        Ok(()) => format!("Opened {}", url),
+                        Err(e) => e,
+                    }
+                }
+                None => "The event has no position".to_owned(),
+            },
+            MenuEntry::Rsvp => return self.prompt("rsvp "),
+            MenuEntry::AddAlarm => return self.prompt("alarm add -PT15M"),
+        };
+        self.0.last_error_message = Some(message);
+    }
+}
+
+impl Behavior for MenuBehaviour<'_> {
+    fn input(mut self, input: Input) -> Option<Input> {
+        if let Event::Key(key) = input.event {
+            let entries = menu_entries(self.0);
+            match key {
+                Key::Char('\n') => {
+                    if let Some(entry) = entries.get(self.0.menu_index) {
+                        self.run(*entry);
+                    }
+                }
+                Key::Down | Key::Char('j') => self.select(true),
+                Key::Up | Key::Char('k') => self.select(false),
+                Key::Char(c) => match entries.into_iter().find(|entry| entry.key() == c) {
+                    Some(entry) => self.run(entry),
+                    None => return Some(input),
+                },
+                _ => return Some(input),
+            }
+            None
+        } else {
+            Some(input)
+        }
+    }
+}
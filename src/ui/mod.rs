@@ -1,13 +1,24 @@
 pub mod app;
 pub mod calendar_window;
 pub mod command;
+pub mod console_window;
 pub mod context;
+pub mod countdown_window;
 pub mod eventlist_window;
 pub mod insert;
+pub mod menu;
+pub mod picker;
+pub mod plain;
+pub mod search;
 
 pub use app::*;
 pub use calendar_window::*;
 pub use command::*;
+pub use console_window::*;
 pub use context::*;
+pub use countdown_window::*;
 pub use eventlist_window::*;
 pub use insert::*;
+pub use menu::*;
+pub use picker::*;
+pub use search::*;
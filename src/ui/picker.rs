@@ -0,0 +1,191 @@
+use chrono::{Local, NaiveTime};
+use std::fmt::Write;
+use std::ops::Bound;
+use unsegen::base::*;
+use unsegen::input::*;
+use unsegen::widget::*;
+
+use super::context::{Context, Mode};
+use crate::locale::FormatLocalized;
+use crate::provider::{CalendarEvent, EventFilter};
+
+/// Scores how well `pattern` matches `text` if all of its characters occur in
+/// `text` in order, ignoring case. Consecutive characters and characters at
+/// the start of words score higher, gaps lower.
+fn fuzzy_score(pattern: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut last_match: Option<usize> = None;
+
+    for p in pattern
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+    {
+        let found = pos + text[pos..].iter().position(|&c| c == p)?;
+
+        if last_match.is_some_and(|last| last + 1 == found) {
+            score += 5;
+        } else {
+            score -= (found - pos) as i64;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+
+        last_match = Some(found);
+        pos = found + 1;
+    }
+
+    Some(score)
+}
+
+/// Upcoming events or those of the picker's days whose summary or location
+/// matches the picker's input, best matches first
+pub fn picker_matches(context: &Context) -> Vec<CalendarEvent<'_>> {
+    let pattern = context.input_sink(Mode::Picker).active_line();
+    let today = context.now().date_naive().and_time(NaiveTime::MIN);
+
+    let events: Box<dyn Iterator<Item = CalendarEvent>> = match &context.picker_days {
+        Some(days) => Box::new(context.agenda().events_of_days(days)),
+        None => Box::new(
+            context
+                .agenda()
+                .filter_events(EventFilter::default().from_datetime(Bound::Included(today))),
+        ),
+    };
+    let mut matches: Vec<_> = events
+        .filter_map(|event| {
+            let summary_score = fuzzy_score(pattern, event.summary());
+            let location_score = event
+                .location()
+                .and_then(|location| fuzzy_score(pattern, &location));
+            let score = summary_score.max(location_score)?;
+            Some((score, event))
+        })
+        .collect();
+
+    matches.sort_by_key(|(score, event)| (-score, event.begin()));
+    matches.into_iter().map(|(_, event)| event).collect()
+}
+
+/// Overlay listing the events matching the picker's input
+pub struct PickerWindow<'a> {
+    context: &'a Context,
+}
+
+impl<'a> PickerWindow<'a> {
+    pub fn new(context: &'a Context) -> Self {
+        PickerWindow { context }
+    }
+}
+
+impl Widget for PickerWindow<'_> {
+    fn space_demand(&self) -> Demand2D {
+        Demand2D {
+            width: ColDemand::at_least(10),
+            height: RowDemand::at_least(1),
+        }
+    }
+
+    fn draw(&self, mut window: unsegen::base::Window, _hints: RenderingHints) {
+        let theme = self.context.theme();
+        let matches = picker_matches(self.context);
+        let height = window.get_height().raw_value().max(1) as usize;
+
+        let mut cursor = Cursor::new(&mut window);
+
+        let saved_style = cursor.get_style_modifier();
+        cursor.apply_style_modifier(theme.month_header_style);
+        match &self.context.picker_days {
+            Some(days) => write!(
+                &mut cursor,
+                "Go to event from {} to {} ({})",
+                days.start().format("%Y-%m-%d"),
+                days.end().format("%Y-%m-%d"),
+                matches.len()
+            ),
+            None => write!(&mut cursor, "Go to event ({})", matches.len()),
+        }
+        .unwrap();
+        cursor.fill_and_wrap_line();
+        cursor.set_style_modifier(saved_style);
+
+        // Scroll so that the selected match stays visible
+        let num_shown = height - 1;
+        let first = (self.context.picker_index + 1).saturating_sub(num_shown);
+
+        for (i, event) in matches.iter().enumerate().skip(first).take(num_shown) {
+            let begin = event.occurrence().clone().with_tz(&Local {}).begin();
+
+            let saved_style = cursor.get_style_modifier();
+            if i == self.context.picker_index {
+                cursor.apply_style_modifier(StyleModifier::new().invert(true));
+            }
+            write!(
+                &mut cursor,
+                "{}  {}",
+                begin.format_in(&self.context.locale, "%a %d %b %Y %H:%M"),
+                event.summary()
+            )
+            .unwrap();
+            if let Some(location) = event.location() {
+                write!(&mut cursor, " @ {}", location).unwrap();
+            }
+            cursor.fill_and_wrap_line();
+            cursor.set_style_modifier(saved_style);
+        }
+    }
+}
+
+pub struct PickerBehaviour<'a>(pub &'a mut Context);
+
+impl PickerBehaviour<'_> {
+    fn select(&mut self, forwards: bool) {
+        let num_matches = picker_matches(self.0).len();
+        let index = &mut self.0.picker_index;
+
+        if forwards && *index + 1 < num_matches {
+            *index += 1;
+        } else if !forwards && *index > 0 {
+            *index -= 1;
+        }
+    }
+
+    /// Moves the cursor to the chosen event and highlights it in the event list
+    fn jump(&mut self) {
+        let target = picker_matches(self.0)
+            .get(self.0.picker_index)
+            .map(|event| {
+                (
+                    event.uid().to_owned(),
+                    event.occurrence().clone().with_tz(&Local {}).begin(),
+                )
+            });
+
+        self.0.input_sink_mut(Mode::Picker).finish_line();
+        self.0.mode = Mode::Normal;
+
+        match target {
+            Some((uid, begin)) => self.0.jump_to_event(&uid, begin),
+            None => self.0.last_error_message = Some("No matching event".to_owned()),
+        }
+    }
+}
+
+impl Behavior for PickerBehaviour<'_> {
+    fn input(mut self, input: Input) -> Option<Input> {
+        if let Event::Key(key) = input.event {
+            match key {
+                Key::Char('\n') => self.jump(),
+                Key::Down | Key::Ctrl('n') => self.select(true),
+                Key::Up | Key::Ctrl('p') => self.select(false),
+                _ => return Some(input),
+            }
+            None
+        } else {
+            Some(input)
+        }
+    }
+}
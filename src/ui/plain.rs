@@ -0,0 +1,180 @@
+use chrono::{Duration, Local};
+use std::io::{self, BufRead, Write};
+
+use crate::agenda::Agenda;
+use crate::config::Config;
+use crate::locale::FormatLocalized;
+use crate::provider::CalendarEvent;
+
+use super::{CommandParser, Context};
+
+const HELP: &str = "Commands: h/l previous/next day, k/j previous/next week, \
+t today, [/] previous/next event, a list all events of the day, \
+u undo, :<command> run a command, q quit. An empty line repeats the selection.";
+
+fn describe_event(context: &Context, event: &CalendarEvent) -> String {
+    let occurrence = event.occurrence().clone().with_tz(&Local {});
+    let begin = occurrence.begin();
+    let end = occurrence.display_end(context.min_display_duration);
+
+    let mut description = if occurrence.is_allday() {
+        "all day".to_owned()
+    } else if occurrence.is_instant() {
+        format!("deadline at {}", begin.format("%H:%M"))
+    } else if begin == end {
+        format!("at {}", begin.format("%H:%M"))
+    } else {
+        format!("{} to {}", begin.format("%H:%M"), end.format("%H:%M"))
+    };
+
+    description += &format!(", {}, calendar {}", event.summary(), event.calendar.name());
+
+    let categories = event.categories();
+    if !categories.is_empty() {
+        description += &format!(", categories {}", categories.join(" and "));
+    }
+
+    if let Some(note) = context.annotations.get(event.uid()) {
+        description += &format!(", note: {}", note);
+    }
+
+    description
+}
+
+/// A line based interface without colors or cursor movement which announces
+/// every change as a full sentence, e.g. for screen readers
+struct PlainUi<'a> {
+    config: &'a Config,
+    context: Context,
+    last_message: Option<String>,
+}
+
+impl<'a> PlainUi<'a> {
+    fn announce_day(&self, out: &mut impl Write) -> io::Result<()> {
+        let events = self.context.day_events();
+        let day = self
+            .context
+            .cursor()
+            .format_in(&self.context.locale, "%A, %-d %B %Y");
+
+        match events.len() {
+            0 => writeln!(out, "{}, no events.", day),
+            1 => writeln!(out, "{}, 1 event.", day),
+            n => writeln!(out, "{}, {} events.", day, n),
+        }?;
+
+        self.announce_selection(out)
+    }
+
+    fn announce_selection(&self, out: &mut impl Write) -> io::Result<()> {
+        let events = self.context.day_events();
+        if let Some(event) = events.get(self.context.eventlist_index) {
+            writeln!(
+                out,
+                "Event {} of {}: {}.",
+                self.context.eventlist_index + 1,
+                events.len(),
+                describe_event(&self.context, event)
+            )?;
+        }
+        Ok(())
+    }
+
+    fn list_events(&self, out: &mut impl Write) -> io::Result<()> {
+        let events = self.context.day_events();
+        for (i, event) in events.iter().enumerate() {
+            writeln!(out, "{}. {}.", i + 1, describe_event(&self.context, event))?;
+        }
+        Ok(())
+    }
+
+    fn move_cursor(&mut self, by: Duration, out: &mut impl Write) -> io::Result<()> {
+        self.context.cursor += by;
+        self.context.eventlist_index = 0;
+        self.announce_day(out)
+    }
+
+    fn select_event(&mut self, forwards: bool, out: &mut impl Write) -> io::Result<()> {
+        let num_events = self.context.day_events().len();
+        let index = self.context.eventlist_index;
+
+        if forwards && index + 1 < num_events {
+            self.context.eventlist_index += 1;
+        } else if !forwards && index > 0 {
+            self.context.eventlist_index -= 1;
+        } else {
+            return writeln!(out, "No more events on this day.");
+        }
+        self.announce_selection(out)
+    }
+
+    /// Returns false if the user wants to quit
+    fn handle_line(&mut self, line: &str, out: &mut impl Write) -> io::Result<bool> {
+        self.context.update();
+
+        match line.trim() {
+            "q" | "quit" => return Ok(false),
+            "" => self.announce_day(out)?,
+            "?" | "help" => writeln!(out, "{}", HELP)?,
+            "h" => self.move_cursor(Duration::days(-1), out)?,
+            "l" => self.move_cursor(Duration::days(1), out)?,
+            "k" => self.move_cursor(Duration::weeks(-1), out)?,
+            "j" => self.move_cursor(Duration::weeks(1), out)?,
+            "t" => {
+                self.context.select_today();
+                self.context.eventlist_index = 0;
+                self.announce_day(out)?;
+            }
+            "[" => self.select_event(false, out)?,
+            "]" => self.select_event(true, out)?,
+            "a" => self.list_events(out)?,
+            "u" => self.context.undo(),
+            line => match line.strip_prefix(':') {
+                Some(command) => {
+                    let day = self.context.cursor().date_naive();
+                    let result =
+                        CommandParser::new(&mut self.context, self.config).run_command(command);
+                    if let Err(err) = result {
+                        writeln!(out, "Error: {}", err)?;
+                    } else if self.context.cursor().date_naive() != day {
+                        self.context.eventlist_index = 0;
+                        self.announce_day(out)?;
+                    }
+                }
+                None => writeln!(out, "Unknown command '{}', type ? for help.", line)?,
+            },
+        }
+
+        // Messages are shown in the status bar of the TUI
+        if self.context.last_error_message != self.last_message {
+            self.last_message = self.context.last_error_message.clone();
+            if let Some(message) = &self.last_message {
+                writeln!(out, "{}", message)?;
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+pub fn run_plain(config: &Config, agenda: Agenda) -> Result<(), Box<dyn std::error::Error>> {
+    let mut ui = PlainUi {
+        config,
+        context: Context::from_config(agenda, config),
+        last_message: None,
+    };
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    writeln!(out, "Type ? for help.")?;
+    ui.handle_line("", &mut out)?;
+
+    for line in io::stdin().lock().lines() {
+        if !ui.handle_line(&line?, &mut out)? {
+            break;
+        }
+    }
+
+    Ok(())
+}
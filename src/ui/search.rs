@@ -0,0 +1,24 @@
+use unsegen::input::*;
+
+use super::context::{Context, Mode};
+
+/// Runs the search typed after / on enter
+pub struct SearchBehaviour<'a>(pub &'a mut Context);
+
+impl Behavior for SearchBehaviour<'_> {
+    fn input(self, input: Input) -> Option<Input> {
+        if let Event::Key(Key::Char('\n')) = input.event {
+            let query = self.0.input_sink_mut(Mode::Search).finish_line().to_owned();
+            self.0.mode = Mode::Normal;
+            if query.trim().is_empty() {
+                // Like in vi, an empty query repeats the last one
+                self.0.search_next(true);
+            } else {
+                self.0.search(query.trim());
+            }
+            None
+        } else {
+            Some(input)
+        }
+    }
+}